@@ -0,0 +1,336 @@
+use std::{
+    io::Read,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tauri::{AppHandle, Emitter, Manager};
+use tiny_http::{Header, Response, Server};
+
+use crate::config::ConfigState;
+use crate::ordering::SortMode;
+use crate::workspace::{Workspace, WorkspaceState};
+
+// 通过接口修改笔记、或笔记在应用之外被修改后发给前端的事件，载荷为笔记路径
+pub const NOTES_CHANGED_EVENT: &str = "notes-changed";
+
+// 请求体的大小上限
+const MAX_BODY_SIZE: u64 = 16 * 1024 * 1024;
+
+// 处理请求的线程数，慢请求只占用其中一个
+const WORKER_COUNT: usize = 4;
+
+// 接口收到的请求
+#[derive(Debug, Clone, Default)]
+pub struct ApiRequest {
+    pub method: String,
+    // 路径和查询参数，如 "/api/search?q=..."
+    pub url: String,
+    pub authorization: Option<String>,
+    pub body: String,
+}
+
+// 接口的响应
+#[derive(Debug, Clone)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: Value,
+    // 被修改的笔记路径，需要通知前端
+    pub changed: Option<String>,
+}
+
+impl ApiResponse {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body, changed: None }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self { status, body: json!({ "error": message.into() }), changed: None }
+    }
+}
+
+// 处理请求时的错误，工作区返回的错误默认是内部错误
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        Self { status: 500, message }
+    }
+}
+
+fn bad_request(message: String) -> ApiError {
+    ApiError { status: 400, message }
+}
+
+// 笔记操作失败时区分原因：笔记或节点不存在时是 404，
+// 笔记本身无法读取是内部错误，其余是请求的内容不能应用到笔记上
+fn note_failure(workspace: &Workspace, path: &str, node_ids: &[&str], message: String) -> ApiError {
+    match workspace.resolve(path) {
+        Ok(note_path) if note_path.is_file() => {}
+        Ok(_) => return ApiError { status: 404, message: format!("笔记不存在: {}", path) },
+        Err(e) => return bad_request(e),
+    }
+    if workspace.read_note(path).is_err() {
+        return message.into();
+    }
+    if let Some(node_id) = node_ids.iter().find(|node_id| workspace.get_node(path, node_id).is_err()) {
+        return ApiError { status: 404, message: format!("节点不存在: {}", node_id) };
+    }
+    bad_request(message)
+}
+
+// 添加节点的请求体
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddNodeBody {
+    parent_id: String,
+    data: Map<String, Value>,
+    index: Option<usize>,
+}
+
+// 移动节点的请求体
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MoveNodeBody {
+    parent_id: String,
+    index: Option<usize>,
+}
+
+// 解码 URL 中的百分号编码，查询参数中的 "+" 表示空格
+fn percent_decode(text: &str, plus_as_space: bool) -> Result<String, String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes.get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| format!("无效的 URL 编码: {}", text))?;
+                decoded.push(hex);
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| format!("无效的 URL 编码: {}", text))
+}
+
+// 逐字节比较令牌，耗时与不同字符的位置无关
+fn token_matches(authorization: Option<&str>, token: &str) -> bool {
+    let Some(given) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    let (given, token) = (given.trim().as_bytes(), token.as_bytes());
+    !token.is_empty()
+        && given.len() == token.len()
+        && given.iter().zip(token).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(body: &str) -> Result<T, ApiError> {
+    serde_json::from_str(body).map_err(|e| bad_request(format!("请求体格式错误: {}", e)))
+}
+
+fn to_json(value: impl serde::Serialize) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("无法序列化响应: {}", e))
+}
+
+// 处理一个请求：校验令牌后在当前工作区上执行，与 Tauri 命令共用同一个工作区状态
+pub fn handle_request(state: &WorkspaceState, token: &str, request: &ApiRequest) -> ApiResponse {
+    if !token_matches(request.authorization.as_deref(), token) {
+        return ApiResponse::error(401, "缺少或错误的访问令牌");
+    }
+
+    let (path, query) = request.url.split_once('?').unwrap_or((&request.url, ""));
+    let segments = path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| percent_decode(segment, false))
+        .collect::<Result<Vec<_>, _>>();
+    let segments = match segments {
+        Ok(segments) => segments,
+        Err(e) => return ApiResponse::error(400, e),
+    };
+    let mut params = Map::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match (percent_decode(key, true), percent_decode(value, true)) {
+            (Ok(key), Ok(value)) => params.insert(key, Value::String(value)),
+            (Err(e), _) | (_, Err(e)) => return ApiResponse::error(400, e),
+        };
+    }
+
+    let workspace = match state.current() {
+        Ok(workspace) => workspace,
+        Err(e) => return ApiResponse::error(503, e),
+    };
+    let param = |name: &str| params.get(name).and_then(Value::as_str).map(str::to_string);
+    let note_path = || param("path").ok_or_else(|| bad_request("缺少 path 参数".to_string()));
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    let read = |value: Result<Value, ApiError>| value.map(ApiResponse::ok);
+    let write = |path: String, value: Result<Value, ApiError>| {
+        value.map(|body| ApiResponse { changed: Some(path), ..ApiResponse::ok(body) })
+    };
+    // 在笔记上执行操作，失败时按笔记和涉及的节点判断状态码
+    let on_note = |path: &str, node_ids: &[&str], value: Result<Value, String>| {
+        value.map_err(|e| note_failure(&workspace, path, node_ids, e))
+    };
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["api", "notes"]) => {
+            let sort = match param("sort") {
+                Some(sort) => serde_json::from_value(Value::String(sort)).map_err(|_| bad_request("未知的排序方式".to_string())),
+                None => Ok(SortMode::default()),
+            };
+            read(sort.and_then(|sort| workspace.get_all_notes(sort).and_then(to_json).map_err(ApiError::from)))
+        }
+        ("GET", ["api", "categories"]) => read(workspace.get_all_categories(SortMode::default()).and_then(to_json).map_err(ApiError::from)),
+        ("GET", ["api", "note"]) => read(note_path().and_then(|path| {
+            let content = workspace.read_note(&path).map_err(|e| note_failure(&workspace, &path, &[], e))?;
+            Ok(serde_json::from_str(&content).unwrap_or(Value::String(content)))
+        })),
+        ("PUT", ["api", "note"]) => note_path().and_then(|path| {
+            parse_body::<Value>(&request.body)?;
+            // 接口只修改已有的笔记，不会顺带创建分类目录
+            let saved = match workspace.resolve(&path) {
+                Ok(note_path) if note_path.is_file() => workspace.save_note(&path, &request.body).map(|_| json!({})),
+                Ok(_) => Err(format!("笔记不存在: {}", path)),
+                Err(e) => Err(e),
+            };
+            let saved = on_note(&path, &[], saved);
+            write(path, saved)
+        }),
+        ("GET", ["api", "note", "markdown"]) => read(note_path().and_then(|path| {
+            on_note(&path, &[], workspace.export_markdown(&path).map(|markdown| json!({ "markdown": markdown })))
+        })),
+        ("GET", ["api", "search"]) => read(workspace.search_notes(&param("q").unwrap_or_default()).and_then(to_json).map_err(ApiError::from)),
+        ("GET", ["api", "nodes", node_id]) => read(note_path().and_then(|path| {
+            on_note(&path, &[node_id], workspace.get_node(&path, node_id).and_then(to_json))
+        })),
+        ("POST", ["api", "nodes"]) => note_path().and_then(|path| {
+            let body: AddNodeBody = parse_body(&request.body)?;
+            let node = workspace.add_node(&path, &body.parent_id, body.data, body.index).and_then(to_json);
+            let node = on_note(&path, &[&body.parent_id], node);
+            write(path, node)
+        }),
+        ("PATCH", ["api", "nodes", node_id]) => note_path().and_then(|path| {
+            let node = workspace.update_node(&path, node_id, parse_body(&request.body)?).and_then(to_json);
+            let node = on_note(&path, &[node_id], node);
+            write(path, node)
+        }),
+        ("DELETE", ["api", "nodes", node_id]) => note_path().and_then(|path| {
+            let removed = workspace.delete_node(&path, node_id).map(|removed| json!({ "removed": removed }));
+            let removed = on_note(&path, &[node_id], removed);
+            write(path, removed)
+        }),
+        ("POST", ["api", "nodes", node_id, "move"]) => note_path().and_then(|path| {
+            let body: MoveNodeBody = parse_body(&request.body)?;
+            let node = workspace.move_node(&path, node_id, &body.parent_id, body.index).and_then(to_json);
+            let node = on_note(&path, &[node_id, &body.parent_id], node);
+            write(path, node)
+        }),
+        _ => return ApiResponse::error(404, format!("未知的接口: {} {}", request.method, path)),
+    };
+    result.unwrap_or_else(|e| ApiResponse::error(e.status, e.message))
+}
+
+// 正在运行的接口服务
+struct RunningServer {
+    port: u16,
+    server: Arc<Server>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+// 本地 HTTP 接口，作为 Tauri 托管状态保存，按配置启动或停止
+#[derive(Default)]
+pub struct ApiServer {
+    running: Mutex<Option<RunningServer>>,
+}
+
+impl ApiServer {
+    // 按当前配置启动、重启或停止接口；令牌在每个请求时读取，修改令牌不需要重启
+    pub fn apply(&self, app: &AppHandle) {
+        let config = app.state::<ConfigState>();
+        let api = config.get().api;
+        let mut running = self.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if api.enabled && running.as_ref().is_some_and(|server| server.port == api.port) {
+            return;
+        }
+        if let Some(server) = running.take() {
+            // 每次 unblock 只唤醒一个等待请求的线程
+            for _ in &server.workers {
+                server.server.unblock();
+            }
+            for worker in server.workers {
+                let _ = worker.join();
+            }
+            backend_log!("[后端] 已停止本地接口");
+        }
+        if !api.enabled {
+            return;
+        }
+        if let Err(e) = config.ensure_api_token() {
+            backend_log!("[后端] 无法生成接口令牌: {}", e);
+            return;
+        }
+        match Server::http(("127.0.0.1", api.port)) {
+            Ok(server) => {
+                let server = Arc::new(server);
+                let workers = (0..WORKER_COUNT).map(|_| spawn_worker(server.clone(), app.clone())).collect();
+                *running = Some(RunningServer { port: api.port, server, workers });
+                backend_log!("[后端] 本地接口已启动: http://127.0.0.1:{}", api.port);
+            }
+            Err(e) => backend_log!("[后端] 无法启动本地接口: {}", e),
+        }
+    }
+}
+
+// 处理请求的线程，所有线程从同一个服务上轮流取请求
+fn spawn_worker(server: Arc<Server>, app: AppHandle) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            // 多读一个字节，超出上限的请求直接拒绝，而不是截断后处理
+            let read = request.as_reader().take(MAX_BODY_SIZE + 1).read_to_string(&mut body);
+            let response = match read {
+                Ok(size) if size as u64 > MAX_BODY_SIZE => ApiResponse::error(413, "请求体过大"),
+                Ok(_) => {
+                    let api_request = ApiRequest {
+                        method: request.method().as_str().to_uppercase(),
+                        url: request.url().to_string(),
+                        authorization: request.headers()
+                            .iter()
+                            .find(|header| header.field.equiv("Authorization"))
+                            .map(|header| header.value.to_string()),
+                        body,
+                    };
+                    let token = app.state::<ConfigState>().get().api.token;
+                    handle_request(&app.state::<WorkspaceState>(), &token, &api_request)
+                }
+                Err(e) => ApiResponse::error(400, format!("无法读取请求体: {}", e)),
+            };
+            if let Some(path) = &response.changed {
+                let _ = app.emit(NOTES_CHANGED_EVENT, path);
+            }
+            let content_type = Header::from_bytes("Content-Type", "application/json; charset=utf-8")
+                .expect("固定的响应头");
+            let reply = Response::from_string(response.body.to_string())
+                .with_status_code(response.status)
+                .with_header(content_type);
+            if let Err(e) = request.respond(reply) {
+                backend_log!("[后端] 无法发送接口响应: {}", e);
+            }
+        }
+    })
+}
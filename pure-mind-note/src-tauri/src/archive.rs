@@ -0,0 +1,400 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::commands::{is_note_file, note_title, ASSETS_SUFFIX};
+use crate::metadata::{self, METADATA_FILE};
+use crate::naming;
+use crate::sync::{self, join_relative};
+use crate::workspace::{write_atomic, Workspace, WorkspaceState, TEMP_SUFFIX};
+
+// 归档文件的扩展名
+pub const ARCHIVE_EXTENSION: &str = "pmn";
+
+// 归档内的清单文件和内容目录
+const MANIFEST_NAME: &str = "manifest.json";
+const CONTENT_DIR: &str = "content/";
+const ARCHIVE_FORMAT: &str = "pure-mind-note-archive";
+const ARCHIVE_VERSION: u32 = 1;
+
+// 归档清单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub format: String,
+    pub version: u32,
+    pub app_version: String,
+    // 导出时间（RFC 3339）
+    pub created: String,
+    pub notes: Vec<ArchiveNote>,
+    // 归档中的全部文件（相对于工作区根目录），包括笔记、分类元数据和附件
+    pub files: Vec<ArchiveFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveNote {
+    pub path: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveFile {
+    pub path: String,
+    pub size: u64,
+}
+
+// 导出的范围，都为空时导出整个工作区
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ArchiveSelection {
+    // 分类或子分类，如 "工作"、"工作/会议"
+    pub categories: Vec<String>,
+    // 笔记路径，如 "工作/周报.json"
+    pub notes: Vec<String>,
+}
+
+// 导入时与已有文件重名的处理方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CollisionStrategy {
+    // 重命名导入的文件，如 "周报 (2).json"
+    #[default]
+    Rename,
+    // 覆盖已有的文件
+    Overwrite,
+    // 保留已有的文件，跳过导入
+    Skip,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamedEntry {
+    pub from: String,
+    pub to: String,
+}
+
+// 导入结果汇总
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub imported: Vec<String>,
+    pub renamed: Vec<RenamedEntry>,
+    pub overwritten: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+fn zip_error(e: zip::result::ZipError) -> String {
+    format!("归档文件操作失败: {}", e)
+}
+
+// 根据笔记在工作区中的位置生成笔记ID
+fn note_id_for(relative: &str) -> Option<String> {
+    let parts: Vec<&str> = relative.split('/').collect();
+    let stem = parts.last()?.strip_suffix(".json")?;
+    match parts.as_slice() {
+        [category, _] => Some(format!("{}##{}", category, stem)),
+        [category, sub, _] => Some(format!("{}#{}#{}", category, sub, stem)),
+        _ => None,
+    }
+}
+
+// 在目录中为重名的条目找一个可用的名称，如 "周报 (2).json"
+fn available_name(dir: &Path, name: &str) -> String {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (name, None),
+    };
+    (2..)
+        .map(|index| format!("{} ({})", stem, index))
+        .find(|candidate| naming::find_conflict(dir, candidate, extension).is_none()
+            && naming::find_conflict(dir, &with_extension(candidate, extension), None).is_none())
+        .map(|candidate| with_extension(&candidate, extension))
+        .unwrap_or_else(|| name.to_string())
+}
+
+// 归档中的文件只能位于分类目录中，不能写入隐藏目录（如 .git、模板、草稿）或根目录下的设置文件
+fn check_entry_path(relative: &str) -> Result<(), String> {
+    let parts: Vec<&str> = relative.split('/').collect();
+    if parts.len() < 2 {
+        return Err(format!("归档中包含工作区根目录下的文件: {}", relative));
+    }
+    if parts.iter().any(|part| part.is_empty() || part.starts_with('.')) {
+        return Err(format!("归档中包含非法路径: {}", relative));
+    }
+    Ok(())
+}
+
+fn with_extension(stem: &str, extension: Option<&str>) -> String {
+    match extension {
+        Some(extension) => format!("{}.{}", stem, extension),
+        None => stem.to_string(),
+    }
+}
+
+impl Workspace {
+    // 目录中的全部文件
+    fn add_tree_files(&self, relative: &str, files: &mut BTreeSet<String>) -> Result<(), String> {
+        for entry in sync::walk_tree(&self.resolve(relative)?)? {
+            if !entry.is_dir {
+                files.insert(self.relative_path(&entry.path));
+            }
+        }
+        Ok(())
+    }
+
+    // 按选择范围收集要导出的文件
+    fn archive_files(&self, selection: &ArchiveSelection) -> Result<BTreeSet<String>, String> {
+        let mut files = BTreeSet::new();
+
+        if selection.categories.is_empty() && selection.notes.is_empty() {
+            // 整个工作区：根目录下只有分类目录，根目录中的文件（如加密参数）不导出
+            for entry in sync::walk_tree(self.root())? {
+                if !entry.is_dir && entry.relative.contains('/') {
+                    files.insert(entry.relative);
+                }
+            }
+            return Ok(files);
+        }
+
+        for category in &selection.categories {
+            if !self.resolve(category)?.is_dir() {
+                return Err(format!("分类 '{}' 不存在", category));
+            }
+            self.add_tree_files(category, &mut files)?;
+        }
+
+        for note in &selection.notes {
+            let note_path = self.resolve(note)?;
+            if !is_note_file(&note_path) {
+                return Err(format!("笔记 '{}' 不存在", note));
+            }
+            files.insert(self.relative_path(&note_path));
+            let assets = format!("{}{}", note.trim_end_matches(".json"), ASSETS_SUFFIX);
+            if self.resolve(&assets)?.is_dir() {
+                self.add_tree_files(&assets, &mut files)?;
+            }
+        }
+
+        // 带上所在分类和子分类的元数据，导入后显示名称等信息不丢失
+        let parents: Vec<String> = files.iter()
+            .filter_map(|file| file.rsplit_once('/').map(|(dir, _)| dir.to_string()))
+            .collect();
+        for dir in parents {
+            let mut current = Some(dir.as_str());
+            while let Some(dir) = current {
+                let meta = format!("{}/{}", dir, METADATA_FILE);
+                if self.resolve(&meta)?.is_file() {
+                    files.insert(meta);
+                }
+                current = dir.rsplit_once('/').map(|(parent, _)| parent);
+            }
+        }
+        Ok(files)
+    }
+
+    // 导出为单个 .pmn 归档；加密工作区中的笔记以明文导出，单独加锁的笔记保持加锁
+    pub fn export_archive(&self, destination: &str, selection: &ArchiveSelection) -> Result<ArchiveManifest, String> {
+        let mut destination = PathBuf::from(destination);
+        if destination.as_os_str().is_empty() {
+            return Err("导出路径不能为空".to_string());
+        }
+        if destination.extension().and_then(|ext| ext.to_str()) != Some(ARCHIVE_EXTENSION) {
+            destination.set_extension(ARCHIVE_EXTENSION);
+        }
+        backend_log!("[后端] 导出归档: {}", destination.display());
+
+        let mut manifest = ArchiveManifest {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created: chrono::Local::now().to_rfc3339(),
+            notes: Vec::new(),
+            files: Vec::new(),
+        };
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        let mut temp_name = destination.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(TEMP_SUFFIX);
+        let temp_path = destination.with_file_name(temp_name);
+
+        let result = (|| {
+            let file = fs::File::create(&temp_path).map_err(|e| format!("无法创建归档文件: {}", e))?;
+            let mut writer = ZipWriter::new(file);
+            for relative in self.archive_files(selection)? {
+                let path = join_relative(self.root(), &relative);
+                let content = if is_note_file(&path) {
+                    let content = self.read_note_bytes(&path)?;
+                    let file_stem = path.file_stem().and_then(|name| name.to_str()).unwrap_or_default();
+                    manifest.notes.push(ArchiveNote {
+                        path: relative.clone(),
+                        title: note_title(&content).unwrap_or_else(|| file_stem.to_string()),
+                    });
+                    content
+                } else {
+                    fs::read(&path).map_err(|e| format!("无法读取文件 {}: {}", relative, e))?
+                };
+                writer.start_file(format!("{}{}", CONTENT_DIR, relative), options).map_err(zip_error)?;
+                writer.write_all(&content).map_err(|e| format!("无法写入归档文件: {}", e))?;
+                manifest.files.push(ArchiveFile { path: relative, size: content.len() as u64 });
+            }
+
+            let manifest_json = serde_json::to_string_pretty(&manifest)
+                .map_err(|e| format!("无法序列化归档清单: {}", e))?;
+            writer.start_file(MANIFEST_NAME, options).map_err(zip_error)?;
+            writer.write_all(manifest_json.as_bytes()).map_err(|e| format!("无法写入归档文件: {}", e))?;
+            writer.finish().map_err(zip_error)?;
+            fs::rename(&temp_path, &destination).map_err(|e| format!("无法保存归档文件: {}", e))
+        })();
+        if let Err(e) = result {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+
+        backend_log!("[后端] 导出完成: {} 篇笔记，{} 个文件", manifest.notes.len(), manifest.files.len());
+        Ok(manifest)
+    }
+
+    // 将 .pmn 归档合并到当前工作区
+    pub fn import_archive(&self, source: &str, strategy: CollisionStrategy) -> Result<ImportSummary, String> {
+        let file = fs::File::open(source).map_err(|e| format!("无法打开归档文件: {}", e))?;
+        let mut archive = ZipArchive::new(file).map_err(zip_error)?;
+
+        let manifest: ArchiveManifest = {
+            let mut entry = archive.by_name(MANIFEST_NAME).map_err(|_| "不是有效的归档文件：缺少清单".to_string())?;
+            let mut content = String::new();
+            entry.read_to_string(&mut content).map_err(|e| format!("无法读取归档清单: {}", e))?;
+            serde_json::from_str(&content).map_err(|e| format!("无法解析归档清单: {}", e))?
+        };
+        if manifest.format != ARCHIVE_FORMAT {
+            return Err("不是有效的归档文件".to_string());
+        }
+        if manifest.version > ARCHIVE_VERSION {
+            return Err(format!("归档版本 {} 过新，请先升级应用", manifest.version));
+        }
+        backend_log!("[后端] 导入归档: {}，共 {} 个文件", source, manifest.files.len());
+
+        // 先导入元数据和笔记，附件跟随笔记的新名称
+        let mut files: Vec<&ArchiveFile> = manifest.files.iter().collect();
+        files.sort_by_key(|file| {
+            let path = Path::new(&file.path);
+            (!metadata::is_metadata_file(path), path.extension().and_then(|ext| ext.to_str()) != Some("json") || file.path.contains(ASSETS_SUFFIX))
+        });
+
+        let mut summary = ImportSummary::default();
+        // 被重命名的笔记的附件目录：旧路径前缀 -> 新路径前缀
+        let mut renamed_assets: HashMap<String, String> = HashMap::new();
+        for file in files {
+            let content = {
+                let mut entry = archive.by_name(&format!("{}{}", CONTENT_DIR, file.path))
+                    .map_err(|_| format!("归档中缺少文件: {}", file.path))?;
+                let mut content = Vec::new();
+                entry.read_to_end(&mut content).map_err(|e| format!("归档文件 {} 已损坏: {}", file.path, e))?;
+                content
+            };
+
+            let mut relative = file.path.clone();
+            if let Some((from, to)) = renamed_assets.iter().find(|(from, _)| relative.starts_with(from.as_str())) {
+                relative = format!("{}{}", to, &relative[from.len()..]);
+            }
+            check_entry_path(&relative)?;
+            // 第一级必须是已有的分类目录，或者是随归档导入的新分类
+            let category = self.resolve(relative.split('/').next().unwrap_or_default())?;
+            if category.exists() && !category.is_dir() {
+                return Err(format!("归档中的分类与已有文件冲突: {}", relative));
+            }
+            let mut dest = self.resolve(&relative)?;
+            let parent = dest.parent().map(Path::to_path_buf).unwrap_or_default();
+            let file_name = dest.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_string();
+            let is_metadata = metadata::is_metadata_file(&dest);
+            let is_note = !is_metadata && file_name.ends_with(".json") && !relative.contains(ASSETS_SUFFIX);
+
+            let existing = match is_note {
+                true => naming::find_conflict(&parent, file_name.trim_end_matches(".json"), Some("json")),
+                false => naming::find_conflict(&parent, &file_name, None),
+            };
+            if existing.is_some() {
+                // 同名分类合并到已有分类中，除非选择覆盖，否则保留已有的分类元数据
+                let keep_existing = strategy == CollisionStrategy::Skip
+                    || (is_metadata && strategy == CollisionStrategy::Rename);
+                if keep_existing {
+                    summary.skipped.push(relative);
+                    continue;
+                }
+                if strategy == CollisionStrategy::Overwrite {
+                    summary.overwritten.push(relative.clone());
+                } else {
+                    let new_name = available_name(&parent, &file_name);
+                    let new_relative = match relative.rsplit_once('/') {
+                        Some((dir, _)) => format!("{}/{}", dir, new_name),
+                        None => new_name.clone(),
+                    };
+                    if is_note {
+                        renamed_assets.insert(
+                            format!("{}{}/", relative.trim_end_matches(".json"), ASSETS_SUFFIX),
+                            format!("{}{}/", new_relative.trim_end_matches(".json"), ASSETS_SUFFIX),
+                        );
+                    }
+                    summary.renamed.push(RenamedEntry { from: relative.clone(), to: new_relative.clone() });
+                    relative = new_relative;
+                    dest = self.resolve(&relative)?;
+                }
+            }
+
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("无法创建目录: {}", e))?;
+            }
+            self.ensure_inside(&dest)?;
+            if is_note {
+                // 笔记ID与导入后的位置保持一致（加锁的笔记无法修改内容）
+                let mut content = content;
+                if let (Ok(serde_json::Value::Object(mut note)), Some(id)) =
+                    (serde_json::from_slice::<serde_json::Value>(&content), note_id_for(&relative))
+                {
+                    note.insert("id".to_string(), id.into());
+                    content = serde_json::to_vec_pretty(&note).map_err(|e| format!("无法序列化笔记内容: {}", e))?;
+                }
+                self.write_note_bytes(&dest, &content)?;
+            } else {
+                write_atomic(&dest, &content)?;
+            }
+            summary.imported.push(relative);
+        }
+
+        let name = Path::new(source).file_name().unwrap_or_default().to_string_lossy();
+        self.record_change(format!("导入归档 {}", name));
+        backend_log!(
+            "[后端] 导入完成: 导入 {} 个，重命名 {} 个，跳过 {} 个",
+            summary.imported.len(),
+            summary.renamed.len(),
+            summary.skipped.len()
+        );
+        Ok(summary)
+    }
+}
+
+// 导出 .pmn 归档
+#[tauri::command]
+pub async fn export_archive(
+    state: State<'_, WorkspaceState>,
+    destination: String,
+    selection: Option<ArchiveSelection>,
+) -> Result<ArchiveManifest, String> {
+    state.run(move |workspace| workspace.export_archive(&destination, &selection.unwrap_or_default())).await
+}
+
+// 导入 .pmn 归档
+#[tauri::command]
+pub async fn import_archive(
+    state: State<'_, WorkspaceState>,
+    source: String,
+    strategy: Option<CollisionStrategy>,
+) -> Result<ImportSummary, String> {
+    state.run(move |workspace| workspace.import_archive(&source, strategy.unwrap_or_default())).await
+}
@@ -0,0 +1,404 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::naming;
+use crate::registry::run_blocking;
+use crate::sync::{self, join_relative};
+use crate::workspace::{write_atomic, Workspace, WorkspaceState, TEMP_SUFFIX};
+
+// 工作区根目录下保存备份设置的文件（只对本机有效，不参与同步）
+pub const BACKUP_SETTINGS_FILE: &str = ".pmn-backup.json";
+
+// 备份文件名：pmn-backup_<工作区名>_<时间>.zip
+const BACKUP_PREFIX: &str = "pmn-backup_";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+
+// 自动备份设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    // 备份目录，不能位于工作区内
+    pub folder: Option<String>,
+    // 两次自动备份的间隔（小时）
+    pub interval_hours: u64,
+    // 保留最近多少天、多少周、多少个月中每个周期的最新备份
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+    pub keep_monthly: usize,
+    // 上次备份时间（RFC 3339），由后端维护
+    pub last_backup: Option<String>,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            folder: None,
+            interval_hours: 24,
+            keep_daily: 7,
+            keep_weekly: 4,
+            keep_monthly: 12,
+            last_backup: None,
+        }
+    }
+}
+
+// 备份目录中的一个备份
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub path: String,
+    // 备份时间（RFC 3339）
+    pub created: String,
+    pub size: u64,
+}
+
+// 按保留策略选出要保留的备份：最新的一个总是保留，此外每天、每周、每月各保留最新的一个
+// times 需按时间从新到旧排列，返回每个备份是否保留
+pub fn retained_backups(times: &[DateTime<Local>], settings: &BackupSettings) -> Vec<bool> {
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut months = HashSet::new();
+    times.iter().enumerate().map(|(index, time)| {
+        let day = time.date_naive();
+        let week = time.iso_week();
+        let month = (time.year(), time.month());
+        let mut keep = index == 0;
+        if !days.contains(&day) && days.len() < settings.keep_daily {
+            days.insert(day);
+            keep = true;
+        }
+        if !weeks.contains(&week) && weeks.len() < settings.keep_weekly {
+            weeks.insert(week);
+            keep = true;
+        }
+        if !months.contains(&month) && months.len() < settings.keep_monthly {
+            months.insert(month);
+            keep = true;
+        }
+        keep
+    }).collect()
+}
+
+fn zip_error(e: zip::result::ZipError) -> String {
+    format!("备份文件操作失败: {}", e)
+}
+
+// 重新读取备份中的每个文件（读取时会校验 CRC），并核对文件列表和大小
+fn verify_archive(path: &Path, expected: &[(String, u64)]) -> Result<(), String> {
+    let file = fs::File::open(path).map_err(|e| format!("无法打开备份文件: {}", e))?;
+    let mut archive = ZipArchive::new(file).map_err(zip_error)?;
+    for (name, size) in expected {
+        let mut entry = archive.by_name(name).map_err(|_| format!("备份中缺少文件: {}", name))?;
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content).map_err(|e| format!("备份文件 {} 已损坏: {}", name, e))?;
+        if content.len() as u64 != *size {
+            return Err(format!("备份文件 {} 的大小不一致", name));
+        }
+    }
+    Ok(())
+}
+
+// 将备份解压到目录中，返回备份中的全部条目
+fn extract_archive(archive_path: &Path, target: &Path) -> Result<HashSet<String>, String> {
+    let file = fs::File::open(archive_path).map_err(|e| format!("无法打开备份文件: {}", e))?;
+    let mut archive = ZipArchive::new(file).map_err(zip_error)?;
+    let mut names = HashSet::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(zip_error)?;
+        // 拒绝包含 ".." 或绝对路径的条目
+        let relative = entry.enclosed_name().ok_or_else(|| format!("备份中包含非法路径: {}", entry.name()))?;
+        let path = target.join(&relative);
+        names.insert(entry.name().trim_end_matches('/').to_string());
+        if entry.is_dir() {
+            fs::create_dir_all(&path).map_err(|e| format!("无法创建目录: {}", e))?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("无法创建目录: {}", e))?;
+        }
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content).map_err(|e| format!("备份文件 {} 已损坏: {}", entry.name(), e))?;
+        write_atomic(&path, &content)?;
+    }
+    Ok(names)
+}
+
+impl Workspace {
+    // 读取备份设置
+    pub fn backup_settings(&self) -> BackupSettings {
+        fs::read_to_string(self.root().join(BACKUP_SETTINGS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn write_backup_settings(&self, settings: &BackupSettings) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(settings)
+            .map_err(|e| format!("无法序列化备份设置: {}", e))?;
+        write_atomic(&self.root().join(BACKUP_SETTINGS_FILE), content.as_bytes())
+    }
+
+    // 更新备份设置，返回保存后的设置
+    pub fn update_backup_settings(&self, mut settings: BackupSettings) -> Result<BackupSettings, String> {
+        if settings.interval_hours == 0 {
+            return Err("备份间隔至少为 1 小时".to_string());
+        }
+        if let Some(folder) = &settings.folder {
+            let folder = self.prepare_backup_folder(folder)?;
+            settings.folder = Some(folder.to_string_lossy().to_string());
+        } else if settings.enabled {
+            return Err("启用自动备份前请先设置备份目录".to_string());
+        }
+        // 上次备份时间由后端维护
+        settings.last_backup = self.backup_settings().last_backup;
+        self.write_backup_settings(&settings)?;
+        Ok(settings)
+    }
+
+    // 创建并规范化备份目录，备份目录不能位于工作区内
+    fn prepare_backup_folder(&self, folder: &str) -> Result<PathBuf, String> {
+        if folder.trim().is_empty() {
+            return Err("备份目录不能为空".to_string());
+        }
+        fs::create_dir_all(folder).map_err(|e| format!("无法创建备份目录: {}", e))?;
+        let folder = fs::canonicalize(folder).map_err(|e| format!("无法解析备份目录: {}", e))?;
+        if folder.starts_with(self.root()) {
+            return Err("备份目录不能位于工作区内".to_string());
+        }
+        Ok(folder)
+    }
+
+    fn backup_folder(&self) -> Result<PathBuf, String> {
+        let folder = self.backup_settings().folder.ok_or_else(|| "尚未设置备份目录".to_string())?;
+        self.prepare_backup_folder(&folder)
+    }
+
+    // 备份文件名中的工作区名称
+    fn backup_name(&self) -> String {
+        let dir_name = self.root().file_name().and_then(|name| name.to_str()).unwrap_or("workspace");
+        naming::slugify(dir_name).unwrap_or_else(|_| "workspace".to_string()).replace('_', "-")
+    }
+
+    // 备份目录中属于本工作区的备份及其时间，从新到旧排列
+    fn backup_files(&self) -> Result<Vec<(DateTime<Local>, PathBuf)>, String> {
+        let prefix = format!("{}{}_", BACKUP_PREFIX, self.backup_name());
+        let mut backups: Vec<(DateTime<Local>, PathBuf)> = fs::read_dir(self.backup_folder()?)
+            .map_err(|e| format!("无法读取备份目录: {}", e))?
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_str()?.to_string();
+                let timestamp = name.strip_prefix(&prefix)?.strip_suffix(".zip")?;
+                let time = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+                Some((Local.from_local_datetime(&time).earliest()?, entry.path()))
+            })
+            .collect();
+        backups.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+        Ok(backups)
+    }
+
+    // 将工作区打包备份到备份目录，并按保留策略清理旧备份
+    pub fn create_backup(&self) -> Result<BackupInfo, String> {
+        let backup = self.write_backup()?;
+        let removed = self.prune_backups()?;
+        backend_log!("[后端] 清理旧备份 {} 个", removed.len());
+        Ok(backup)
+    }
+
+    // 写入一个新备份并校验；加密工作区的备份中保存的是密文，恢复后仍需口令
+    fn write_backup(&self) -> Result<BackupInfo, String> {
+        let folder = self.backup_folder()?;
+        let now = Local::now();
+        let file_name = format!("{}{}_{}.zip", BACKUP_PREFIX, self.backup_name(), now.format(TIMESTAMP_FORMAT));
+        let archive_path = folder.join(&file_name);
+        let temp_path = folder.join(format!("{}{}", file_name, TEMP_SUFFIX));
+        backend_log!("[后端] 开始备份工作区: {}", archive_path.display());
+
+        let mut entries = sync::walk_tree(self.root())?;
+        entries.sort_by(|a, b| a.relative.cmp(&b.relative));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        let mut expected = Vec::new();
+        let result = (|| {
+            let file = fs::File::create(&temp_path).map_err(|e| format!("无法创建备份文件: {}", e))?;
+            let mut writer = ZipWriter::new(file);
+            for entry in &entries {
+                if entry.is_dir {
+                    writer.add_directory(entry.relative.as_str(), options).map_err(zip_error)?;
+                    continue;
+                }
+                let content = fs::read(&entry.path).map_err(|e| format!("无法读取文件 {}: {}", entry.relative, e))?;
+                writer.start_file(entry.relative.as_str(), options).map_err(zip_error)?;
+                writer.write_all(&content).map_err(|e| format!("无法写入备份文件: {}", e))?;
+                expected.push((entry.relative.clone(), content.len() as u64));
+            }
+            writer.finish().map_err(zip_error)?.sync_all().map_err(|e| format!("无法写入备份文件: {}", e))?;
+            verify_archive(&temp_path, &expected)?;
+            fs::rename(&temp_path, &archive_path).map_err(|e| format!("无法保存备份文件: {}", e))
+        })();
+        if let Err(e) = result {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+
+        let mut settings = self.backup_settings();
+        settings.last_backup = Some(now.to_rfc3339());
+        self.write_backup_settings(&settings)?;
+        backend_log!("[后端] 备份完成: {} 个文件", expected.len());
+
+        Ok(BackupInfo {
+            file_name,
+            size: fs::metadata(&archive_path).map(|meta| meta.len()).unwrap_or(0),
+            path: archive_path.to_string_lossy().to_string(),
+            created: now.to_rfc3339(),
+        })
+    }
+
+    // 按保留策略删除多余的备份，返回被删除的文件名
+    pub fn prune_backups(&self) -> Result<Vec<String>, String> {
+        let settings = self.backup_settings();
+        let backups = self.backup_files()?;
+        let times: Vec<DateTime<Local>> = backups.iter().map(|(time, _)| *time).collect();
+
+        let mut removed = Vec::new();
+        for ((_, path), keep) in backups.iter().zip(retained_backups(&times, &settings)) {
+            if !keep {
+                fs::remove_file(path).map_err(|e| format!("无法删除旧备份: {}", e))?;
+                removed.push(path.file_name().unwrap_or_default().to_string_lossy().to_string());
+            }
+        }
+        Ok(removed)
+    }
+
+    // 列出本工作区的备份，最新的在前
+    pub fn list_backups(&self) -> Result<Vec<BackupInfo>, String> {
+        Ok(self.backup_files()?
+            .into_iter()
+            .map(|(time, path)| BackupInfo {
+                file_name: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+                size: fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0),
+                path: path.to_string_lossy().to_string(),
+                created: time.to_rfc3339(),
+            })
+            .collect())
+    }
+
+    // 从备份恢复：target 为 Some 时解压到新目录，否则覆盖当前工作区（恢复前先备份当前状态）
+    // 返回恢复后的目录
+    pub fn restore_backup(&self, file_name: &str, target: Option<&str>) -> Result<String, String> {
+        let archive_path = self.backup_files()?
+            .into_iter()
+            .map(|(_, path)| path)
+            .find(|path| path.file_name().and_then(|name| name.to_str()) == Some(file_name))
+            .ok_or_else(|| format!("找不到备份: {}", file_name))?;
+
+        if let Some(target) = target {
+            let target = Path::new(target);
+            let is_empty = fs::read_dir(target).map(|mut entries| entries.next().is_none()).unwrap_or(true);
+            if !is_empty {
+                return Err("恢复目录必须是空目录".to_string());
+            }
+            fs::create_dir_all(target).map_err(|e| format!("无法创建恢复目录: {}", e))?;
+            let target = fs::canonicalize(target).map_err(|e| format!("无法解析恢复目录: {}", e))?;
+            if target.starts_with(self.root()) {
+                let _ = fs::remove_dir(&target);
+                return Err("恢复目录不能位于工作区内".to_string());
+            }
+            extract_archive(&archive_path, &target)?;
+            backend_log!("[后端] 已将备份 {} 恢复到 {}", file_name, target.display());
+            return Ok(target.to_string_lossy().to_string());
+        }
+
+        // 恢复前先备份当前状态；这里不清理旧备份，避免要恢复的备份被清理掉。
+        // 备份、解压和清理期间不允许其他修改写入工作区
+        let _guard = self.lock_edits();
+        self.write_backup()?;
+        let restored = extract_archive(&archive_path, self.root())?;
+
+        // 删除备份之后新建的文件和目录（仓库、临时文件等被跳过的条目保持不变）
+        let mut extra: Vec<_> = sync::walk_tree(self.root())?
+            .into_iter()
+            .filter(|entry| !restored.contains(&entry.relative))
+            .collect();
+        extra.sort_by(|a, b| b.relative.cmp(&a.relative));
+        for entry in extra {
+            let path = join_relative(self.root(), &entry.relative);
+            if entry.is_dir {
+                let _ = fs::remove_dir(&path);
+            } else {
+                fs::remove_file(&path).map_err(|e| format!("无法删除文件 {}: {}", entry.relative, e))?;
+            }
+        }
+
+        self.record_change(format!("从备份 {} 恢复", file_name));
+        backend_log!("[后端] 已从备份 {} 恢复工作区", file_name);
+        Ok(self.root().to_string_lossy().to_string())
+    }
+
+    // 自动备份：已启用且距上次备份超过间隔时备份
+    pub fn run_scheduled_backup(&self) -> Result<Option<BackupInfo>, String> {
+        let settings = self.backup_settings();
+        if !settings.enabled || settings.folder.is_none() {
+            return Ok(None);
+        }
+        let due = settings.last_backup
+            .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+            .map(|time| Local::now().signed_duration_since(time).num_hours() >= settings.interval_hours as i64)
+            .unwrap_or(true);
+        if !due {
+            return Ok(None);
+        }
+        self.create_backup().map(Some)
+    }
+}
+
+// 获取备份设置
+#[tauri::command]
+pub async fn get_backup_settings(state: State<'_, WorkspaceState>) -> Result<BackupSettings, String> {
+    state.run(move |workspace| Ok(workspace.backup_settings())).await
+}
+
+// 更新备份设置
+#[tauri::command]
+pub async fn update_backup_settings(state: State<'_, WorkspaceState>, settings: BackupSettings) -> Result<BackupSettings, String> {
+    state.run(move |workspace| workspace.update_backup_settings(settings)).await
+}
+
+// 立即备份
+#[tauri::command]
+pub async fn create_backup(state: State<'_, WorkspaceState>) -> Result<BackupInfo, String> {
+    state.run(move |workspace| workspace.create_backup()).await
+}
+
+// 列出备份
+#[tauri::command]
+pub async fn list_backups(state: State<'_, WorkspaceState>) -> Result<Vec<BackupInfo>, String> {
+    state.run(move |workspace| workspace.list_backups()).await
+}
+
+// 从备份恢复，覆盖当前工作区时恢复后重新打开工作区
+#[tauri::command]
+pub async fn restore_backup(
+    app: AppHandle,
+    state: State<'_, WorkspaceState>,
+    file_name: String,
+    target: Option<String>,
+) -> Result<String, String> {
+    let in_place = target.is_none();
+    let restored = state.run(move |workspace| workspace.restore_backup(&file_name, target.as_deref())).await?;
+    if in_place {
+        let path = restored.clone();
+        run_blocking(app, move |app| app.state::<WorkspaceState>().set(Workspace::open(&path)?).map(|_| ())).await?;
+    }
+    Ok(restored)
+}
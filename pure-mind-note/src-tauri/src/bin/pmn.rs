@@ -0,0 +1,17 @@
+// 不启动界面、直接操作工作区目录的命令行工具
+use std::io::Write;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // 不要一直持有标准输出的锁，--stdio-server 模式下后台线程也要写入通知
+    let mut stdout = std::io::stdout();
+    let code = match app_lib::cli::run(&args, &mut stdout) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            1
+        }
+    };
+    let _ = stdout.flush();
+    std::process::exit(code);
+}
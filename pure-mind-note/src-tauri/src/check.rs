@@ -0,0 +1,435 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::State;
+
+use crate::commands::{is_note_file, is_subcategory_dir};
+use crate::crypto::REKEY_SUFFIX;
+use crate::nodes::{new_node_id, node_id};
+use crate::note_format;
+use crate::note_lock;
+use crate::workspace::{Workspace, WorkspaceState, TEMP_SUFFIX};
+
+// 无法解析的笔记修复时改名加上的后缀，改名后不再出现在笔记列表中
+pub const CORRUPT_SUFFIX: &str = ".corrupt";
+
+// 问题的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueKind {
+    // 目录无法读取
+    UnreadableDirectory,
+    // 笔记文件无法读取或解密
+    UnreadableNote,
+    // 笔记内容不是有效的 JSON
+    InvalidJson,
+    // rootId 指向的根节点不存在
+    MissingRoot,
+    // children 中引用了不存在的节点
+    DanglingChild,
+    // 无法从根节点到达的节点
+    UnreachableNode,
+    // 嵌套节点树中不是节点的子项，或 children 不是数组
+    InvalidNode,
+    // 节点没有ID
+    MissingNodeId,
+    // 同一篇笔记中多个节点使用了同一个节点ID
+    DuplicateNodeId,
+    // 多个笔记文件使用了同一个笔记ID
+    DuplicateNoteId,
+    // 写入中断留下的临时文件
+    StrayTempFile,
+}
+
+// 检查发现的一个问题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceIssue {
+    pub kind: IssueKind,
+    // 相对于工作区根目录的路径
+    pub path: String,
+    pub node_id: Option<String>,
+    pub detail: String,
+    // 是否已经修复
+    pub repaired: bool,
+}
+
+// 检查结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckReport {
+    pub notes_checked: usize,
+    // 加锁后无法检查内容的笔记数量
+    pub locked_notes: usize,
+    pub issues: Vec<WorkspaceIssue>,
+}
+
+impl CheckReport {
+    fn issue(&mut self, kind: IssueKind, path: &str, node_id: Option<&str>, detail: String, repaired: bool) {
+        self.issues.push(WorkspaceIssue {
+            kind,
+            path: path.to_string(),
+            node_id: node_id.map(str::to_string),
+            detail,
+            repaired,
+        });
+    }
+}
+
+// 遍历时收集到的文件
+#[derive(Default)]
+struct ScannedFiles {
+    notes: Vec<PathBuf>,
+    temp_files: Vec<PathBuf>,
+    unreadable_dirs: Vec<(PathBuf, String)>,
+}
+
+// 遍历工作区，目录无法读取时记录下来继续检查其他目录；不跟随符号链接
+fn scan_workspace(root: &Path) -> ScannedFiles {
+    let mut scanned = ScannedFiles::default();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let read_dir = match fs::read_dir(&dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) => {
+                scanned.unreadable_dirs.push((dir, e.to_string()));
+                continue;
+            }
+        };
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                // 跳过 .git、草稿日志等隐藏目录
+                if !name.starts_with('.') {
+                    pending.push((path, depth + 1));
+                }
+            } else if !file_type.is_file() {
+                continue;
+            } else if name.ends_with(TEMP_SUFFIX) || name.ends_with(REKEY_SUFFIX) {
+                scanned.temp_files.push(path);
+            } else if is_note_location(&path, depth) && is_note_file(&path) {
+                scanned.notes.push(path);
+            }
+        }
+    }
+    scanned.notes.sort();
+    scanned.temp_files.sort();
+    scanned
+}
+
+// 笔记只位于分类目录或子分类目录中（附件目录除外）
+fn is_note_location(path: &Path, depth: usize) -> bool {
+    match depth {
+        1 => true,
+        2 => path.parent().is_some_and(is_subcategory_dir),
+        _ => false,
+    }
+}
+
+// 由笔记文件的位置得到笔记ID，与列表中的ID一致
+fn expected_note_id(relative: &str) -> Option<String> {
+    let stem = relative.strip_suffix(".json")?;
+    match stem.split('/').collect::<Vec<_>>()[..] {
+        [category, note] => Some(format!("{}##{}", category, note)),
+        [category, subcategory, note] => Some(format!("{}#{}#{}", category, subcategory, note)),
+        _ => None,
+    }
+}
+
+fn parent_of(nodes: &Map<String, Value>, child: &str) -> Option<String> {
+    nodes.iter()
+        .find(|(_, node)| children_of(node).iter().any(|id| id == child))
+        .map(|(parent, _)| parent.clone())
+}
+
+fn reachable_from(nodes: &Map<String, Value>, start: &str) -> HashSet<String> {
+    let mut reachable = HashSet::new();
+    let mut pending = vec![start.to_string()];
+    while let Some(id) = pending.pop() {
+        if let Some(node) = nodes.get(&id) {
+            if reachable.insert(id) {
+                pending.extend(children_of(node));
+            }
+        }
+    }
+    reachable
+}
+
+fn children_of(node: &Value) -> Vec<String> {
+    node.get("children")
+        .and_then(Value::as_array)
+        .map(|children| children.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+// 检查嵌套节点树中的一个节点及其子树，返回是否有修改
+fn check_nested_node(
+    report: &mut CheckReport,
+    path: &str,
+    node: &mut Value,
+    seen: &mut HashSet<String>,
+    repair: bool,
+) -> bool {
+    let mut changed = false;
+    let id = match node_id(node).filter(|id| !id.is_empty()) {
+        Some(id) if seen.insert(id.to_string()) => Some(id.to_string()),
+        found => {
+            let (kind, detail) = match found {
+                Some(id) => (IssueKind::DuplicateNodeId, format!("节点ID {} 重复", id)),
+                None => (IssueKind::MissingNodeId, "节点没有ID".to_string()),
+            };
+            report.issue(kind, path, found, detail, repair);
+            // 修复时分配新的ID，已有的链接仍指向先出现的那个节点
+            if repair {
+                let new_id = new_node_id(seen);
+                seen.insert(new_id.clone());
+                if let Some(data) = node.get_mut("data").and_then(Value::as_object_mut) {
+                    data.remove("id");
+                    data.insert("uid".to_string(), Value::String(new_id.clone()));
+                }
+                changed = true;
+                Some(new_id)
+            } else {
+                None
+            }
+        }
+    };
+
+    let Some(node) = node.as_object_mut() else { return changed };
+    match node.get("children") {
+        None | Some(Value::Array(_)) => {}
+        Some(_) => {
+            report.issue(IssueKind::InvalidNode, path, id.as_deref(), "children 不是数组".to_string(), repair);
+            if !repair {
+                return changed;
+            }
+            node.insert("children".to_string(), Value::Array(Vec::new()));
+            changed = true;
+        }
+    }
+    let Some(children) = node.get_mut("children").and_then(Value::as_array_mut) else { return changed };
+
+    // 不是节点的子项在编辑器中无法显示：不是对象的直接去掉，缺少 data 的补上空节点数据以保留其子树
+    let mut index = 0;
+    while index < children.len() {
+        let child = &mut children[index];
+        if !child.is_object() {
+            report.issue(IssueKind::InvalidNode, path, id.as_deref(), format!("第 {} 个子节点不是节点", index + 1), repair);
+            if repair {
+                children.remove(index);
+                changed = true;
+            } else {
+                index += 1;
+            }
+            continue;
+        }
+        if !child.get("data").is_some_and(Value::is_object) {
+            report.issue(IssueKind::InvalidNode, path, id.as_deref(), format!("第 {} 个子节点缺少节点数据", index + 1), repair);
+            if !repair {
+                index += 1;
+                continue;
+            }
+            child["data"] = serde_json::json!({ "text": "" });
+            changed = true;
+        }
+        changed |= check_nested_node(report, path, child, seen, repair);
+        index += 1;
+    }
+    changed
+}
+
+// 检查一篇笔记的节点树，repair 为 true 时就地修复，返回是否有修改
+fn check_note_tree(report: &mut CheckReport, path: &str, note: &mut Value, repair: bool) -> bool {
+    // 编辑器保存的嵌套节点树
+    if let Some(tree) = note.get_mut("data").and_then(Value::as_object_mut).filter(|tree| !tree.is_empty() && !note_format::is_node_table(tree)) {
+        let mut root = Value::Object(std::mem::take(tree));
+        let changed = check_nested_node(report, path, &mut root, &mut HashSet::new(), repair);
+        note["data"] = root;
+        return changed;
+    }
+    // 有 children 却没有根节点数据的嵌套树
+    if note.get("data").and_then(Value::as_object).is_some_and(|tree| tree.contains_key("data") || tree.contains_key("children")) {
+        report.issue(IssueKind::MissingRoot, path, None, "根节点缺少节点数据".to_string(), false);
+        return false;
+    }
+
+    let root_id = note.get("rootId").and_then(Value::as_str).unwrap_or("root").to_string();
+    let Some(nodes) = note.get_mut("data").and_then(Value::as_object_mut) else {
+        report.issue(IssueKind::MissingRoot, path, None, "笔记中没有节点数据".to_string(), false);
+        return false;
+    };
+    let mut changed = false;
+
+    // 去掉指向不存在节点的引用
+    let existing: HashSet<String> = nodes.keys().cloned().collect();
+    for (id, node) in nodes.iter_mut() {
+        let dangling: Vec<String> = children_of(node).into_iter().filter(|child| !existing.contains(child)).collect();
+        for child in &dangling {
+            report.issue(IssueKind::DanglingChild, path, Some(id), format!("子节点 {} 不存在", child), repair);
+        }
+        if repair && !dangling.is_empty() {
+            if let Some(children) = node.get_mut("children").and_then(Value::as_array_mut) {
+                children.retain(|child| child.as_str().is_some_and(|child| existing.contains(child)));
+            }
+            changed = true;
+        }
+    }
+
+    if !nodes.contains_key(&root_id) {
+        report.issue(IssueKind::MissingRoot, path, Some(&root_id), "根节点不存在".to_string(), false);
+        return changed;
+    }
+
+    // 每次找出一棵不可达子树的顶端（处在环中时从当前节点断开），修复时挂到根节点下
+    let mut covered = reachable_from(nodes, &root_id);
+    while let Some(start) = nodes.keys().find(|id| !covered.contains(*id)).cloned() {
+        let mut top = start;
+        let mut visited = HashSet::from([top.clone()]);
+        while let Some(parent) = parent_of(nodes, &top) {
+            if !visited.insert(parent.clone()) {
+                break;
+            }
+            top = parent;
+        }
+        report.issue(IssueKind::UnreachableNode, path, Some(&top), "节点无法从根节点到达".to_string(), repair);
+        if repair {
+            for node in nodes.values_mut() {
+                if let Some(children) = node.get_mut("children").and_then(Value::as_array_mut) {
+                    children.retain(|child| child.as_str() != Some(top.as_str()));
+                }
+            }
+            if let Some(root) = nodes.get_mut(&root_id).and_then(Value::as_object_mut) {
+                let children = root.entry("children").or_insert_with(|| Value::Array(Vec::new()));
+                if let Some(children) = children.as_array_mut() {
+                    children.push(Value::String(top.clone()));
+                }
+            }
+            changed = true;
+        }
+        covered.extend(reachable_from(nodes, &top));
+    }
+    changed
+}
+
+impl Workspace {
+    // 检查工作区的完整性，repair 为 true 时修复可以安全修复的问题
+    pub fn check_workspace(&self, repair: bool) -> Result<CheckReport, String> {
+        if self.vault().is_enabled() && !self.vault().status().unlocked {
+            return Err("工作区已锁定，请先解锁".to_string());
+        }
+        if repair {
+            // 修复单独成为一次历史提交
+            self.flush_history()?;
+        }
+
+        let scanned = scan_workspace(self.root());
+        let mut report = CheckReport::default();
+
+        for (dir, error) in &scanned.unreadable_dirs {
+            report.issue(IssueKind::UnreadableDirectory, &self.relative_path(dir), None, error.clone(), false);
+        }
+
+        // 写入中断留下的临时文件可以删除；重新加密的临时文件在解锁时自动处理，只报告
+        for path in &scanned.temp_files {
+            let relative = self.relative_path(path);
+            let is_temp = relative.ends_with(TEMP_SUFFIX);
+            let repaired = repair && is_temp && fs::remove_file(path).is_ok();
+            let detail = if is_temp { "写入中断留下的临时文件" } else { "更换口令中断留下的文件，解锁后会自动处理" };
+            report.issue(IssueKind::StrayTempFile, &relative, None, detail.to_string(), repaired);
+        }
+
+        // 笔记ID到使用它的文件
+        let mut ids: BTreeMap<String, Vec<(PathBuf, String)>> = BTreeMap::new();
+        let mut repaired_notes = 0;
+        for note_path in &scanned.notes {
+            let relative = self.relative_path(note_path);
+            report.notes_checked += 1;
+            let bytes = match self.read_note_bytes(note_path) {
+                Ok(bytes) if note_lock::is_locked(&bytes) => {
+                    report.locked_notes += 1;
+                    continue;
+                }
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    report.issue(IssueKind::UnreadableNote, &relative, None, e, false);
+                    continue;
+                }
+            };
+            let mut note: Value = match serde_json::from_slice(&bytes) {
+                Ok(note @ Value::Object(_)) => note,
+                parsed => {
+                    let detail = match parsed {
+                        Err(e) => format!("笔记内容不是有效的JSON: {}", e),
+                        Ok(_) => "笔记内容不是JSON对象".to_string(),
+                    };
+                    // 改名隔离，保留原内容以便手动恢复
+                    let mut corrupt = note_path.as_os_str().to_os_string();
+                    corrupt.push(CORRUPT_SUFFIX);
+                    let repaired = repair && !Path::new(&corrupt).exists() && fs::rename(note_path, &corrupt).is_ok();
+                    report.issue(IssueKind::InvalidJson, &relative, None, detail, repaired);
+                    repaired_notes += usize::from(repaired);
+                    continue;
+                }
+            };
+
+            if let Some(id) = note.get("id").and_then(Value::as_str) {
+                ids.entry(id.to_string()).or_default().push((note_path.clone(), relative.clone()));
+            }
+            if check_note_tree(&mut report, &relative, &mut note, repair) {
+                self.write_note_value(note_path, &note)?;
+                repaired_notes += 1;
+            }
+        }
+
+        // 重复的笔记ID改为由文件位置决定的ID，位置与ID相符的那篇笔记保持不变
+        for (id, paths) in ids.into_iter().filter(|(_, paths)| paths.len() > 1) {
+            for (note_path, relative) in paths {
+                let Some(expected) = expected_note_id(&relative).filter(|expected| *expected != id) else {
+                    continue;
+                };
+                if repair {
+                    let mut note: Value = serde_json::from_slice(&self.read_note_bytes(&note_path)?)
+                        .map_err(|e| format!("笔记内容不是有效的JSON: {}", e))?;
+                    if let Some(object) = note.as_object_mut() {
+                        object.insert("id".to_string(), Value::String(expected));
+                    }
+                    self.write_note_value(&note_path, &note)?;
+                    repaired_notes += 1;
+                }
+                report.issue(IssueKind::DuplicateNoteId, &relative, None, format!("笔记ID {} 被多个文件使用", id), repair);
+            }
+        }
+
+        if repaired_notes > 0 {
+            self.record_change(format!("修复 {} 篇笔记", repaired_notes));
+            self.flush_history()?;
+        }
+        backend_log!("[后端] 检查工作区完成: {} 篇笔记，{} 个问题", report.notes_checked, report.issues.len());
+        Ok(report)
+    }
+
+    fn write_note_value(&self, note_path: &Path, note: &Value) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(note).map_err(|e| format!("无法序列化笔记内容: {}", e))?;
+        self.write_note_bytes(note_path, content.as_bytes())
+    }
+}
+
+// 检查工作区完整性
+#[tauri::command]
+pub async fn check_workspace(state: State<'_, WorkspaceState>) -> Result<CheckReport, String> {
+    state.run(move |workspace| workspace.check_workspace(false)).await
+}
+
+// 检查并修复工作区
+#[tauri::command]
+pub async fn repair_workspace(state: State<'_, WorkspaceState>) -> Result<CheckReport, String> {
+    state.run(move |workspace| workspace.check_workspace(true)).await
+}
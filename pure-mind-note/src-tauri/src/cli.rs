@@ -0,0 +1,316 @@
+use std::{collections::HashMap, fs, io::Write, sync::Arc};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::archive::ArchiveSelection;
+use crate::ordering::SortMode;
+use crate::outline::note_to_markdown;
+use crate::rpc;
+use crate::workspace::Workspace;
+use crate::{set_log_output, LogOutput};
+
+// 工作区路径和口令的环境变量，便于在 cron 等脚本中使用
+pub const WORKSPACE_ENV: &str = "PMN_WORKSPACE";
+pub const PASSPHRASE_ENV: &str = "PMN_PASSPHRASE";
+
+pub const USAGE: &str = "\
+用法: pmn [--workspace <目录>] [--json] [--verbose] <命令> [参数]
+      pmn [--workspace <目录>] [--verbose] --stdio-server
+
+命令:
+  list [--category <分类>]                        列出笔记
+  categories                                      列出分类和子分类
+  cat <笔记路径>                                  输出笔记（Markdown 大纲，--json 时为原始内容）
+  search <关键词>...                              搜索节点文字和标签
+  add-node <笔记路径> <文字> [--parent <节点ID>] [--tag <标签>]... [--index <位置>]
+                                                  添加节点，默认添加到根节点下
+  capture <文字> [--tag <标签>]...                记录到收集箱
+  export [<笔记路径>...] [--format markdown|json|pmn] [--output <文件>]
+                                                  导出笔记；pmn 格式导出归档，不指定笔记时导出整个工作区
+  check [--repair]                                检查工作区，发现未修复的问题时退出码为 1
+
+--stdio-server 在标准输入输出上提供 JSON-RPC 2.0 服务（每行一条消息），
+方法有 list、read、search、addNode 和 export，笔记文件变化时发出 notesChanged 通知。
+
+工作区路径也可以通过环境变量 PMN_WORKSPACE 指定，加密工作区的口令通过 PMN_PASSPHRASE 提供。";
+
+// 命令行参数：位置参数和选项（选项可以重复）
+struct ParsedArgs {
+    positional: Vec<String>,
+    options: HashMap<String, Vec<String>>,
+}
+
+impl ParsedArgs {
+    // value_options 中的选项带一个值，其余以 -- 开头的参数视为开关
+    fn parse(args: &[String], value_options: &[&str]) -> Result<Self, String> {
+        let mut parsed = ParsedArgs { positional: Vec::new(), options: HashMap::new() };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--" {
+                parsed.positional.extend(iter.by_ref().cloned());
+                break;
+            }
+            let Some(name) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg.clone());
+                continue;
+            };
+            let (name, inline) = match name.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (name, None),
+            };
+            let value = if value_options.contains(&name) {
+                match inline {
+                    Some(value) => value,
+                    None => iter.next().cloned().ok_or_else(|| format!("选项 --{} 缺少值", name))?,
+                }
+            } else if inline.is_some() {
+                return Err(format!("选项 --{} 不接受值", name));
+            } else {
+                String::new()
+            };
+            parsed.options.entry(name.to_string()).or_default().push(value);
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.options.get(name).and_then(|values| values.last()).map(String::as_str)
+    }
+
+    fn values(&self, name: &str) -> Vec<String> {
+        self.options.get(name).cloned().unwrap_or_default()
+    }
+
+    // 只允许出现 allowed 中的选项
+    fn check_options(&self, allowed: &[&str]) -> Result<(), String> {
+        match self.options.keys().find(|name| !allowed.contains(&name.as_str())) {
+            Some(name) => Err(format!("未知的选项: --{}", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+// 打开工作区，加密的工作区用环境变量中的口令解锁
+pub fn open_workspace(path: Option<&str>) -> Result<Workspace, String> {
+    let path = match path {
+        Some(path) => path.to_string(),
+        None => std::env::var(WORKSPACE_ENV).map_err(|_| format!("请通过 --workspace 或环境变量 {} 指定工作区", WORKSPACE_ENV))?,
+    };
+    let workspace = Workspace::open(&path)?;
+    if workspace.vault().is_enabled() {
+        let passphrase = std::env::var(PASSPHRASE_ENV).map_err(|_| format!("工作区已加密，请通过环境变量 {} 提供口令", PASSPHRASE_ENV))?;
+        workspace.unlock(&passphrase)?;
+    }
+    Ok(workspace)
+}
+
+fn write_json(out: &mut dyn Write, value: &impl Serialize) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| format!("无法序列化输出: {}", e))?;
+    writeln!(out, "{}", json).map_err(|e| format!("无法写入输出: {}", e))
+}
+
+fn write_line(out: &mut dyn Write, line: &str) -> Result<(), String> {
+    writeln!(out, "{}", line).map_err(|e| format!("无法写入输出: {}", e))
+}
+
+// 执行命令行，返回退出码；args 不包括程序名
+pub fn run(args: &[String], out: &mut dyn Write) -> Result<i32, String> {
+    // 全局选项出现在命令之前
+    let mut command_index = 0;
+    while args.get(command_index).is_some_and(|arg| arg.starts_with("--")) {
+        command_index += if args[command_index] == "--workspace" { 2 } else { 1 };
+    }
+    let (global, rest) = args.split_at(command_index.min(args.len()));
+    let global = ParsedArgs::parse(global, &["workspace"])?;
+    global.check_options(&["workspace", "json", "verbose", "help", "stdio-server"])?;
+    if global.flag("stdio-server") && !global.flag("help") {
+        if !rest.is_empty() {
+            return Err("--stdio-server 不能与命令一起使用".to_string());
+        }
+        // 标准输出只用于协议消息
+        set_log_output(if global.flag("verbose") { LogOutput::Stderr } else { LogOutput::Off });
+        let workspace = Arc::new(open_workspace(global.value("workspace"))?);
+        let result = rpc::serve(workspace.clone(), std::io::stdin().lock(), std::io::stdout());
+        workspace.close();
+        return result.map(|_| 0);
+    }
+    let Some((command, rest)) = rest.split_first() else {
+        write_line(out, USAGE)?;
+        return Ok(if global.flag("help") { 0 } else { 2 });
+    };
+    if command == "help" || global.flag("help") {
+        write_line(out, USAGE)?;
+        return Ok(0);
+    }
+
+    // 标准输出用于命令结果，日志只在 --verbose 时写到标准错误
+    set_log_output(if global.flag("verbose") { LogOutput::Stderr } else { LogOutput::Off });
+    let json = global.flag("json");
+    let workspace = open_workspace(global.value("workspace"))?;
+    let result = run_command(&workspace, command, rest, json, out);
+    workspace.close();
+    result
+}
+
+fn run_command(workspace: &Workspace, command: &str, args: &[String], json: bool, out: &mut dyn Write) -> Result<i32, String> {
+    match command {
+        "list" => {
+            let args = ParsedArgs::parse(args, &["category"])?;
+            args.check_options(&["category"])?;
+            let mut notes = workspace.get_all_notes(SortMode::default())?;
+            if let Some(category) = args.value("category") {
+                notes.retain(|note| note.category_id == category);
+            }
+            if json {
+                write_json(out, &notes)?;
+            } else {
+                for note in notes {
+                    let locked = if note.locked { " [已加锁]" } else { "" };
+                    write_line(out, &format!("{}\t{}{}", note.path, note.title, locked))?;
+                }
+            }
+        }
+        "categories" => {
+            ParsedArgs::parse(args, &[])?.check_options(&[])?;
+            let categories = workspace.get_all_categories(SortMode::default())?;
+            if json {
+                write_json(out, &categories)?;
+            } else {
+                for category in categories {
+                    write_line(out, &category.name)?;
+                    for sub_category in category.sub_categories {
+                        write_line(out, &format!("  {}", sub_category.name))?;
+                    }
+                }
+            }
+        }
+        "cat" => {
+            let args = ParsedArgs::parse(args, &[])?;
+            args.check_options(&[])?;
+            let [path] = args.positional.as_slice() else {
+                return Err("用法: pmn cat <笔记路径>".to_string());
+            };
+            let content = workspace.read_note(path)?;
+            if json {
+                write_line(out, &content)?;
+            } else {
+                let note: Value = serde_json::from_str(&content).map_err(|e| format!("笔记内容不是有效的JSON: {}", e))?;
+                write!(out, "{}", note_to_markdown(&note)).map_err(|e| format!("无法写入输出: {}", e))?;
+            }
+        }
+        "search" => {
+            let args = ParsedArgs::parse(args, &[])?;
+            args.check_options(&[])?;
+            if args.positional.is_empty() {
+                return Err("用法: pmn search <关键词>...".to_string());
+            }
+            let hits = workspace.search_notes(&args.positional.join(" "))?;
+            if json {
+                write_json(out, &hits)?;
+            } else {
+                for hit in &hits {
+                    write_line(out, &format!("{}#{}\t{}", hit.path, hit.node_id, hit.text))?;
+                }
+            }
+            // 与 grep 一致，没有结果时退出码为 1
+            return Ok(if hits.is_empty() { 1 } else { 0 });
+        }
+        "add-node" => {
+            let args = ParsedArgs::parse(args, &["parent", "tag", "index"])?;
+            args.check_options(&["parent", "tag", "index"])?;
+            let [path, text] = args.positional.as_slice() else {
+                return Err("用法: pmn add-node <笔记路径> <文字> [--parent <节点ID>] [--tag <标签>]... [--index <位置>]".to_string());
+            };
+            let parent = match args.value("parent") {
+                Some(parent) => parent.to_string(),
+                None => workspace.root_node_id(path)?,
+            };
+            let index = match args.value("index") {
+                Some(index) => Some(index.parse::<usize>().map_err(|_| format!("无效的位置: {}", index))?),
+                None => None,
+            };
+            let mut data = Map::new();
+            data.insert("text".to_string(), json!(text));
+            let tags = args.values("tag");
+            if !tags.is_empty() {
+                data.insert("tag".to_string(), json!(tags));
+            }
+            let node = workspace.add_node(path, &parent, data, index)?;
+            if json {
+                write_json(out, &node)?;
+            } else {
+                write_line(out, &node.id)?;
+            }
+        }
+        "capture" => {
+            let args = ParsedArgs::parse(args, &["tag"])?;
+            args.check_options(&["tag"])?;
+            if args.positional.is_empty() {
+                return Err("用法: pmn capture <文字> [--tag <标签>]...".to_string());
+            }
+            let node = workspace.append_to_inbox(&args.positional.join(" "), &args.values("tag"))?;
+            if json {
+                write_json(out, &node)?;
+            } else {
+                write_line(out, &node.id)?;
+            }
+        }
+        "export" => {
+            let args = ParsedArgs::parse(args, &["format", "output"])?;
+            args.check_options(&["format", "output"])?;
+            let output = args.value("output");
+            match args.value("format").unwrap_or("markdown") {
+                "pmn" => {
+                    let output = output.ok_or_else(|| "导出归档时必须指定 --output".to_string())?;
+                    let selection = ArchiveSelection { categories: Vec::new(), notes: args.positional.clone() };
+                    let manifest = workspace.export_archive(output, &selection)?;
+                    if json {
+                        write_json(out, &manifest)?;
+                    } else {
+                        write_line(out, &format!("已导出 {} 篇笔记", manifest.notes.len()))?;
+                    }
+                    return Ok(0);
+                }
+                format @ ("markdown" | "json") => {
+                    if args.positional.is_empty() {
+                        return Err("请指定要导出的笔记".to_string());
+                    }
+                    let mut exported = Vec::new();
+                    for path in &args.positional {
+                        exported.push(match format {
+                            "markdown" => workspace.export_markdown(path)?,
+                            _ => format!("{}\n", workspace.read_note(path)?),
+                        });
+                    }
+                    let content = exported.join("\n");
+                    match output {
+                        Some(output) => fs::write(output, content).map_err(|e| format!("无法写入文件: {}", e))?,
+                        None => write!(out, "{}", content).map_err(|e| format!("无法写入输出: {}", e))?,
+                    }
+                }
+                format => return Err(format!("不支持的导出格式: {}", format)),
+            }
+        }
+        "check" => {
+            let args = ParsedArgs::parse(args, &[])?;
+            args.check_options(&["repair"])?;
+            let report = workspace.check_workspace(args.flag("repair"))?;
+            if json {
+                write_json(out, &report)?;
+            } else {
+                write_line(out, &format!("检查了 {} 篇笔记，{} 篇已加锁", report.notes_checked, report.locked_notes))?;
+                for issue in &report.issues {
+                    let status = if issue.repaired { "已修复" } else { "未修复" };
+                    write_line(out, &format!("[{}] {}: {}", status, issue.path, issue.detail))?;
+                }
+            }
+            return Ok(if report.issues.iter().all(|issue| issue.repaired) { 0 } else { 1 });
+        }
+        _ => return Err(format!("未知的命令: {}\n\n{}", command, USAGE)),
+    }
+    Ok(0)
+}
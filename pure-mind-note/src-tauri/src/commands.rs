@@ -1,16 +1,29 @@
 use std::{fs, path::{Path, PathBuf}};
 use serde::{Deserialize, Serialize};
-use std::io::Write;
+use rayon::prelude::*;
+use tauri::State;
+
+use crate::metadata::{self, CategoryMetadata, CategoryMetadataUpdate};
+use crate::naming;
+use crate::note_format;
+use crate::note_lock;
+use crate::ordering::{self, SortKey, SortMode};
+use crate::workspace::{write_atomic, Workspace, WorkspaceState};
 
 // 定义笔记信息结构体
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NoteInfo {
     pub id: String,
     pub title: String,
+    // 相对于工作区根目录的路径，以 "/" 分隔
     pub path: String,
     pub category_id: String,
     pub sub_category_id: Option<String>,
     pub last_updated: String,
+    // 在所在目录自定义顺序中的位置
+    pub position: Option<i64>,
+    // 笔记是否单独加了密码锁，加锁的笔记标题显示为文件名
+    pub locked: bool,
 }
 
 // 定义分类信息结构体
@@ -20,6 +33,10 @@ pub struct CategoryInfo {
     pub name: String,
     pub sub_categories: Vec<SubCategoryInfo>,
     pub created_time: String,
+    pub description: Option<String>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub position: Option<i64>,
 }
 
 // 定义子分类信息结构体
@@ -29,354 +46,661 @@ pub struct SubCategoryInfo {
     pub name: String,
     pub parent_id: String,
     pub created_time: String,
+    pub description: Option<String>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub position: Option<i64>,
 }
 
-// 读取笔记内容
-#[tauri::command]
-pub fn read_note(path: String) -> Result<String, String> {
-    println!("[后端] 读取笔记文件: {}", path);
-    match fs::read_to_string(&path) {
-        Ok(content) => {
-            println!("[后端] 成功读取文件，内容长度: {} 字符", content.len());
-            Ok(content)
-        },
-        Err(e) => {
-            println!("[后端] 读取文件失败: {}", e);
-            Err(format!("无法读取笔记: {}", e))
-        },
+// 笔记文件中只需要读取标题，其余内容在解析时跳过
+#[derive(Deserialize)]
+struct NoteHeader {
+    title: Option<String>,
+}
+
+// 从笔记内容中读取原始标题，文件名只是经过转换的安全名称；加密且未解锁时无法读取
+pub(crate) fn note_title(content: &[u8]) -> Option<String> {
+    serde_json::from_slice::<NoteHeader>(content).ok()?.title
+}
+
+// 目录或文件名，无法转换为字符串时使用 "unknown"
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+// 文件系统记录的创建时间，元数据中没有创建时间时使用
+fn fs_created_time(path: &Path) -> String {
+    fs::metadata(path)
+        .and_then(|meta| meta.created())
+        .map(|time| format!("{:?}", time))
+        .unwrap_or_else(|_| "未知".to_string())
+}
+
+// 扫描阶段找到的笔记文件，之后再并行读取其内容
+struct NoteEntry {
+    path: PathBuf,
+    category_id: String,
+    sub_category_id: Option<String>,
+    position: Option<i64>,
+}
+
+impl NoteEntry {
+    fn new(path: PathBuf, category_id: &str, sub_category_id: Option<&str>, folder_meta: &CategoryMetadata) -> Self {
+        let file_stem = path.file_stem().and_then(|name| name.to_str()).unwrap_or("未命名笔记");
+        Self {
+            position: folder_meta.note_position(file_stem),
+            path,
+            category_id: category_id.to_string(),
+            sub_category_id: sub_category_id.map(|id| id.to_string()),
+        }
+    }
+
+    fn into_info(self, workspace: &Workspace) -> NoteInfo {
+        // 文件名（不含扩展名）作为ID的一部分
+        let file_stem = self.path.file_stem()
+            .and_then(|name| name.to_str())
+            .unwrap_or("未命名笔记")
+            .to_string();
+
+        // 生成唯一的笔记ID，包含分类和子分类信息；分类下的直接笔记子分类部分为空
+        let id = format!("{}#{}#{}", self.category_id, self.sub_category_id.as_deref().unwrap_or(""), file_stem);
+
+        let content = workspace.read_note_bytes(&self.path).ok();
+        let locked = content.as_deref().is_some_and(note_lock::is_locked);
+
+        NoteInfo {
+            id,
+            title: content.as_deref().and_then(note_title).unwrap_or(file_stem),
+            path: workspace.relative_path(&self.path),
+            category_id: self.category_id,
+            sub_category_id: self.sub_category_id,
+            last_updated: fs::metadata(&self.path)
+                .and_then(|meta| meta.modified())
+                .map(|time| format!("{:?}", time))
+                .unwrap_or_else(|_| "未知".to_string()),
+            position: self.position,
+            locked,
+        }
     }
 }
 
-// 保存笔记内容
-#[tauri::command]
-pub fn save_note(path: String, content: String) -> Result<(), String> {
-    println!("[后端] 保存笔记到文件: {}", path);
-    println!("[后端] 保存内容长度: {} 字符", content.len());
-    
-    // 确保目录存在
-    if let Some(parent) = Path::new(&path).parent() {
-        println!("[后端] 确保目录存在: {:?}", parent);
-        fs::create_dir_all(parent).map_err(|e| format!("无法创建目录: {}", e))?;
+// 笔记的附件保存在与笔记同名的 "<笔记文件名>.assets" 目录中
+pub const ASSETS_SUFFIX: &str = ".assets";
+
+// 判断路径是否为子分类目录（附件目录不是子分类）
+pub(crate) fn is_subcategory_dir(path: &Path) -> bool {
+    path.is_dir() && !path.to_string_lossy().ends_with(ASSETS_SUFFIX)
+}
+
+// 解析笔记ID "分类#子分类#文件名" 或 "分类##文件名"，返回 (分类, 子分类, 文件名)；
+// 只按前两个 '#' 分割，旧版本创建的文件名中可能含有 '#'
+pub(crate) fn parse_note_id(note_id: &str) -> Result<(&str, Option<&str>, &str), String> {
+    let mut parts = note_id.splitn(3, '#');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(category_id), Some(sub_id), Some(file_stem)) if !category_id.is_empty() && !file_stem.is_empty() => {
+            Ok((category_id, (!sub_id.is_empty()).then_some(sub_id), file_stem))
+        }
+        _ => Err(format!("无效的笔记ID格式: {}", note_id)),
     }
-    
-    // 写入文件
-    let mut file = fs::File::create(&path).map_err(|e| format!("无法创建文件: {}", e))?;
-    file.write_all(content.as_bytes()).map_err(|e| format!("无法写入文件: {}", e))?;
-    
-    println!("[后端] 文件保存成功: {}", path);
-    Ok(())
 }
 
-// 获取所有笔记
-#[tauri::command]
-pub fn get_all_notes(data_dir: String) -> Result<Vec<NoteInfo>, String> {
-    println!("[后端] 开始扫描笔记，数据目录: {}", data_dir);
-    let mut notes = Vec::new();
-    let base_path = PathBuf::from(&data_dir);
-    
-    // 确保数据目录存在
-    if !base_path.exists() {
-        println!("[后端] 数据目录不存在，创建目录: {}", data_dir);
-        fs::create_dir_all(&base_path).map_err(|e| format!("无法创建数据目录: {}", e))?;
-        return Ok(notes);
+// 判断路径是否为笔记文件
+pub(crate) fn is_note_file(path: &Path) -> bool {
+    path.is_file()
+        && path.extension().and_then(|ext| ext.to_str()) == Some("json")
+        && !metadata::is_metadata_file(path)
+}
+
+impl Workspace {
+    // 读取笔记内容（已加密的笔记会被解密）
+    pub fn read_note(&self, path: &str) -> Result<String, String> {
+        let note_path = self.resolve(path)?;
+        backend_log!("[后端] 读取笔记文件: {}", note_path.display());
+        let content = self.read_note_bytes(&note_path)
+            .and_then(|bytes| {
+                if note_lock::is_locked(&bytes) {
+                    return Err("笔记已加锁，请输入密码".to_string());
+                }
+                String::from_utf8(bytes).map_err(|e| format!("无法读取笔记: {}", e))
+            })
+            .map(note_format::upgrade_for_read);
+        match content {
+            Ok(content) => {
+                backend_log!("[后端] 成功读取文件，内容长度: {} 字符", content.len());
+                Ok(content)
+            },
+            Err(e) => {
+                backend_log!("[后端] 读取文件失败: {}", e);
+                Err(e)
+            },
+        }
+    }
+
+    // 保存笔记内容（启用加密时写入密文）
+    pub fn save_note(&self, path: &str, content: &str) -> Result<(), String> {
+        let _guard = self.lock_edits();
+        self.save_note_locked(path, content)
+    }
+
+    // 同 save_note，调用者已持有 lock_edits
+    pub(crate) fn save_note_locked(&self, path: &str, content: &str) -> Result<(), String> {
+        let note_path = self.resolve(path)?;
+        backend_log!("[后端] 保存笔记到文件: {}", note_path.display());
+        backend_log!("[后端] 保存内容长度: {} 字符", content.len());
+
+        // 加锁的笔记只能通过 save_locked_note 保存，避免以明文覆盖
+        if note_path.is_file() && self.read_note_bytes(&note_path).is_ok_and(|bytes| note_lock::is_locked(&bytes)) {
+            return Err("笔记已加锁，请输入密码".to_string());
+        }
+
+        // 写入时标记为当前格式版本，新版本应用的笔记格式不能保存；非 JSON 内容原样保存
+        let upgraded = match serde_json::from_str::<serde_json::Value>(content) {
+            Ok(_) => note_format::upgrade_content(content)?,
+            Err(_) => None,
+        };
+        let content = upgraded.as_deref().unwrap_or(content);
+
+        self.write_note_bytes(&note_path, content.as_bytes())?;
+        let label = self.change_label(&note_path, note_title(content.as_bytes()));
+        self.record_change(format!("更新笔记《{}》", label));
+        if let Err(e) = self.discard_draft_at(&note_path) {
+            backend_log!("[后端] {}", e);
+        }
+
+        backend_log!("[后端] 文件保存成功: {}", note_path.display());
+        Ok(())
+    }
+
+    // 读取笔记文件并去掉工作区加密层，返回的内容可能仍是加锁的
+    pub fn read_note_bytes(&self, note_path: &Path) -> Result<Vec<u8>, String> {
+        let bytes = fs::read(note_path).map_err(|e| format!("无法读取笔记: {}", e))?;
+        self.vault().open(&bytes)
     }
-    
-    // 遍历分类目录
-    for category_entry in fs::read_dir(&base_path).map_err(|e| format!("无法读取目录: {}", e))? {
-        if let Ok(category_dir) = category_entry {
-            let category_path = category_dir.path();
-            
-            // 确保这是一个目录
-            if category_path.is_dir() {
-                let category_id = category_path.file_name()
-                    .and_then(|name| name.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-                
-                // 遍历子分类目录
-                for subcategory_entry in fs::read_dir(&category_path).map_err(|e| format!("无法读取子目录: {}", e))? {
-                    if let Ok(subcategory_dir) = subcategory_entry {
-                        let subcategory_path = subcategory_dir.path();
-                        
-                        // 如果是目录，则为子分类
-                        if subcategory_path.is_dir() {
-                            let subcategory_id = subcategory_path.file_name()
-                                .and_then(|name| name.to_str())
-                                .unwrap_or("unknown")
-                                .to_string();
-                            
-                            // 读取子分类目录中的所有JSON文件
-                            for note_entry in fs::read_dir(&subcategory_path).map_err(|e| format!("无法读取笔记: {}", e))? {
-                                if let Ok(note_file) = note_entry {
-                                    let note_path = note_file.path();
-                                    
-                                    // 确保这是一个JSON文件
-                                    if note_path.is_file() && note_path.extension().and_then(|ext| ext.to_str()) == Some("json") {
-                                        // 尝试解析文件名作为标题
-                                        let title = note_path.file_stem()
-                                            .and_then(|name| name.to_str())
-                                            .unwrap_or("未命名笔记")
-                                            .to_string();
-                                        
-                                        // 生成唯一的笔记ID，包含分类和子分类信息
-                                        let note_id = format!("{}#{}#{}", category_id, subcategory_id, title);
-                                        
-                                        notes.push(NoteInfo {
-                                            id: note_id,
-                                            title,
-                                            path: note_path.to_string_lossy().to_string(),
-                                            category_id: category_id.clone(),
-                                            sub_category_id: Some(subcategory_id.clone()),
-                                            last_updated: fs::metadata(&note_path)
-                                                .and_then(|meta| meta.modified())
-                                                .map(|time| format!("{:?}", time))
-                                                .unwrap_or_else(|_| "未知".to_string()),
-                                        });
-                                    }
-                                }
-                            }
-                        } else if subcategory_path.is_file() && subcategory_path.extension().and_then(|ext| ext.to_str()) == Some("json") {
-                            // 这是分类目录下的直接笔记文件
-                            let title = subcategory_path.file_stem()
-                                .and_then(|name| name.to_str())
-                                .unwrap_or("未命名笔记")
-                                .to_string();
-                            
-                            // 生成唯一的笔记ID，分类下的直接笔记
-                            let note_id = format!("{}##{}", category_id, title);
-                            
-                            notes.push(NoteInfo {
-                                id: note_id,
-                                title,
-                                path: subcategory_path.to_string_lossy().to_string(),
-                                category_id: category_id.clone(),
-                                sub_category_id: None,
-                                last_updated: fs::metadata(&subcategory_path)
-                                    .and_then(|meta| meta.modified())
-                                    .map(|time| format!("{:?}", time))
-                                    .unwrap_or_else(|_| "未知".to_string()),
-                            });
-                        }
+
+    // 加上工作区加密层后写入笔记文件
+    pub fn write_note_bytes(&self, note_path: &Path, content: &[u8]) -> Result<(), String> {
+        // 确保目录存在
+        if let Some(parent) = note_path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("无法创建目录: {}", e))?;
+        }
+        let bytes = self.vault().seal(content)?;
+        write_atomic(note_path, &bytes)?;
+        self.record_written_note(note_path, content);
+        Ok(())
+    }
+
+    // 可以被搜索和链接索引的笔记及其内容，加锁或无法读取的笔记会被跳过
+    pub fn indexable_notes(&self) -> Result<Vec<(NoteInfo, String)>, String> {
+        let notes = self.get_all_notes(SortMode::default())?;
+        let contents = self.indexable_contents(&notes);
+        let mut notes: Vec<Option<NoteInfo>> = notes.into_iter().map(Some).collect();
+        Ok(contents.into_iter().filter_map(|(i, content)| Some((notes[i].take()?, content))).collect())
+    }
+
+    // 同 indexable_notes，使用已经扫描好的笔记列表，返回笔记在列表中的位置和内容
+    pub(crate) fn indexable_contents(&self, notes: &[NoteInfo]) -> Vec<(usize, String)> {
+        notes
+            .par_iter()
+            .enumerate()
+            .filter(|(_, note)| !note.locked)
+            .filter_map(|(i, note)| {
+                Some((i, self.read_indexed_note(&self.resolve(&note.path).ok()?).ok()?))
+            })
+            .collect()
+    }
+
+    // 工作区中所有笔记文件的路径
+    pub fn note_files(&self) -> Result<Vec<PathBuf>, String> {
+        let entries = self.category_dirs()?
+            .iter()
+            .map(|category_path| self.scan_category_notes(category_path))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(entries.into_iter().flatten().map(|entry| entry.path).collect())
+    }
+
+    // 分类和子分类目录中以 suffix 结尾的文件（如写入中断留下的临时文件）
+    pub fn note_files_with_suffix(&self, suffix: &str) -> Result<Vec<PathBuf>, String> {
+        let mut files = Vec::new();
+        for category_path in self.category_dirs()? {
+            let mut dirs = vec![category_path.clone()];
+            for entry in fs::read_dir(&category_path).map_err(|e| format!("无法读取目录: {}", e))?.flatten() {
+                let path = entry.path();
+                if path.is_dir() && self.ensure_inside(&path).is_ok() {
+                    dirs.push(path);
+                }
+            }
+
+            for dir in dirs {
+                for entry in fs::read_dir(&dir).map_err(|e| format!("无法读取目录: {}", e))?.flatten() {
+                    let path = entry.path();
+                    if path.is_file() && path.to_string_lossy().ends_with(suffix) {
+                        files.push(path);
                     }
                 }
             }
         }
+        Ok(files)
     }
-    
-    // 按创建时间排序（最新的在前）
-    notes.sort_by(|a, b| {
-        // 尝试解析时间字符串进行比较
-        let time_a = fs::metadata(&a.path)
-            .and_then(|meta| meta.created())
-            .unwrap_or_else(|_| std::time::SystemTime::UNIX_EPOCH);
-        let time_b = fs::metadata(&b.path)
-            .and_then(|meta| meta.created())
-            .unwrap_or_else(|_| std::time::SystemTime::UNIX_EPOCH);
-        time_b.cmp(&time_a) // 降序排列，最新的在前
-    });
-    
-    println!("[后端] 扫描完成，找到 {} 个笔记", notes.len());
-    for note in &notes {
-        println!("[后端] 笔记: ID={}, 标题={}, 路径={}", note.id, note.title, note.path);
-    }
-    
-    Ok(notes)
-}
 
-// 获取所有分类
-#[tauri::command]
-pub fn get_all_categories(data_dir: String) -> Result<Vec<CategoryInfo>, String> {
-    println!("[后端] 开始扫描分类，数据目录: {}", data_dir);
-    let mut categories = Vec::new();
-    let base_path = PathBuf::from(&data_dir);
-    
-    // 确保数据目录存在
-    if !base_path.exists() {
-        println!("[后端] 数据目录不存在，创建目录: {}", data_dir);
-        fs::create_dir_all(&base_path).map_err(|e| format!("无法创建数据目录: {}", e))?;
-        return Ok(categories);
+    // 列出工作区根目录下的分类目录（跳过指向工作区外的符号链接和 .git 等隐藏目录）
+    fn category_dirs(&self) -> Result<Vec<PathBuf>, String> {
+        Ok(fs::read_dir(self.root())
+            .map_err(|e| format!("无法读取目录: {}", e))?
+            .flatten()
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir() && self.ensure_inside(path).is_ok())
+            .collect())
     }
-    
-    // 遍历分类目录
-    for category_entry in fs::read_dir(&base_path).map_err(|e| format!("无法读取目录: {}", e))? {
-        if let Ok(category_dir) = category_entry {
-            let category_path = category_dir.path();
-            
-            // 确保这是一个目录
-            if category_path.is_dir() {
-                let category_id = category_path.file_name()
-                    .and_then(|name| name.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-                
-                let mut sub_categories = Vec::new();
-                
-                // 遍历子分类目录
-                for subcategory_entry in fs::read_dir(&category_path).map_err(|e| format!("无法读取子目录: {}", e))? {
-                    if let Ok(subcategory_dir) = subcategory_entry {
-                        let subcategory_path = subcategory_dir.path();
-                        
-                        // 如果是目录，则为子分类
-                        if subcategory_path.is_dir() {
-                            let subcategory_id = subcategory_path.file_name()
-                                .and_then(|name| name.to_str())
-                                .unwrap_or("unknown")
-                                .to_string();
-                            
-                            let created_time = fs::metadata(&subcategory_path)
-                                .and_then(|meta| meta.created())
-                                .map(|time| format!("{:?}", time))
-                                .unwrap_or_else(|_| "未知".to_string());
-                            
-                            sub_categories.push(SubCategoryInfo {
-                                id: subcategory_id.clone(),
-                                name: subcategory_id,
-                                parent_id: category_id.clone(),
-                                created_time,
-                            });
-                        }
+
+    // 扫描一个分类目录，收集其中（包括子分类中）的笔记文件
+    fn scan_category_notes(&self, category_path: &Path) -> Result<Vec<NoteEntry>, String> {
+        let category_id = dir_name(category_path);
+        let mut entries = Vec::new();
+
+        // 分类下直接笔记的自定义顺序
+        let category_meta = metadata::read_metadata(category_path);
+
+        // 遍历子分类目录
+        for subcategory_dir in fs::read_dir(category_path).map_err(|e| format!("无法读取子目录: {}", e))?.flatten() {
+            let subcategory_path = subcategory_dir.path();
+            if self.ensure_inside(&subcategory_path).is_err() {
+                backend_log!("[后端] 跳过指向工作区外的路径: {}", subcategory_path.display());
+                continue;
+            }
+
+            // 如果是目录，则为子分类
+            if is_subcategory_dir(&subcategory_path) {
+                let subcategory_id = dir_name(&subcategory_path);
+
+                // 子分类中笔记的自定义顺序
+                let folder_meta = metadata::read_metadata(&subcategory_path);
+
+                // 读取子分类目录中的所有JSON文件
+                for note_file in fs::read_dir(&subcategory_path).map_err(|e| format!("无法读取笔记: {}", e))?.flatten() {
+                    let note_path = note_file.path();
+
+                    // 确保这是一个JSON笔记文件
+                    if is_note_file(&note_path) && self.ensure_inside(&note_path).is_ok() {
+                        entries.push(NoteEntry::new(note_path, &category_id, Some(&subcategory_id), &folder_meta));
                     }
                 }
-                
-                // 按创建时间排序子分类（最新的在前）
-                sub_categories.sort_by(|a, b| {
-                    let time_a = fs::metadata(PathBuf::from(&data_dir).join(&category_id).join(&a.id))
-                        .and_then(|meta| meta.created())
-                        .unwrap_or_else(|_| std::time::SystemTime::UNIX_EPOCH);
-                    let time_b = fs::metadata(PathBuf::from(&data_dir).join(&category_id).join(&b.id))
-                        .and_then(|meta| meta.created())
-                        .unwrap_or_else(|_| std::time::SystemTime::UNIX_EPOCH);
-                    time_b.cmp(&time_a) // 降序排列，最新的在前
-                });
-                
-                let created_time = fs::metadata(&category_path)
-                    .and_then(|meta| meta.created())
-                    .map(|time| format!("{:?}", time))
-                    .unwrap_or_else(|_| "未知".to_string());
-                
-                categories.push(CategoryInfo {
-                    id: category_id.clone(),
-                    name: category_id,
-                    sub_categories,
+            } else if is_note_file(&subcategory_path) {
+                // 这是分类目录下的直接笔记文件
+                entries.push(NoteEntry::new(subcategory_path, &category_id, None, &category_meta));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    // 获取所有笔记，按 sort 指定的方式排序
+    pub fn get_all_notes(&self, sort: SortMode) -> Result<Vec<NoteInfo>, String> {
+        backend_log!("[后端] 开始扫描笔记，数据目录: {}", self.root().display());
+
+        // 并行扫描各个分类目录
+        let entries = self.category_dirs()?
+            .par_iter()
+            .map(|category_path| self.scan_category_notes(category_path))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        // 并行读取笔记标题与修改时间
+        let mut notes: Vec<NoteInfo> = entries
+            .into_par_iter()
+            .flatten()
+            .map(|entry| entry.into_info(self))
+            .collect();
+
+        // 按指定方式排序，默认按创建时间（最新的在前）
+        ordering::sort_by_mode(&mut notes, sort, |note| {
+            SortKey::from_path(&self.root().join(&note.path), &note.title, note.position)
+        });
+
+        backend_log!("[后端] 扫描完成，找到 {} 个笔记", notes.len());
+        Ok(notes)
+    }
+
+    // 读取一个分类目录及其子分类的信息
+    fn scan_category(&self, category_path: &Path, sort: SortMode) -> Result<CategoryInfo, String> {
+        let category_id = dir_name(category_path);
+        let mut sub_categories = Vec::new();
+
+        // 遍历子分类目录
+        for subcategory_dir in fs::read_dir(category_path).map_err(|e| format!("无法读取子目录: {}", e))?.flatten() {
+            let subcategory_path = subcategory_dir.path();
+
+            // 如果是目录，则为子分类
+            if is_subcategory_dir(&subcategory_path) && self.ensure_inside(&subcategory_path).is_ok() {
+                let subcategory_id = dir_name(&subcategory_path);
+
+                // 显示信息保存在元数据中，缺失时使用目录名和文件系统时间
+                let meta = metadata::read_metadata(&subcategory_path);
+                let created_time = meta.created_time.unwrap_or_else(|| fs_created_time(&subcategory_path));
+
+                sub_categories.push(SubCategoryInfo {
+                    name: meta.name.unwrap_or_else(|| subcategory_id.clone()),
+                    id: subcategory_id,
+                    parent_id: category_id.clone(),
                     created_time,
+                    description: meta.description,
+                    color: meta.color,
+                    icon: meta.icon,
+                    position: meta.position,
                 });
             }
         }
+
+        // 排序子分类
+        ordering::sort_by_mode(&mut sub_categories, sort, |sub| {
+            SortKey::from_path(&category_path.join(&sub.id), &sub.name, sub.position)
+        });
+
+        let meta = metadata::read_metadata(category_path);
+        let created_time = meta.created_time.unwrap_or_else(|| fs_created_time(category_path));
+
+        Ok(CategoryInfo {
+            name: meta.name.unwrap_or_else(|| category_id.clone()),
+            id: category_id,
+            sub_categories,
+            created_time,
+            description: meta.description,
+            color: meta.color,
+            icon: meta.icon,
+            position: meta.position,
+        })
+    }
+
+    // 获取所有分类，分类和子分类都按 sort 指定的方式排序
+    pub fn get_all_categories(&self, sort: SortMode) -> Result<Vec<CategoryInfo>, String> {
+        backend_log!("[后端] 开始扫描分类，数据目录: {}", self.root().display());
+
+        // 并行扫描各个分类目录
+        let mut categories = self.category_dirs()?
+            .par_iter()
+            .map(|category_path| self.scan_category(category_path, sort))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        // 排序分类
+        ordering::sort_by_mode(&mut categories, sort, |category| {
+            SortKey::from_path(&self.root().join(&category.id), &category.name, category.position)
+        });
+
+        Ok(categories)
+    }
+
+    // 在 parent 目录下创建一个以安全文件名命名的目录，并记录原始显示名称
+    fn create_named_dir(&self, parent: &[&str], name: &str, kind: &str) -> Result<String, String> {
+        let display_name = naming::validate_display_name(name)?;
+        let dir_name = naming::slugify(&display_name)?;
+
+        let mut parts = parent.to_vec();
+        parts.push(&dir_name);
+        let dir_path = self.resolve_parts(&parts)?;
+
+        // 大小写或 Unicode 规范化不同的同名目录在部分系统上是同一个目录
+        if let Some(parent_path) = dir_path.parent() {
+            if let Some(existing) = naming::find_conflict(parent_path, &dir_name, None) {
+                return Err(format!("{} '{}' 与已有的 '{}' 冲突", kind, display_name, existing));
+            }
+        }
+
+        fs::create_dir_all(&dir_path).map_err(|e| format!("无法创建{}目录: {}", kind, e))?;
+        metadata::write_metadata(&dir_path, &CategoryMetadata::new(display_name.clone()))?;
+        self.record_change(format!("新建{}《{}》", kind, display_name));
+
+        Ok(dir_name)
+    }
+
+    // 创建新分类，返回分类ID（即目录名）
+    pub fn create_category(&self, name: &str) -> Result<String, String> {
+        self.create_named_dir(&[], name, "分类")
+    }
+
+    // 创建新子分类，返回子分类ID（即目录名）
+    pub fn create_subcategory(&self, category_id: &str, name: &str) -> Result<String, String> {
+        self.create_named_dir(&[category_id], name, "子分类")
+    }
+
+    // 创建新笔记：标题转换为安全的文件名，内容中的 id 与生成的笔记ID保持一致
+    pub fn create_note(
+        &self,
+        category_id: &str,
+        sub_category_id: Option<&str>,
+        title: &str,
+        content: &str,
+    ) -> Result<NoteInfo, String> {
+        let title = naming::validate_display_name(title)?;
+        let mut file_stem = naming::slugify(&title)?;
+        // 避免与元数据文件同名
+        if naming::fold_name(&format!("{}.json", file_stem)) == metadata::METADATA_FILE {
+            file_stem.push('_');
+        }
+
+        let file_name = format!("{}.json", file_stem);
+        let (note_path, note_id) = match sub_category_id {
+            Some(sub_id) => (
+                self.resolve_parts(&[category_id, sub_id, &file_name])?,
+                format!("{}#{}#{}", category_id, sub_id, file_stem),
+            ),
+            None => (
+                self.resolve_parts(&[category_id, &file_name])?,
+                format!("{}##{}", category_id, file_stem),
+            ),
+        };
+
+        if let Some(parent) = note_path.parent() {
+            if let Some(existing) = naming::find_conflict(parent, &file_stem, Some("json")) {
+                return Err(format!("笔记 '{}' 与已有的笔记 '{}' 冲突", title, existing));
+            }
+        }
+
+        // 将内容中的 id 和 title 更新为后端确定的值
+        let mut data: serde_json::Value = serde_json::from_str(content)
+            .map_err(|e| format!("笔记内容不是有效的JSON: {}", e))?;
+        if let Some(object) = data.as_object_mut() {
+            object.insert("id".to_string(), note_id.clone().into());
+            object.insert("title".to_string(), title.clone().into());
+        }
+        note_format::migrate_note(&mut data)?;
+        let content = serde_json::to_string_pretty(&data)
+            .map_err(|e| format!("无法序列化笔记内容: {}", e))?;
+
+        let relative_path = self.relative_path(&note_path);
+        self.write_note_bytes(&note_path, content.as_bytes())?;
+        self.record_change(format!("新建笔记《{}》", self.change_label(&note_path, Some(title.clone()))));
+
+        Ok(NoteInfo {
+            id: note_id,
+            position: None,
+            title,
+            path: relative_path,
+            category_id: category_id.to_string(),
+            sub_category_id: sub_category_id.map(|id| id.to_string()),
+            last_updated: fs::metadata(&note_path)
+                .and_then(|meta| meta.modified())
+                .map(|time| format!("{:?}", time))
+                .unwrap_or_else(|_| "未知".to_string()),
+            locked: false,
+        })
+    }
+
+    // 更新分类或子分类的元数据，返回更新后的结果
+    pub fn update_category_metadata(
+        &self,
+        category_id: &str,
+        sub_category_id: Option<&str>,
+        update: CategoryMetadataUpdate,
+    ) -> Result<CategoryMetadata, String> {
+        let dir_path = match sub_category_id {
+            Some(sub_id) => self.resolve_parts(&[category_id, sub_id])?,
+            None => self.resolve_parts(&[category_id])?,
+        };
+        if !dir_path.is_dir() {
+            return Err(format!("分类 '{}' 不存在", sub_category_id.unwrap_or(category_id)));
+        }
+
+        let mut meta = metadata::read_metadata(&dir_path);
+        meta.apply(update)?;
+        metadata::write_metadata(&dir_path, &meta)?;
+        self.record_change(format!("更新分类《{}》的信息", sub_category_id.unwrap_or(category_id)));
+
+        Ok(meta)
+    }
+
+    // 删除分类
+    pub fn delete_category(&self, category_id: &str) -> Result<(), String> {
+        let category_path = self.resolve_parts(&[category_id])?;
+
+        // 检查目录是否存在
+        if !category_path.exists() {
+            return Err(format!("分类 '{}' 不存在", category_id));
+        }
+
+        // 检查目录是否为空（元数据文件不计入）
+        let is_empty = fs::read_dir(&category_path)
+            .map_err(|e| format!("无法读取分类目录: {}", e))?
+            .flatten()
+            .all(|entry| metadata::is_metadata_file(&entry.path()));
+
+        if !is_empty {
+            return Err(format!("分类 '{}' 不为空，请先删除其中的内容", category_id));
+        }
+
+        // 删除目录及其元数据文件
+        fs::remove_dir_all(&category_path)
+            .map_err(|e| format!("无法删除分类目录: {}", e))?;
+        self.discard_drafts_under(&category_path);
+        self.record_change(format!("删除分类《{}》", category_id));
+
+        Ok(())
+    }
+
+    // 删除子分类
+    pub fn delete_subcategory(&self, category_id: &str, sub_category_id: &str) -> Result<(), String> {
+        let subcategory_path = self.resolve_parts(&[category_id, sub_category_id])?;
+
+        // 检查目录是否存在
+        if !subcategory_path.exists() {
+            return Err(format!("子分类 '{}' 不存在", sub_category_id));
+        }
+
+        // 删除目录及其所有内容
+        fs::remove_dir_all(&subcategory_path)
+            .map_err(|e| format!("无法删除子分类目录: {}", e))?;
+        self.discard_drafts_under(&subcategory_path);
+        self.record_change(format!("删除子分类《{}》", sub_category_id));
+
+        Ok(())
+    }
+
+    // 删除笔记
+    pub fn delete_note(&self, note_id: &str) -> Result<(), String> {
+        // 解析笔记ID来获取路径信息
+        let (category_id, subcategory_id, file_stem) = parse_note_id(note_id)?;
+        let file_name = format!("{}.json", file_stem);
+
+        // 构建文件路径
+        let note_path = if let Some(sub_id) = subcategory_id {
+            // 子分类下的笔记
+            self.resolve_parts(&[category_id, sub_id, &file_name])?
+        } else {
+            // 分类下的直接笔记
+            self.resolve_parts(&[category_id, &file_name])?
+        };
+
+        // 检查文件是否存在
+        if !note_path.exists() {
+            return Err(format!("笔记文件不存在: {}", note_path.display()));
+        }
+
+        // 删除文件
+        fs::remove_file(&note_path)
+            .map_err(|e| format!("无法删除笔记文件: {}", e))?;
+        self.record_change(format!("删除笔记《{}》", file_stem));
+        if let Err(e) = self.discard_draft_at(&note_path) {
+            backend_log!("[后端] {}", e);
+        }
+
+        Ok(())
     }
-    
-    // 按创建时间排序分类（最新的在前）
-    categories.sort_by(|a, b| {
-        let time_a = fs::metadata(PathBuf::from(&data_dir).join(&a.id))
-            .and_then(|meta| meta.created())
-            .unwrap_or_else(|_| std::time::SystemTime::UNIX_EPOCH);
-        let time_b = fs::metadata(PathBuf::from(&data_dir).join(&b.id))
-            .and_then(|meta| meta.created())
-            .unwrap_or_else(|_| std::time::SystemTime::UNIX_EPOCH);
-        time_b.cmp(&time_a) // 降序排列，最新的在前
-    });
-    
-    Ok(categories)
+}
+
+// 读取笔记内容，path 为相对于工作区根目录的路径
+#[tauri::command]
+pub async fn read_note(state: State<'_, WorkspaceState>, path: String) -> Result<String, String> {
+    state.run(move |workspace| workspace.read_note(&path)).await
+}
+
+// 保存笔记内容
+#[tauri::command]
+pub async fn save_note(state: State<'_, WorkspaceState>, path: String, content: String) -> Result<(), String> {
+    state.run(move |workspace| workspace.save_note(&path, &content)).await
+}
+
+// 获取所有笔记，sort 缺省时按创建时间排序
+#[tauri::command]
+pub async fn get_all_notes(state: State<'_, WorkspaceState>, sort: Option<SortMode>) -> Result<Vec<NoteInfo>, String> {
+    state.run(move |workspace| workspace.get_all_notes(sort.unwrap_or_default())).await
+}
+
+// 获取所有分类，sort 缺省时按创建时间排序
+#[tauri::command]
+pub async fn get_all_categories(state: State<'_, WorkspaceState>, sort: Option<SortMode>) -> Result<Vec<CategoryInfo>, String> {
+    state.run(move |workspace| workspace.get_all_categories(sort.unwrap_or_default())).await
 }
 
 // 创建新分类
 #[tauri::command]
-pub fn create_category(data_dir: String, name: String) -> Result<String, String> {
-    let category_id = name.clone();
-    let category_path = PathBuf::from(&data_dir).join(&category_id);
-    
-    fs::create_dir_all(category_path).map_err(|e| format!("无法创建分类目录: {}", e))?;
-    
-    Ok(category_id)
+pub async fn create_category(state: State<'_, WorkspaceState>, name: String) -> Result<String, String> {
+    state.run(move |workspace| workspace.create_category(&name)).await
 }
 
 // 创建新子分类
 #[tauri::command]
-pub fn create_subcategory(data_dir: String, category_id: String, name: String) -> Result<String, String> {
-    let subcategory_id = name.clone();
-    let subcategory_path = PathBuf::from(&data_dir).join(&category_id).join(&subcategory_id);
-    
-    fs::create_dir_all(subcategory_path).map_err(|e| format!("无法创建子分类目录: {}", e))?;
-    
-    Ok(subcategory_id)
+pub async fn create_subcategory(state: State<'_, WorkspaceState>, category_id: String, name: String) -> Result<String, String> {
+    state.run(move |workspace| workspace.create_subcategory(&category_id, &name)).await
+}
+
+// 创建新笔记
+#[tauri::command]
+pub async fn create_note(
+    state: State<'_, WorkspaceState>,
+    category_id: String,
+    sub_category_id: Option<String>,
+    title: String,
+    content: String,
+) -> Result<NoteInfo, String> {
+    state.run(move |workspace| workspace.create_note(&category_id, sub_category_id.as_deref(), &title, &content)).await
+}
+
+// 更新分类元数据（显示名称、描述、颜色、图标、排序位置）
+#[tauri::command]
+pub async fn update_category_metadata(
+    state: State<'_, WorkspaceState>,
+    category_id: String,
+    sub_category_id: Option<String>,
+    update: CategoryMetadataUpdate,
+) -> Result<CategoryMetadata, String> {
+    state.run(move |workspace| workspace.update_category_metadata(&category_id, sub_category_id.as_deref(), update)).await
 }
 
 // 删除分类
 #[tauri::command]
-pub fn delete_category(data_dir: String, category_id: String) -> Result<(), String> {
-    let category_path = PathBuf::from(&data_dir).join(&category_id);
-    
-    // 检查目录是否存在
-    if !category_path.exists() {
-        return Err(format!("分类 '{}' 不存在", category_id));
-    }
-    
-    // 检查目录是否为空
-    let is_empty = fs::read_dir(&category_path)
-        .map_err(|e| format!("无法读取分类目录: {}", e))?
-        .next()
-        .is_none();
-    
-    if !is_empty {
-        return Err(format!("分类 '{}' 不为空，请先删除其中的内容", category_id));
-    }
-    
-    // 删除目录
-    fs::remove_dir(&category_path)
-        .map_err(|e| format!("无法删除分类目录: {}", e))?;
-    
-    Ok(())
+pub async fn delete_category(state: State<'_, WorkspaceState>, category_id: String) -> Result<(), String> {
+    state.run(move |workspace| workspace.delete_category(&category_id)).await
 }
 
 // 删除子分类
 #[tauri::command]
-pub fn delete_subcategory(data_dir: String, category_id: String, sub_category_id: String) -> Result<(), String> {
-    let subcategory_path = PathBuf::from(&data_dir).join(&category_id).join(&sub_category_id);
-    
-    // 检查目录是否存在
-    if !subcategory_path.exists() {
-        return Err(format!("子分类 '{}' 不存在", sub_category_id));
-    }
-    
-    // 删除目录及其所有内容
-    fs::remove_dir_all(&subcategory_path)
-        .map_err(|e| format!("无法删除子分类目录: {}", e))?;
-    
-    Ok(())
+pub async fn delete_subcategory(state: State<'_, WorkspaceState>, category_id: String, sub_category_id: String) -> Result<(), String> {
+    state.run(move |workspace| workspace.delete_subcategory(&category_id, &sub_category_id)).await
 }
 
 // 删除笔记
 #[tauri::command]
-pub fn delete_note(data_dir: String, note_id: String) -> Result<(), String> {
-    // 解析笔记ID来获取路径信息
-    // ID格式: "category#subcategory#title" 或 "category##title"
-    let parts: Vec<&str> = note_id.split('#').collect();
-    
-    if parts.len() < 3 {
-        return Err(format!("无效的笔记ID格式: {}", note_id));
-    }
-    
-    let category_id = parts[0];
-    let subcategory_id = if parts[1].is_empty() { None } else { Some(parts[1]) };
-    let title = parts[2];
-    
-    // 构建文件路径
-    let base_path = PathBuf::from(&data_dir);
-    let note_path = if let Some(sub_id) = subcategory_id {
-        // 子分类下的笔记
-        base_path.join(category_id).join(sub_id).join(format!("{}.json", title))
-    } else {
-        // 分类下的直接笔记
-        base_path.join(category_id).join(format!("{}.json", title))
-    };
-    
-    // 检查文件是否存在
-    if !note_path.exists() {
-        return Err(format!("笔记文件不存在: {}", note_path.display()));
-    }
-    
-    // 删除文件
-    fs::remove_file(&note_path)
-        .map_err(|e| format!("无法删除笔记文件: {}", e))?;
-    
-    Ok(())
-} 
\ No newline at end of file
+pub async fn delete_note(state: State<'_, WorkspaceState>, note_id: String) -> Result<(), String> {
+    state.run(move |workspace| workspace.delete_note(&note_id)).await
+}
@@ -0,0 +1,276 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::api::ApiServer;
+use crate::workspace::write_atomic;
+
+// 应用配置目录中的配置文件
+pub const CONFIG_FILE: &str = "app-config.json";
+
+// 当前的配置格式版本，每次调整结构时加一并在 MIGRATIONS 中补充迁移
+pub const CONFIG_VERSION: u64 = 1;
+
+// 配置变化时发给前端的事件
+pub const CONFIG_CHANGED_EVENT: &str = "config-changed";
+
+// 界面主题
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ThemeConfig {
+    // light、dark 或 system
+    pub mode: String,
+    // small、medium 或 large
+    pub font_size: String,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self { mode: "system".to_string(), font_size: "medium".to_string() }
+    }
+}
+
+// 同步设置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncConfig {
+    // 未指定目标目录时使用的同步目录
+    pub folder: Option<String>,
+}
+
+// 本地 HTTP 接口的设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ApiConfig {
+    // 默认关闭，开启后只监听 127.0.0.1
+    pub enabled: bool,
+    pub port: u16,
+    // 请求需要携带的 Bearer 令牌，为空时在首次启动接口时生成
+    pub token: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self { enabled: false, port: 27183, token: String::new() }
+    }
+}
+
+// 应用配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppConfig {
+    pub version: u64,
+    // 前端指定的工作区目录，为空时使用应用数据目录
+    pub workspace_path: String,
+    // 界面语言
+    pub locale: String,
+    pub theme: ThemeConfig,
+    pub sync: SyncConfig,
+    pub api: ApiConfig,
+    // 前端自行使用的其他字段，原样保存
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            workspace_path: String::new(),
+            locale: "zh-CN".to_string(),
+            theme: ThemeConfig::default(),
+            sync: SyncConfig::default(),
+            api: ApiConfig::default(),
+            extra: Map::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    fn validate(&self) -> Result<(), String> {
+        if !["light", "dark", "system"].contains(&self.theme.mode.as_str()) {
+            return Err(format!("未知的主题模式: {}", self.theme.mode));
+        }
+        if !["small", "medium", "large"].contains(&self.theme.font_size.as_str()) {
+            return Err(format!("未知的字体大小: {}", self.theme.font_size));
+        }
+        if self.api.port == 0 {
+            return Err("接口端口不能为 0".to_string());
+        }
+        if self.locale.trim().is_empty() {
+            return Err("界面语言不能为空".to_string());
+        }
+        Ok(())
+    }
+}
+
+// 版本 0：前端直接写入的配置，没有版本号，可能带有存储测试留下的字段
+fn migrate_v0(config: &mut Map<String, Value>) {
+    config.remove("_testTimestamp");
+    if config.get("workspacePath").is_some_and(|path| path == "__test__") {
+        config.insert("workspacePath".to_string(), Value::String(String::new()));
+    }
+}
+
+// 第 n 项把版本 n 的配置迁移到版本 n + 1
+const MIGRATIONS: [fn(&mut Map<String, Value>); CONFIG_VERSION as usize] = [migrate_v0];
+
+// 将任意旧版本的配置迁移到当前版本
+pub fn migrate_config(value: Value) -> Result<AppConfig, String> {
+    let Value::Object(mut config) = value else {
+        return Err("配置文件格式错误".to_string());
+    };
+    let version = config.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > CONFIG_VERSION {
+        // 新版本程序写入的配置，尽量读取已知字段
+        backend_log!("[后端] 配置文件版本 {} 高于当前支持的版本 {}", version, CONFIG_VERSION);
+    }
+    for migration in MIGRATIONS.iter().skip(version as usize) {
+        migration(&mut config);
+    }
+    config.insert("version".to_string(), Value::from(CONFIG_VERSION.max(version)));
+    serde_json::from_value(Value::Object(config)).map_err(|e| format!("配置文件格式错误: {}", e))
+}
+
+// 按 JSON Merge Patch 的规则合并：对象逐键合并，null 删除键，其他值直接替换
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(&key);
+            } else {
+                merge_patch(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+// 作为 Tauri 托管状态保存应用配置
+#[derive(Debug)]
+pub struct ConfigState {
+    file: PathBuf,
+    config: RwLock<AppConfig>,
+}
+
+impl ConfigState {
+    // 读取配置并迁移到当前版本；配置文件不存在时尝试导入旧版前端写入的文件
+    pub fn load(file: impl Into<PathBuf>, legacy: Option<&Path>) -> Self {
+        let file = file.into();
+        let source = if file.exists() {
+            Some(file.clone())
+        } else {
+            legacy.filter(|path| path.exists()).map(Path::to_path_buf)
+        };
+        let config = match &source {
+            Some(source) => match Self::read(source) {
+                Ok(config) => config,
+                Err(e) => {
+                    // 保留损坏的文件以便手动恢复
+                    backend_log!("[后端] 无法读取配置文件 {}: {}", source.display(), e);
+                    let mut corrupt = source.as_os_str().to_os_string();
+                    corrupt.push(".corrupt");
+                    let _ = fs::copy(source, corrupt);
+                    AppConfig::default()
+                }
+            },
+            None => AppConfig::default(),
+        };
+
+        let state = Self { file, config: RwLock::new(config) };
+        // 迁移后的配置立即写回
+        if source.is_some() {
+            if let Err(e) = state.save(&state.read_config()) {
+                backend_log!("[后端] 无法保存配置: {}", e);
+            }
+        }
+        state
+    }
+
+    fn read(path: &Path) -> Result<AppConfig, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("无法读取配置文件: {}", e))?;
+        let value = serde_json::from_str(&content).map_err(|e| format!("配置文件格式错误: {}", e))?;
+        migrate_config(value)
+    }
+
+    fn read_config(&self) -> RwLockReadGuard<'_, AppConfig> {
+        self.config.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write_config(&self) -> RwLockWriteGuard<'_, AppConfig> {
+        self.config.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn save(&self, config: &AppConfig) -> Result<(), String> {
+        if let Some(parent) = self.file.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("无法创建配置目录: {}", e))?;
+        }
+        let content = serde_json::to_string_pretty(config)
+            .map_err(|e| format!("无法序列化配置: {}", e))?;
+        write_atomic(&self.file, content.as_bytes())
+    }
+
+    // 当前配置的副本
+    pub fn get(&self) -> AppConfig {
+        self.read_config().clone()
+    }
+
+    // 合并部分配置并保存，返回更新后的配置；版本号由后端维护
+    pub fn update(&self, patch: Value) -> Result<AppConfig, String> {
+        let mut current = self.write_config();
+        let mut value = serde_json::to_value(&*current).map_err(|e| format!("无法序列化配置: {}", e))?;
+        merge_patch(&mut value, patch);
+        let mut updated: AppConfig = serde_json::from_value(value)
+            .map_err(|e| format!("配置格式错误: {}", e))?;
+        updated.version = current.version;
+        updated.validate()?;
+
+        self.save(&updated)?;
+        *current = updated.clone();
+        Ok(updated)
+    }
+
+    // 本地接口的令牌，尚未生成时生成一个新的并保存
+    pub fn ensure_api_token(&self) -> Result<String, String> {
+        let current = self.get().api.token;
+        if !current.is_empty() {
+            return Ok(current);
+        }
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        self.update(serde_json::json!({ "api": { "token": token } }))?;
+        Ok(token)
+    }
+}
+
+// 获取应用配置
+#[tauri::command]
+pub fn get_config(config: State<'_, ConfigState>) -> AppConfig {
+    config.get()
+}
+
+// 更新应用配置（部分字段），并通知所有窗口；本地接口的设置随之生效
+#[tauri::command]
+pub async fn update_config(
+    app: AppHandle,
+    config: State<'_, ConfigState>,
+    api_server: State<'_, ApiServer>,
+    patch: Value,
+) -> Result<AppConfig, String> {
+    config.update(patch)?;
+    // 开启接口时可能生成了新的令牌
+    api_server.apply(&app);
+    let updated = config.get();
+    let _ = app.emit(CONFIG_CHANGED_EVENT, &updated);
+    Ok(updated)
+}
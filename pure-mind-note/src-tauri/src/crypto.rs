@@ -0,0 +1,452 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, KeyInit, OsRng},
+    AeadCore, XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use zeroize::Zeroizing;
+
+use crate::workspace::{write_atomic, Workspace, WorkspaceState};
+
+// 工作区根目录下保存加密参数的文件
+pub const ENCRYPTION_FILE: &str = ".pmn-encryption.json";
+
+// 更换口令时新密文的临时文件后缀
+pub const REKEY_SUFFIX: &str = ".pmn-rekey";
+
+// 加密笔记文件的文件头，用于区分明文（旧笔记）和密文
+const MAGIC: &[u8] = b"PMNENC1\n";
+
+// 用于校验口令是否正确的已知明文
+const VERIFIER_PLAINTEXT: &[u8] = b"pure-mind-note";
+
+// 默认的自动锁定时间（分钟）
+const DEFAULT_AUTO_LOCK_MINUTES: u64 = 15;
+
+const KEY_LEN: usize = 32;
+pub(crate) const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+pub(crate) type Key = Zeroizing<[u8; KEY_LEN]>;
+
+// 加密参数，口令本身和密钥都不会写入磁盘
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EncryptionHeader {
+    version: u32,
+    kdf: String,
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    cipher: String,
+    // 用派生密钥加密的已知明文
+    verifier: String,
+    auto_lock_minutes: u64,
+}
+
+impl EncryptionHeader {
+    fn new(auto_lock_minutes: u64) -> Self {
+        Self {
+            version: 1,
+            kdf: "argon2id".to_string(),
+            salt: BASE64.encode(random_salt()),
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+            cipher: "xchacha20poly1305".to_string(),
+            verifier: String::new(),
+            auto_lock_minutes,
+        }
+    }
+
+    // 由口令派生密钥
+    fn derive_key(&self, passphrase: &str) -> Result<Key, String> {
+        if self.kdf != "argon2id" || self.cipher != "xchacha20poly1305" {
+            return Err(format!("不支持的加密方式: {} / {}", self.kdf, self.cipher));
+        }
+        let salt = BASE64.decode(&self.salt).map_err(|e| format!("加密参数已损坏: {}", e))?;
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(KEY_LEN))
+            .map_err(|e| format!("加密参数已损坏: {}", e))?;
+        derive_key(passphrase, &salt, params)
+    }
+
+    // 用密钥生成校验值
+    fn set_verifier(&mut self, key: &Key) -> Result<(), String> {
+        self.verifier = BASE64.encode(encrypt(key, VERIFIER_PLAINTEXT)?);
+        Ok(())
+    }
+
+    // 检查密钥是否与校验值匹配
+    fn verify(&self, key: &Key) -> bool {
+        BASE64.decode(&self.verifier)
+            .ok()
+            .and_then(|sealed| decrypt(key, &sealed).ok())
+            .is_some_and(|plain| plain == VERIFIER_PLAINTEXT)
+    }
+}
+
+// 用 Argon2id 由口令派生密钥
+pub(crate) fn derive_key(passphrase: &str, salt: &[u8], params: Params) -> Result<Key, String> {
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| format!("无法派生密钥: {}", e))?;
+    Ok(key)
+}
+
+// 生成随机盐
+pub(crate) fn random_salt() -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+// 加密：文件头 magic + 随机 nonce + 密文
+pub(crate) fn encrypt_with(magic: &[u8], key: &Key, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, plaintext).map_err(|_| "加密失败".to_string())?;
+
+    let mut sealed = Vec::with_capacity(magic.len() + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(magic);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+// 解密 encrypt_with 生成的数据
+pub(crate) fn decrypt_with(magic: &[u8], key: &Key, sealed: &[u8]) -> Result<Vec<u8>, String> {
+    let body = sealed.strip_prefix(magic).ok_or_else(|| "不是加密文件".to_string())?;
+    if body.len() < NONCE_LEN {
+        return Err("加密文件已损坏".to_string());
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(key.as_ref().into())
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "解密失败，文件已损坏或密钥不正确".to_string())
+}
+
+fn encrypt(key: &Key, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    encrypt_with(MAGIC, key, plaintext)
+}
+
+fn decrypt(key: &Key, sealed: &[u8]) -> Result<Vec<u8>, String> {
+    decrypt_with(MAGIC, key, sealed)
+}
+
+// 判断内容是否为加密数据
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+// 加密状态，返回给前端
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+    pub auto_lock_minutes: Option<u64>,
+}
+
+#[derive(Default)]
+struct VaultInner {
+    header: Option<EncryptionHeader>,
+    key: Option<Key>,
+    last_activity: Option<Instant>,
+}
+
+// 工作区的加密状态：加密参数和解锁后保存在内存中的密钥
+#[derive(Default)]
+pub struct Vault {
+    inner: Mutex<VaultInner>,
+}
+
+impl std::fmt::Debug for Vault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Vault").field("status", &self.status()).finish()
+    }
+}
+
+impl Vault {
+    // 读取工作区的加密参数，未启用加密时返回空的 Vault
+    pub fn load(root: &Path) -> Result<Self, String> {
+        let path = root.join(ENCRYPTION_FILE);
+        let header = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|e| format!("无法读取加密参数: {}", e))?;
+            Some(serde_json::from_str(&content).map_err(|e| format!("加密参数已损坏: {}", e))?)
+        } else {
+            None
+        };
+        Ok(Self { inner: Mutex::new(VaultInner { header, ..Default::default() }) })
+    }
+
+    fn lock_inner(&self) -> std::sync::MutexGuard<'_, VaultInner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn status(&self) -> EncryptionStatus {
+        let inner = self.lock_inner();
+        EncryptionStatus {
+            enabled: inner.header.is_some(),
+            unlocked: inner.key.is_some(),
+            auto_lock_minutes: inner.header.as_ref().map(|header| header.auto_lock_minutes),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.lock_inner().header.is_some()
+    }
+
+    // 清除内存中的密钥
+    pub fn lock(&self) {
+        self.lock_inner().key = None;
+    }
+
+    // 超过自动锁定时间没有访问时锁定，返回是否因此被锁定
+    pub fn lock_if_idle(&self) -> bool {
+        let mut inner = self.lock_inner();
+        let idle_limit = match &inner.header {
+            Some(header) if header.auto_lock_minutes > 0 => Duration::from_secs(header.auto_lock_minutes * 60),
+            _ => return false,
+        };
+        let idle = inner.last_activity.is_some_and(|last| last.elapsed() >= idle_limit);
+        if inner.key.is_some() && idle {
+            inner.key = None;
+            backend_log!("[后端] 工作区长时间未使用，已自动锁定");
+            return true;
+        }
+        false
+    }
+
+    // 获取密钥并刷新最近访问时间；未启用加密时返回 None
+    fn active_key(&self) -> Result<Option<Key>, String> {
+        self.lock_if_idle();
+        let mut inner = self.lock_inner();
+        if inner.header.is_none() {
+            return Ok(None);
+        }
+        let key = inner.key.clone().ok_or_else(|| "工作区已加密，请先解锁".to_string())?;
+        inner.last_activity = Some(Instant::now());
+        Ok(Some(key))
+    }
+
+    // 写入前加密（未启用加密时原样返回）
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        match self.active_key()? {
+            Some(key) => encrypt(&key, plaintext),
+            None => Ok(plaintext.to_vec()),
+        }
+    }
+
+    // 读取后解密；启用加密之前写入的明文文件直接返回
+    pub fn open(&self, bytes: &[u8]) -> Result<Vec<u8>, String> {
+        if !is_encrypted(bytes) {
+            return Ok(bytes.to_vec());
+        }
+        match self.active_key()? {
+            Some(key) => decrypt(&key, bytes),
+            None => Err("笔记已加密，但工作区未启用加密".to_string()),
+        }
+    }
+
+    // 用口令解锁
+    fn unlock(&self, passphrase: &str) -> Result<(), String> {
+        let header = self.lock_inner().header.clone().ok_or_else(|| "工作区未启用加密".to_string())?;
+        let key = header.derive_key(passphrase)?;
+        if !header.verify(&key) {
+            return Err("口令不正确".to_string());
+        }
+        let mut inner = self.lock_inner();
+        inner.key = Some(key);
+        inner.last_activity = Some(Instant::now());
+        Ok(())
+    }
+
+    fn set_unlocked(&self, header: EncryptionHeader, key: Key) {
+        let mut inner = self.lock_inner();
+        inner.header = Some(header);
+        inner.key = Some(key);
+        inner.last_activity = Some(Instant::now());
+    }
+}
+
+// 写入加密参数文件
+fn write_header(root: &Path, header: &EncryptionHeader) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(header).map_err(|e| format!("无法序列化加密参数: {}", e))?;
+    write_atomic(&root.join(ENCRYPTION_FILE), content.as_bytes())
+}
+
+// 更换口令时新密文的临时文件路径
+fn rekey_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(REKEY_SUFFIX);
+    path.with_file_name(name)
+}
+
+impl Workspace {
+    // 启用加密：先写入加密参数，再加密所有已有笔记
+    pub fn enable_encryption(&self, passphrase: &str, auto_lock_minutes: Option<u64>) -> Result<(), String> {
+        if self.vault().is_enabled() {
+            return Err("工作区已启用加密".to_string());
+        }
+        if passphrase.is_empty() {
+            return Err("口令不能为空".to_string());
+        }
+
+        let mut header = EncryptionHeader::new(auto_lock_minutes.unwrap_or(DEFAULT_AUTO_LOCK_MINUTES));
+        let key = header.derive_key(passphrase)?;
+        header.set_verifier(&key)?;
+
+        // 加密期间不允许其他修改写入明文
+        let _guard = self.lock_edits();
+        let drafts = self.read_all_drafts();
+        // 先保存参数，这样中途失败时已加密的笔记仍然可以解密，未加密的保持明文
+        write_header(self.root(), &header)?;
+        self.vault().set_unlocked(header, key);
+        // 加密的工作区不缓存明文
+        self.index().clear();
+        self.reseal_drafts(drafts)?;
+
+        for path in self.note_files()? {
+            let content = fs::read(&path).map_err(|e| format!("无法读取笔记 {}: {}", path.display(), e))?;
+            if !is_encrypted(&content) {
+                write_atomic(&path, &self.vault().seal(&content)?)?;
+            }
+        }
+
+        // 启用前的明文版本仍保留在历史记录中，需要清除历史才能移除（见 purge_history）
+        self.record_change("启用工作区加密".to_string());
+        backend_log!("[后端] 已启用工作区加密");
+        Ok(())
+    }
+
+    // 解锁工作区，并完成上次更换口令时未完成的替换
+    pub fn unlock(&self, passphrase: &str) -> Result<(), String> {
+        self.vault().unlock(passphrase)?;
+        self.finish_rekey()
+    }
+
+    // 更换口令：用新密钥重新加密所有笔记
+    pub fn change_passphrase(&self, old_passphrase: &str, new_passphrase: &str) -> Result<(), String> {
+        if new_passphrase.is_empty() {
+            return Err("口令不能为空".to_string());
+        }
+        self.vault().unlock(old_passphrase)?;
+        let auto_lock_minutes = self.vault().status().auto_lock_minutes.unwrap_or(DEFAULT_AUTO_LOCK_MINUTES);
+
+        let mut header = EncryptionHeader::new(auto_lock_minutes);
+        let new_key = header.derive_key(new_passphrase)?;
+        header.set_verifier(&new_key)?;
+
+        // 重新加密期间不允许其他修改用旧密钥写入笔记或草稿
+        let _guard = self.lock_edits();
+        let drafts = self.read_all_drafts();
+        // 第一步：用新密钥写出所有笔记的临时文件
+        let files = self.note_files()?;
+        for path in &files {
+            let content = fs::read(path).map_err(|e| format!("无法读取笔记 {}: {}", path.display(), e))?;
+            let plaintext = self.vault().open(&content)?;
+            write_atomic(&rekey_path(path), &encrypt(&new_key, &plaintext)?)?;
+        }
+
+        // 第二步：保存新的加密参数；此后中断时，下次解锁会完成替换
+        write_header(self.root(), &header)?;
+        self.vault().set_unlocked(header, new_key);
+
+        // 第三步：用临时文件替换原文件，草稿日志也换用新密钥
+        self.finish_rekey()?;
+        self.reseal_drafts(drafts)?;
+        self.record_change("更换工作区口令".to_string());
+        backend_log!("[后端] 已更换口令并重新加密 {} 个笔记", files.len());
+        Ok(())
+    }
+
+    // 修改自动锁定时间，0 表示不自动锁定
+    pub fn set_auto_lock(&self, minutes: u64) -> Result<(), String> {
+        let mut inner = self.vault().lock_inner();
+        let header = inner.header.as_mut().ok_or_else(|| "工作区未启用加密".to_string())?;
+        header.auto_lock_minutes = minutes;
+        write_header(self.root(), header)
+    }
+
+    // 将能用当前密钥解密的临时文件替换原文件，其余的是中断的旧尝试，直接删除
+    fn finish_rekey(&self) -> Result<(), String> {
+        let key = match self.vault().active_key()? {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+        for path in self.note_files_with_suffix(REKEY_SUFFIX)? {
+            let content = fs::read(&path).map_err(|e| format!("无法读取临时文件: {}", e))?;
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let target = path.with_file_name(file_name.trim_end_matches(REKEY_SUFFIX));
+            if decrypt(&key, &content).is_ok() {
+                fs::rename(&path, &target).map_err(|e| format!("无法替换笔记文件: {}", e))?;
+            } else {
+                fs::remove_file(&path).map_err(|e| format!("无法删除临时文件: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// 获取加密状态
+#[tauri::command]
+pub async fn get_encryption_status(state: State<'_, WorkspaceState>) -> Result<EncryptionStatus, String> {
+    Ok(state.current()?.vault().status())
+}
+
+// 启用加密；purge_history 为 true 时清除历史记录中的明文版本
+#[tauri::command]
+pub async fn enable_encryption(
+    state: State<'_, WorkspaceState>,
+    passphrase: String,
+    auto_lock_minutes: Option<u64>,
+    purge_history: Option<bool>,
+) -> Result<(), String> {
+    let passphrase = Zeroizing::new(passphrase);
+    state.run(move |workspace| {
+        workspace.enable_encryption(&passphrase, auto_lock_minutes)?;
+        workspace.handle_plaintext_history(purge_history.unwrap_or(false))
+    }).await
+}
+
+// 解锁工作区
+#[tauri::command]
+pub async fn unlock_workspace(state: State<'_, WorkspaceState>, passphrase: String) -> Result<(), String> {
+    let passphrase = Zeroizing::new(passphrase);
+    state.run(move |workspace| workspace.unlock(&passphrase)).await
+}
+
+// 立即锁定工作区
+#[tauri::command]
+pub async fn lock_workspace(state: State<'_, WorkspaceState>) -> Result<(), String> {
+    state.current()?.vault().lock();
+    Ok(())
+}
+
+// 更换口令
+#[tauri::command]
+pub async fn change_passphrase(
+    state: State<'_, WorkspaceState>,
+    old_passphrase: String,
+    new_passphrase: String,
+) -> Result<(), String> {
+    let old_passphrase = Zeroizing::new(old_passphrase);
+    let new_passphrase = Zeroizing::new(new_passphrase);
+    state.run(move |workspace| workspace.change_passphrase(&old_passphrase, &new_passphrase)).await
+}
+
+// 设置自动锁定时间
+#[tauri::command]
+pub async fn set_auto_lock(state: State<'_, WorkspaceState>, minutes: u64) -> Result<(), String> {
+    state.run(move |workspace| workspace.set_auto_lock(minutes)).await
+}
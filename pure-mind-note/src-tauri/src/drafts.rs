@@ -0,0 +1,251 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Local};
+use git2::{ObjectType, Oid};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::commands::note_title;
+use crate::note_format;
+use crate::note_lock;
+use crate::workspace::{write_atomic, Workspace, WorkspaceState};
+
+// 工作区根目录下保存草稿日志的目录（不参与同步、备份和历史）
+pub const DRAFTS_DIR: &str = ".pmn-drafts";
+
+// 草稿日志超过这个大小时只保留最新的一条
+const COMPACT_THRESHOLD: u64 = 1024 * 1024;
+
+// 日志中的一条草稿
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DraftRecord {
+    path: String,
+    // 写入时间（RFC 3339，精确到毫秒）
+    saved_at: String,
+    content: String,
+}
+
+// 更换工作区密钥前读出的草稿，换用新密钥后重新写入
+pub(crate) struct PendingDrafts(Vec<(PathBuf, Option<DraftRecord>)>);
+
+// 可以恢复的草稿
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoverableDraft {
+    pub path: String,
+    pub title: Option<String>,
+    pub saved_at: String,
+    // 笔记文件的修改时间，笔记已被删除时为 None
+    pub note_modified: Option<String>,
+}
+
+// 每篇笔记的日志文件以路径的哈希命名，避免文件名中出现分类名称
+fn journal_name(path: &str) -> String {
+    let hash = Oid::hash_object(ObjectType::Blob, path.as_bytes())
+        .map(|oid| oid.to_string())
+        .unwrap_or_else(|_| path.len().to_string());
+    format!("{}.journal", hash)
+}
+
+fn modified_time(path: &Path) -> Option<DateTime<Local>> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok().map(DateTime::from)
+}
+
+// 按当前格式比较笔记内容，忽略格式版本和排版的差异
+fn same_content(saved: &[u8], draft: &str) -> bool {
+    let parse = |bytes: &[u8]| {
+        let mut note: Value = serde_json::from_slice(bytes).ok()?;
+        note_format::migrate_note(&mut note).ok()?;
+        Some(note)
+    };
+    match (parse(saved), parse(draft.as_bytes())) {
+        (Some(saved), Some(draft)) => saved == draft,
+        _ => saved == draft.as_bytes(),
+    }
+}
+
+impl Workspace {
+    fn journal_path(&self, path: &str) -> PathBuf {
+        self.root().join(DRAFTS_DIR).join(journal_name(path))
+    }
+
+    // 每条记录单独加密后编码为一行，崩溃时写了一半的最后一行在读取时被忽略
+    fn encode_record(&self, record: &DraftRecord) -> Result<String, String> {
+        let json = serde_json::to_vec(record).map_err(|e| format!("无法序列化草稿: {}", e))?;
+        Ok(BASE64.encode(self.vault().seal(&json)?))
+    }
+
+    // 日志中最后一条完整的记录
+    fn latest_record(&self, journal: &Path) -> Option<DraftRecord> {
+        let content = fs::read_to_string(journal).ok()?;
+        content.lines().rev().find_map(|line| {
+            let bytes = BASE64.decode(line.trim()).ok()?;
+            serde_json::from_slice(&self.vault().open(&bytes).ok()?).ok()
+        })
+    }
+
+    // 追加一条未保存的编辑内容
+    pub fn save_draft(&self, path: &str, content: &str) -> Result<(), String> {
+        let note_path = self.resolve(path)?;
+        let _guard = self.lock_edits();
+        // 加锁笔记的内容不能以工作区密钥之外的形式留在磁盘上
+        if note_path.is_file() && self.read_note_bytes(&note_path).is_ok_and(|bytes| note_lock::is_locked(&bytes)) {
+            return Err("加锁的笔记不保存草稿".to_string());
+        }
+
+        let record = DraftRecord {
+            path: self.relative_path(&note_path),
+            saved_at: Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+            content: content.to_string(),
+        };
+        let line = self.encode_record(&record)?;
+        let journal = self.journal_path(&record.path);
+        fs::create_dir_all(self.root().join(DRAFTS_DIR)).map_err(|e| format!("无法创建草稿目录: {}", e))?;
+
+        // 日志过大时只保留最新的一条，替换整个文件
+        if fs::metadata(&journal).is_ok_and(|meta| meta.len() > COMPACT_THRESHOLD) {
+            return write_atomic(&journal, format!("{}\n", line).as_bytes());
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&journal)
+            .map_err(|e| format!("无法打开草稿日志: {}", e))?;
+        file.write_all(format!("{}\n", line).as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| format!("无法写入草稿日志: {}", e))
+    }
+
+    // 放弃笔记的草稿
+    pub fn discard_draft(&self, path: &str) -> Result<(), String> {
+        self.discard_draft_at(&self.resolve(path)?)
+    }
+
+    // 笔记保存或删除后，之前的草稿不再需要
+    pub(crate) fn discard_draft_at(&self, note_path: &Path) -> Result<(), String> {
+        let journal = self.journal_path(&self.relative_path(note_path));
+        match fs::remove_file(&journal) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("无法删除草稿: {}", e)),
+        }
+    }
+
+    // 分类或子分类被删除后，其中笔记的草稿不再需要
+    pub(crate) fn discard_drafts_under(&self, dir: &Path) {
+        let prefix = format!("{}/", self.relative_path(dir));
+        let Ok(entries) = fs::read_dir(self.root().join(DRAFTS_DIR)) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let orphaned = self.latest_record(&entry.path()).is_some_and(|record| record.path.starts_with(&prefix));
+            if orphaned {
+                if let Err(e) = fs::remove_file(entry.path()) {
+                    backend_log!("[后端] 无法删除草稿: {}", e);
+                }
+            }
+        }
+    }
+
+    // 用当前密钥读出所有草稿日志的最新记录，调用方需持有编辑锁
+    pub(crate) fn read_all_drafts(&self) -> PendingDrafts {
+        let Ok(entries) = fs::read_dir(self.root().join(DRAFTS_DIR)) else {
+            return PendingDrafts(Vec::new());
+        };
+        PendingDrafts(entries.flatten().map(|entry| (entry.path(), self.latest_record(&entry.path()))).collect())
+    }
+
+    // 用当前密钥重新写入读出的草稿，每篇只保留最新的一条；无法读出的日志直接删除
+    pub(crate) fn reseal_drafts(&self, drafts: PendingDrafts) -> Result<(), String> {
+        for (journal, record) in drafts.0 {
+            match record {
+                Some(record) => write_atomic(&journal, format!("{}\n", self.encode_record(&record)?).as_bytes())?,
+                None => fs::remove_file(&journal).map_err(|e| format!("无法删除草稿: {}", e))?,
+            }
+        }
+        Ok(())
+    }
+
+    // 比笔记文件更新、且内容不同的草稿；所在分类已不存在的草稿不能恢复
+    pub fn list_recoverable_drafts(&self) -> Result<Vec<RecoverableDraft>, String> {
+        let dir = self.root().join(DRAFTS_DIR);
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return Ok(Vec::new());
+        };
+
+        let mut drafts = Vec::new();
+        for entry in entries.flatten() {
+            let Some(record) = self.latest_record(&entry.path()) else {
+                continue;
+            };
+            let Ok(note_path) = self.resolve(&record.path) else {
+                continue;
+            };
+            if !note_path.parent().is_some_and(Path::is_dir) {
+                continue;
+            }
+            let Ok(saved_at) = DateTime::parse_from_rfc3339(&record.saved_at) else {
+                continue;
+            };
+            let note_modified = modified_time(&note_path);
+            if let Some(modified) = note_modified {
+                let saved = self.read_note_bytes(&note_path).unwrap_or_default();
+                if modified >= saved_at || same_content(&saved, &record.content) {
+                    continue;
+                }
+            }
+            drafts.push(RecoverableDraft {
+                title: note_title(record.content.as_bytes()),
+                path: record.path,
+                saved_at: record.saved_at,
+                note_modified: note_modified.map(|time| time.to_rfc3339()),
+            });
+        }
+        drafts.sort_by(|a, b| b.saved_at.cmp(&a.saved_at));
+        Ok(drafts)
+    }
+
+    // 用草稿内容覆盖笔记并删除草稿，返回恢复的内容
+    pub fn recover_draft(&self, path: &str) -> Result<String, String> {
+        let note_path = self.resolve(path)?;
+        let journal = self.journal_path(&self.relative_path(&note_path));
+        let record = self.latest_record(&journal).ok_or_else(|| "没有可以恢复的草稿".to_string())?;
+        // 不重新创建已删除的分类
+        if !note_path.parent().is_some_and(Path::is_dir) {
+            return Err("笔记所在的分类已不存在".to_string());
+        }
+        self.save_note(path, &record.content)?;
+        backend_log!("[后端] 已从草稿恢复笔记: {}", record.path);
+        self.read_note(path)
+    }
+}
+
+// 追加一条未保存的编辑内容
+#[tauri::command]
+pub async fn save_draft(state: State<'_, WorkspaceState>, path: String, content: String) -> Result<(), String> {
+    state.run(move |workspace| workspace.save_draft(&path, &content)).await
+}
+
+// 列出可以恢复的草稿
+#[tauri::command]
+pub async fn list_recoverable_drafts(state: State<'_, WorkspaceState>) -> Result<Vec<RecoverableDraft>, String> {
+    state.run(move |workspace| workspace.list_recoverable_drafts()).await
+}
+
+// 用草稿恢复笔记
+#[tauri::command]
+pub async fn recover_draft(state: State<'_, WorkspaceState>, path: String) -> Result<String, String> {
+    state.run(move |workspace| workspace.recover_draft(&path)).await
+}
+
+// 放弃草稿
+#[tauri::command]
+pub async fn discard_draft(state: State<'_, WorkspaceState>, path: String) -> Result<(), String> {
+    state.run(move |workspace| workspace.discard_draft(&path)).await
+}
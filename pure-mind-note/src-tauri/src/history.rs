@@ -0,0 +1,437 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+use git2::{Commit, IndexAddOption, Oid, Repository, Signature};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use zeroize::Zeroizing;
+
+use crate::backup::BACKUP_SETTINGS_FILE;
+use crate::note_format;
+use crate::note_lock;
+use crate::workspace::{write_atomic, Workspace, WorkspaceState, TEMP_SUFFIX};
+use crate::crypto::REKEY_SUFFIX;
+use crate::drafts::DRAFTS_DIR;
+use crate::sync::SYNC_STATE_FILE;
+
+// 最后一次修改后等待多久再提交，连续编辑时合并为一次提交
+pub const COMMIT_DEBOUNCE: Duration = Duration::from_secs(5);
+
+// 仓库中没有配置用户信息时使用的提交者
+const COMMITTER_NAME: &str = "PureMindNote";
+const COMMITTER_EMAIL: &str = "pure-mind-note@localhost";
+
+// 历史记录中的一次提交
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: String,
+    pub short_id: String,
+    // 提交说明的第一行
+    pub summary: String,
+    pub message: String,
+    pub author: String,
+    // 提交时间（RFC 3339）
+    pub time: String,
+}
+
+// 历史记录的开启状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStatus {
+    pub enabled: bool,
+    // 尚未提交的修改数量
+    pub pending_changes: usize,
+}
+
+// 工作区的 git 历史，只在根目录存在仓库时启用
+#[derive(Debug, Default)]
+pub struct History {
+    inner: Mutex<HistoryInner>,
+}
+
+#[derive(Debug, Default)]
+struct HistoryInner {
+    enabled: bool,
+    // 等待提交的修改说明
+    pending: Vec<String>,
+    last_change: Option<Instant>,
+}
+
+impl History {
+    // 检查工作区根目录是否已有仓库（不向上查找父目录）
+    pub fn load(root: &Path) -> Self {
+        let enabled = Repository::open(root)
+            .ok()
+            .and_then(|repo| repo.workdir().map(|dir| dir == root))
+            .unwrap_or(false);
+        Self {
+            inner: Mutex::new(HistoryInner { enabled, ..Default::default() }),
+        }
+    }
+
+    fn lock_inner(&self) -> MutexGuard<'_, HistoryInner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.lock_inner().enabled
+    }
+
+    pub fn status(&self) -> HistoryStatus {
+        let inner = self.lock_inner();
+        HistoryStatus { enabled: inner.enabled, pending_changes: inner.pending.len() }
+    }
+
+    // 记录一次修改，等待合并提交；未启用历史时忽略
+    pub fn record(&self, message: String) {
+        let mut inner = self.lock_inner();
+        if !inner.enabled {
+            return;
+        }
+        if !inner.pending.contains(&message) {
+            inner.pending.push(message);
+        }
+        inner.last_change = Some(Instant::now());
+    }
+}
+
+// 多次修改合并成一条提交说明
+fn commit_message(pending: &[String]) -> String {
+    match pending {
+        [single] => single.clone(),
+        _ => {
+            let details: Vec<String> = pending.iter().map(|message| format!("- {}", message)).collect();
+            format!("更新了 {} 处内容\n\n{}", pending.len(), details.join("\n"))
+        }
+    }
+}
+
+fn git_error(e: git2::Error) -> String {
+    format!("git 操作失败: {}", e.message())
+}
+
+// 写入过程中的临时文件、草稿和本机的同步、备份状态不纳入版本管理；
+// 已有的 .gitignore 保留原有内容，只追加缺少的规则
+fn ensure_ignore_rules(root: &Path) -> Result<(), String> {
+    let ignore_path = root.join(".gitignore");
+    let existing = match fs::read_to_string(&ignore_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("无法读取 .gitignore: {}", e)),
+    };
+    let rules = [
+        format!("*{}", TEMP_SUFFIX),
+        format!("*{}", REKEY_SUFFIX),
+        format!("/{}", SYNC_STATE_FILE),
+        format!("/{}", BACKUP_SETTINGS_FILE),
+        format!("/{}/", DRAFTS_DIR),
+    ];
+    let missing: Vec<&String> = rules.iter()
+        .filter(|rule| !existing.lines().any(|line| line.trim() == rule.as_str()))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let mut content = existing.clone();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for rule in missing {
+        content.push_str(rule);
+        content.push('\n');
+    }
+    write_atomic(&ignore_path, content.as_bytes()).map_err(|e| format!("无法写入 .gitignore: {}", e))
+}
+
+// 提交工作区中的全部改动，没有改动时返回 None
+fn commit_all(root: &Path, message: &str) -> Result<Option<Oid>, String> {
+    ensure_ignore_rules(root)?;
+    let repo = Repository::open(root).map_err(git_error)?;
+    let mut index = repo.index().map_err(git_error)?;
+    index.add_all(["*"], IndexAddOption::DEFAULT, None).map_err(git_error)?;
+    // 同步已删除的文件
+    index.update_all(["*"], None).map_err(git_error)?;
+    // 之前已被提交、现在应忽略的文件（如旧版本留下的草稿）从仓库中移除
+    index.remove_all(["*"], Some(&mut |path: &Path, _: &[u8]| {
+        if repo.is_path_ignored(path).unwrap_or(false) { 0 } else { 1 }
+    })).map_err(git_error)?;
+    index.write().map_err(git_error)?;
+    let tree_id = index.write_tree().map_err(git_error)?;
+
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    if parent.as_ref().is_some_and(|commit| commit.tree_id() == tree_id) {
+        return Ok(None);
+    }
+
+    let tree = repo.find_tree(tree_id).map_err(git_error)?;
+    let signature = repo.signature()
+        .or_else(|_| Signature::now(COMMITTER_NAME, COMMITTER_EMAIL))
+        .map_err(git_error)?;
+    let parents: Vec<&Commit> = parent.iter().collect();
+    repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)
+        .map(Some)
+        .map_err(git_error)
+}
+
+fn history_entry(commit: &Commit) -> HistoryEntry {
+    let id = commit.id().to_string();
+    HistoryEntry {
+        short_id: id.chars().take(7).collect(),
+        id,
+        summary: commit.summary().unwrap_or_default().to_string(),
+        message: commit.message().unwrap_or_default().trim_end().to_string(),
+        author: commit.author().name().unwrap_or_default().to_string(),
+        time: chrono::DateTime::from_timestamp(commit.time().seconds(), 0)
+            .map(|time| time.with_timezone(&chrono::Local).to_rfc3339())
+            .unwrap_or_default(),
+    }
+}
+
+// 某个提交中指定文件的对象ID，文件不存在时为 None
+fn file_id_at(commit: &Commit, relative: &Path) -> Option<Oid> {
+    commit.tree().ok()?.get_path(relative).ok().map(|entry| entry.id())
+}
+
+impl Workspace {
+    // 在工作区根目录初始化仓库，并提交现有内容
+    pub fn enable_history(&self) -> Result<(), String> {
+        if self.history().is_enabled() {
+            return Err("已经启用了历史记录".to_string());
+        }
+        Repository::init(self.root()).map_err(git_error)?;
+        commit_all(self.root(), "初始化笔记历史")?;
+        self.history().lock_inner().enabled = true;
+        backend_log!("[后端] 已启用笔记历史: {}", self.root().display());
+        Ok(())
+    }
+
+    // 记录一次修改，稍后由后台线程合并提交
+    pub fn record_change(&self, message: String) {
+        self.history().record(message);
+    }
+
+    // 生成修改说明中使用的笔记名称；加密工作区只使用文件名，避免标题以明文写入仓库
+    pub fn change_label(&self, path: &Path, title: Option<String>) -> String {
+        let file_stem = path.file_stem().and_then(|name| name.to_str()).unwrap_or("未命名笔记");
+        match title {
+            Some(title) if !self.vault().is_enabled() => title,
+            _ => file_stem.to_string(),
+        }
+    }
+
+    // 最后一次修改超过 debounce 后提交，返回新提交的ID
+    pub fn commit_if_idle(&self, debounce: Duration) -> Result<Option<String>, String> {
+        let mut inner = self.history().lock_inner();
+        let idle = inner.last_change.is_some_and(|time| time.elapsed() >= debounce);
+        if !inner.enabled || inner.pending.is_empty() || !idle {
+            return Ok(None);
+        }
+
+        let oid = commit_all(self.root(), &commit_message(&inner.pending))?;
+        inner.pending.clear();
+        inner.last_change = None;
+        Ok(oid.map(|oid| oid.to_string()))
+    }
+
+    // 立即提交所有等待中的修改
+    pub fn flush_history(&self) -> Result<Option<String>, String> {
+        self.commit_if_idle(Duration::ZERO)
+    }
+
+    // 提交历史，path 为 Some 时只列出修改过该笔记的提交
+    pub fn history_log(&self, path: Option<&str>, limit: usize) -> Result<Vec<HistoryEntry>, String> {
+        if !self.history().is_enabled() {
+            return Err("尚未启用历史记录".to_string());
+        }
+        let relative = match path {
+            Some(path) => Some(self.relative_path(&self.resolve(path)?)),
+            None => None,
+        };
+
+        let repo = Repository::open(self.root()).map_err(git_error)?;
+        let mut revwalk = repo.revwalk().map_err(git_error)?;
+        if revwalk.push_head().is_err() {
+            // 仓库中还没有任何提交
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for oid in revwalk {
+            if entries.len() >= limit {
+                break;
+            }
+            let commit = repo.find_commit(oid.map_err(git_error)?).map_err(git_error)?;
+            if let Some(relative) = &relative {
+                let relative = Path::new(relative);
+                let current = file_id_at(&commit, relative);
+                let previous = commit.parent(0).ok().and_then(|parent| file_id_at(&parent, relative));
+                if current == previous {
+                    continue;
+                }
+            }
+            entries.push(history_entry(&commit));
+        }
+        Ok(entries)
+    }
+
+    // 读取笔记在某个提交中的内容（工作区加密层已去掉）
+    fn note_bytes_at(&self, path: &str, commit_id: &str) -> Result<Vec<u8>, String> {
+        let relative = self.relative_path(&self.resolve(path)?);
+        let repo = Repository::open(self.root()).map_err(git_error)?;
+        let commit = repo.revparse_single(commit_id)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|_| format!("找不到版本: {}", commit_id))?;
+        let entry = commit.tree()
+            .and_then(|tree| tree.get_path(Path::new(&relative)))
+            .map_err(|_| "该版本中不存在此笔记".to_string())?;
+        let blob = repo.find_blob(entry.id()).map_err(git_error)?;
+        self.vault()
+            .open(blob.content())
+            .map_err(|e| format!("无法读取该版本（可能使用了旧口令加密）: {}", e))
+    }
+
+    // 笔记当前已加锁时，必须提供能解开当前内容的密码，返回验证过的密码
+    fn verify_current_lock<'a>(&self, path: &str, password: Option<&'a str>) -> Result<Option<&'a str>, String> {
+        let note_path = self.resolve(path)?;
+        if !note_path.is_file() {
+            return Ok(None);
+        }
+        let current = self.read_note_bytes(&note_path)?;
+        if !note_lock::is_locked(&current) {
+            return Ok(None);
+        }
+        let password = password.ok_or_else(|| "笔记已加锁，请输入密码".to_string())?;
+        note_lock::unlock_content(password, &current)?;
+        Ok(Some(password))
+    }
+
+    // 历史版本的明文内容，加锁的版本用 password 解开
+    fn note_plaintext_at(&self, path: &str, commit_id: &str, password: Option<&str>) -> Result<Zeroizing<Vec<u8>>, String> {
+        let bytes = self.note_bytes_at(path, commit_id)?;
+        if !note_lock::is_locked(&bytes) {
+            return Ok(Zeroizing::new(bytes));
+        }
+        let password = password.ok_or_else(|| "该版本的笔记已加锁，请输入密码".to_string())?;
+        note_lock::unlock_content(password, &bytes)
+    }
+
+    // 查看笔记的历史版本；笔记当前已加锁时，旧版本也需要密码才能查看
+    pub fn read_note_version(&self, path: &str, commit_id: &str, password: Option<&str>) -> Result<String, String> {
+        let password = self.verify_current_lock(path, password)?.or(password);
+        let bytes = self.note_plaintext_at(path, commit_id, password)?;
+        String::from_utf8(bytes.to_vec())
+            .map(note_format::upgrade_for_read)
+            .map_err(|e| format!("无法读取笔记: {}", e))
+    }
+
+    // 将笔记恢复到某个历史版本，恢复前后各提交一次，恢复操作本身也可以撤销；
+    // 笔记当前已加锁时需要密码，恢复的内容用同一密码重新加锁
+    pub fn checkout_note_version(&self, path: &str, commit_id: &str, password: Option<&str>) -> Result<(), String> {
+        let current_password = self.verify_current_lock(path, password)?;
+        let bytes = match current_password {
+            Some(password) => note_lock::lock_content(password, &self.note_plaintext_at(path, commit_id, Some(password))?)?,
+            None => self.note_bytes_at(path, commit_id)?,
+        };
+        self.flush_history()?;
+
+        let note_path = self.resolve(path)?;
+        let _guard = self.lock_edits();
+        self.write_note_bytes(&note_path, &bytes)?;
+        // 旧的草稿基于恢复前的内容，不能再覆盖恢复的版本
+        if let Err(e) = self.discard_draft_at(&note_path) {
+            backend_log!("[后端] {}", e);
+        }
+
+        let short_id: String = commit_id.chars().take(7).collect();
+        let label = self.change_label(&note_path, crate::commands::note_title(&bytes));
+        self.record_change(format!("恢复笔记《{}》到版本 {}", label, short_id));
+        self.flush_history()?;
+        Ok(())
+    }
+
+    // 清除历史记录：重新初始化仓库，只保留当前内容；
+    // 启用加密或加锁笔记后，旧的明文版本只有这样才能从磁盘上移除
+    pub fn purge_history(&self) -> Result<(), String> {
+        if !self.history().is_enabled() {
+            return Err("尚未启用历史记录".to_string());
+        }
+        let mut inner = self.history().lock_inner();
+        fs::remove_dir_all(self.root().join(".git")).map_err(|e| format!("无法删除历史记录: {}", e))?;
+        Repository::init(self.root()).map_err(git_error)?;
+        commit_all(self.root(), "清除历史记录，只保留当前版本")?;
+        inner.pending.clear();
+        inner.last_change = None;
+        backend_log!("[后端] 已清除笔记历史: {}", self.root().display());
+        Ok(())
+    }
+
+    // 历史中可能仍有明文时提醒，purge 为 true 时直接清除历史
+    pub(crate) fn handle_plaintext_history(&self, purge: bool) -> Result<(), String> {
+        if !self.history().is_enabled() {
+            return Ok(());
+        }
+        if purge {
+            self.flush_history()?;
+            return self.purge_history();
+        }
+        backend_log!("[后端] 警告: 之前的明文版本仍保留在历史记录中，可以清除历史记录将其移除");
+        Ok(())
+    }
+}
+
+// 启用 git 历史记录
+#[tauri::command]
+pub async fn enable_history(state: State<'_, WorkspaceState>) -> Result<(), String> {
+    state.run(move |workspace| workspace.enable_history()).await
+}
+
+// 获取历史记录状态
+#[tauri::command]
+pub async fn get_history_status(state: State<'_, WorkspaceState>) -> Result<HistoryStatus, String> {
+    state.run(move |workspace| Ok(workspace.history().status())).await
+}
+
+// 获取提交历史，可按笔记过滤
+#[tauri::command]
+pub async fn get_history_log(
+    state: State<'_, WorkspaceState>,
+    path: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<HistoryEntry>, String> {
+    state.run(move |workspace| workspace.history_log(path.as_deref(), limit.unwrap_or(100))).await
+}
+
+// 查看笔记的历史版本，加锁的笔记需要密码
+#[tauri::command]
+pub async fn read_note_version(
+    state: State<'_, WorkspaceState>,
+    path: String,
+    commit_id: String,
+    password: Option<String>,
+) -> Result<String, String> {
+    let password = password.map(Zeroizing::new);
+    state.run(move |workspace| workspace.read_note_version(&path, &commit_id, password.as_deref().map(String::as_str))).await
+}
+
+// 将笔记恢复到历史版本，加锁的笔记需要密码
+#[tauri::command]
+pub async fn checkout_note_version(
+    state: State<'_, WorkspaceState>,
+    path: String,
+    commit_id: String,
+    password: Option<String>,
+) -> Result<(), String> {
+    let password = password.map(Zeroizing::new);
+    state.run(move |workspace| workspace.checkout_note_version(&path, &commit_id, password.as_deref().map(String::as_str))).await
+}
+
+// 清除历史记录，只保留当前版本
+#[tauri::command]
+pub async fn purge_history(state: State<'_, WorkspaceState>) -> Result<(), String> {
+    state.run(move |workspace| workspace.purge_history()).await
+}
@@ -0,0 +1,169 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tauri::State;
+
+use crate::commands::is_note_file;
+use crate::naming;
+use crate::nodes::NodeInfo;
+use crate::workspace::{write_atomic, Workspace, WorkspaceState};
+
+// 工作区根目录下保存收集箱设置的文件
+pub const INBOX_SETTINGS_FILE: &str = ".pmn-inbox.json";
+
+// 未指定收集箱时使用的分类和笔记名称
+pub const DEFAULT_INBOX_NAME: &str = "收集箱";
+
+// 收集箱设置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InboxSettings {
+    // 收集箱笔记的路径，为空时使用默认的 "收集箱/收集箱.json"
+    pub path: Option<String>,
+}
+
+// 整理收集箱时的一项移动
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxMove {
+    pub node_id: String,
+    pub target_path: String,
+    // 省略时移动到目标笔记的根节点下
+    pub parent_id: Option<String>,
+}
+
+// 一项移动的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxMoveResult {
+    pub node_id: String,
+    pub target_path: String,
+    // 移动后的节点，失败时为 None
+    pub node: Option<NodeInfo>,
+    pub error: Option<String>,
+}
+
+impl Workspace {
+    pub fn inbox_settings(&self) -> InboxSettings {
+        std::fs::read_to_string(self.root().join(INBOX_SETTINGS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    // 指定收集箱笔记，None 表示恢复默认位置
+    pub fn set_inbox_note(&self, path: Option<&str>) -> Result<InboxSettings, String> {
+        let path = match path {
+            Some(path) => {
+                let note_path = self.resolve(path)?;
+                if !is_note_file(&note_path) {
+                    return Err(format!("笔记不存在: {}", path));
+                }
+                Some(self.relative_path(&note_path))
+            }
+            None => None,
+        };
+        let settings = InboxSettings { path };
+        let content = serde_json::to_string_pretty(&settings)
+            .map_err(|e| format!("无法序列化收集箱设置: {}", e))?;
+        write_atomic(&self.root().join(INBOX_SETTINGS_FILE), content.as_bytes())?;
+        Ok(settings)
+    }
+
+    // 收集箱笔记的路径；笔记不存在时在默认位置创建
+    pub fn inbox_note(&self) -> Result<String, String> {
+        if let Some(path) = self.inbox_settings().path {
+            if is_note_file(&self.resolve(&path)?) {
+                return Ok(path);
+            }
+            backend_log!("[后端] 收集箱笔记 {} 不存在，改用默认位置", path);
+        }
+
+        let category_id = naming::slugify(DEFAULT_INBOX_NAME)?;
+        let file_name = format!("{}.json", naming::slugify(DEFAULT_INBOX_NAME)?);
+        let note_path = self.resolve_parts(&[&category_id, &file_name])?;
+        if is_note_file(&note_path) {
+            return Ok(self.relative_path(&note_path));
+        }
+        if !self.resolve_parts(&[&category_id])?.is_dir() {
+            self.create_category(DEFAULT_INBOX_NAME)?;
+        }
+        let content = json!({
+            "title": DEFAULT_INBOX_NAME,
+            "lastUpdated": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "data": { "data": { "uid": "root", "text": DEFAULT_INBOX_NAME }, "children": [] }
+        });
+        let note = self.create_note(&category_id, None, DEFAULT_INBOX_NAME, &content.to_string())?;
+        backend_log!("[后端] 已创建收集箱: {}", note.path);
+        Ok(note.path)
+    }
+
+    // 在收集箱根节点下追加一条记录，记录收集的时间
+    pub fn append_to_inbox(&self, text: &str, tags: &[String]) -> Result<NodeInfo, String> {
+        if text.trim().is_empty() {
+            return Err("内容不能为空".to_string());
+        }
+        let path = self.inbox_note()?;
+        let mut data = Map::new();
+        data.insert("text".to_string(), Value::String(text.trim().to_string()));
+        if !tags.is_empty() {
+            data.insert("tag".to_string(), json!(tags));
+        }
+        let captured_at = Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+        data.insert("capturedAt".to_string(), Value::String(captured_at));
+
+        self.add_node(&path, &self.root_node_id(&path)?, data, None)
+    }
+
+    // 收集箱中待整理的记录（根节点的子节点）
+    pub fn list_inbox(&self) -> Result<Vec<NodeInfo>, String> {
+        self.get_child_nodes(&self.inbox_note()?, None)
+    }
+
+    // 把收集箱中的记录移动到各自的目标笔记，每一项单独执行，失败的项留在收集箱中
+    pub fn process_inbox(&self, moves: &[InboxMove]) -> Result<Vec<InboxMoveResult>, String> {
+        let inbox = self.inbox_note()?;
+        let results: Vec<InboxMoveResult> = moves.iter().map(|item| {
+            let moved = self.move_node_to_note(&inbox, &item.node_id, &item.target_path, item.parent_id.as_deref(), None);
+            InboxMoveResult {
+                node_id: item.node_id.clone(),
+                target_path: item.target_path.clone(),
+                error: moved.as_ref().err().cloned(),
+                node: moved.ok(),
+            }
+        }).collect();
+        let failed = results.iter().filter(|result| result.error.is_some()).count();
+        backend_log!("[后端] 整理收集箱: 移动 {} 项，失败 {} 项", results.len() - failed, failed);
+        Ok(results)
+    }
+}
+
+// 快速记录到收集箱
+#[tauri::command]
+pub async fn append_to_inbox(state: State<'_, WorkspaceState>, text: String, tags: Option<Vec<String>>) -> Result<NodeInfo, String> {
+    state.run(move |workspace| workspace.append_to_inbox(&text, &tags.unwrap_or_default())).await
+}
+
+// 列出收集箱中的记录
+#[tauri::command]
+pub async fn list_inbox(state: State<'_, WorkspaceState>) -> Result<Vec<NodeInfo>, String> {
+    state.run(move |workspace| workspace.list_inbox()).await
+}
+
+// 整理收集箱
+#[tauri::command]
+pub async fn process_inbox(state: State<'_, WorkspaceState>, moves: Vec<InboxMove>) -> Result<Vec<InboxMoveResult>, String> {
+    state.run(move |workspace| workspace.process_inbox(&moves)).await
+}
+
+// 获取收集箱设置
+#[tauri::command]
+pub async fn get_inbox_settings(state: State<'_, WorkspaceState>) -> Result<InboxSettings, String> {
+    state.run(move |workspace| Ok(workspace.inbox_settings())).await
+}
+
+// 指定收集箱笔记
+#[tauri::command]
+pub async fn set_inbox_note(state: State<'_, WorkspaceState>, path: Option<String>) -> Result<InboxSettings, String> {
+    state.run(move |workspace| workspace.set_inbox_note(path.as_deref())).await
+}
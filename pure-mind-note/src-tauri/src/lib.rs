@@ -1,9 +1,104 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// 后端日志的输出位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogOutput {
+  Stdout = 0,
+  // 命令行和 stdio 模式下标准输出用于结果，日志改写到标准错误
+  Stderr = 1,
+  Off = 2,
+}
+
+static LOG_OUTPUT: AtomicU8 = AtomicU8::new(LogOutput::Stdout as u8);
+
+// 设置后端日志的输出位置，默认为标准输出
+pub fn set_log_output(output: LogOutput) {
+  LOG_OUTPUT.store(output as u8, Ordering::Relaxed);
+}
+
+#[doc(hidden)]
+pub fn log_output() -> LogOutput {
+  match LOG_OUTPUT.load(Ordering::Relaxed) {
+    0 => LogOutput::Stdout,
+    1 => LogOutput::Stderr,
+    _ => LogOutput::Off,
+  }
+}
+
+// 输出一行后端日志，位置由 set_log_output 决定
+macro_rules! backend_log {
+  ($($arg:tt)*) => {
+    match $crate::log_output() {
+      $crate::LogOutput::Stdout => println!($($arg)*),
+      $crate::LogOutput::Stderr => eprintln!($($arg)*),
+      $crate::LogOutput::Off => {}
+    }
+  };
+}
+
+// 本地 HTTP 接口
+pub mod api;
+// 单文件归档的导出与导入
+pub mod archive;
+// 定期备份与恢复
+pub mod backup;
+// 工作区完整性检查与修复
+pub mod check;
+// 不启动界面的命令行工具
+pub mod cli;
 // 引入命令模块
 pub mod commands;
+// 应用配置
+pub mod config;
+// 工作区加密
+pub mod crypto;
+// 未保存编辑的草稿日志
+pub mod drafts;
+// 分类元数据
+pub mod metadata;
+// 基于 git 的笔记历史
+pub mod history;
+// 快速记录的收集箱
+pub mod inbox;
+// 笔记之间的链接与反向链接
+pub mod links;
+// 脑图笔记的结构化三方合并
+pub mod merge;
+// 跨平台文件名校验与转换
+pub mod naming;
+// 脑图节点的增删改查
+pub mod nodes;
+// 笔记格式版本与迁移
+pub mod note_format;
+// 笔记内容索引与工作区文件监视
+pub mod note_index;
+// 单篇笔记的密码锁
+pub mod note_lock;
+// 排序方式与自定义顺序
+pub mod ordering;
+// Markdown 大纲的导出与解析
+pub mod outline;
+// 基于 JSON Patch 的增量更新
+pub mod patch;
+// 多工作区列表与切换
+pub mod registry;
+// 标准输入输出上的 JSON-RPC 服务
+pub mod rpc;
+// 节点文字和标签的全文搜索
+pub mod search;
+// 与其他本地目录双向同步
+pub mod sync;
+// 笔记模板
+pub mod templates;
+// 工作区状态与路径解析
+pub mod workspace;
+
+use tauri::{Emitter, Manager};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   tauri::Builder::default()
+    .manage(workspace::WorkspaceState::default())
     .setup(|app| {
       if cfg!(debug_assertions) {
         app.handle().plugin(
@@ -14,15 +109,147 @@ pub fn run() {
       }
       // 注册文件系统插件
       app.handle().plugin(tauri_plugin_fs::init())?;
+
+      // 读取应用配置，首次运行时导入旧版前端写入的配置文件
+      let legacy_config = app.path().app_data_dir()?.join("pure-mind-note").join(config::CONFIG_FILE);
+      let app_config = config::ConfigState::load(app.path().app_config_dir()?.join(config::CONFIG_FILE), Some(&legacy_config));
+      app.manage(app_config);
+
+      // 按配置启动本地 HTTP 接口
+      app.manage(api::ApiServer::default());
+      app.state::<api::ApiServer>().apply(app.handle());
+
+      // 工作区中的笔记在应用之外被修改（同步工具、其他编辑器）时通知前端
+      let handle = app.handle().clone();
+      app.state::<workspace::WorkspaceState>().watch_changes(std::sync::Arc::new(move |path| {
+        let _ = handle.emit(api::NOTES_CHANGED_EVENT, path);
+      }));
+
+      // 读取工作区列表，并重新打开上次使用的工作区
+      let registry = registry::WorkspaceRegistry::load(app.path().app_config_dir()?.join(registry::REGISTRY_FILE));
+      if let Some(entry) = registry.active() {
+        if let Err(e) = registry.switch(&app.state::<workspace::WorkspaceState>(), &entry.id) {
+          backend_log!("[后端] 无法打开上次使用的工作区: {}", e);
+        }
+      }
+      app.manage(registry);
+
+      // 定期检查加密工作区是否长时间未使用，需要自动锁定
+      let handle = app.handle().clone();
+      std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_secs(30));
+        if let Ok(current) = handle.state::<workspace::WorkspaceState>().current() {
+          if current.vault().lock_if_idle() {
+            let _ = handle.emit("workspace-locked", ());
+          }
+        }
+      });
+
+      // 修改停止一段时间后自动提交到 git 历史
+      let handle = app.handle().clone();
+      std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        if let Ok(current) = handle.state::<workspace::WorkspaceState>().current() {
+          match current.commit_if_idle(history::COMMIT_DEBOUNCE) {
+            Ok(Some(commit_id)) => {
+              let _ = handle.emit("history-committed", commit_id);
+            }
+            Ok(None) => {}
+            Err(e) => backend_log!("[后端] 自动提交失败: {}", e),
+          }
+        }
+      });
+
+      // 按设置的间隔自动备份
+      let handle = app.handle().clone();
+      std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_secs(60));
+        if let Ok(current) = handle.state::<workspace::WorkspaceState>().current() {
+          match current.run_scheduled_backup() {
+            Ok(Some(backup)) => {
+              let _ = handle.emit("backup-created", backup);
+            }
+            Ok(None) => {}
+            Err(e) => backend_log!("[后端] 自动备份失败: {}", e),
+          }
+        }
+      });
       Ok(())
     })
     .invoke_handler(tauri::generate_handler![
+      workspace::open_workspace,
+      config::get_config,
+      config::update_config,
+      registry::list_workspaces,
+      registry::get_active_workspace,
+      registry::add_workspace,
+      registry::remove_workspace,
+      registry::rename_workspace,
+      registry::update_workspace_settings,
+      registry::switch_workspace,
       commands::read_note,
       commands::save_note,
       commands::get_all_notes,
       commands::get_all_categories,
       commands::create_category,
       commands::create_subcategory,
+      commands::create_note,
+      commands::update_category_metadata,
+      ordering::reorder_categories,
+      ordering::reorder_subcategories,
+      ordering::reorder_notes,
+      crypto::get_encryption_status,
+      crypto::enable_encryption,
+      crypto::unlock_workspace,
+      crypto::lock_workspace,
+      crypto::change_passphrase,
+      crypto::set_auto_lock,
+      note_format::migrate_workspace,
+      check::check_workspace,
+      check::repair_workspace,
+      patch::read_note_with_revision,
+      patch::apply_note_patch,
+      nodes::get_node,
+      nodes::add_node,
+      nodes::update_node,
+      nodes::delete_node,
+      nodes::move_node,
+      nodes::move_node_to_note,
+      inbox::append_to_inbox,
+      inbox::list_inbox,
+      inbox::process_inbox,
+      inbox::get_inbox_settings,
+      inbox::set_inbox_note,
+      search::search_notes,
+      outline::export_markdown,
+      templates::list_templates,
+      templates::create_note_from_template,
+      templates::save_note_as_template,
+      links::get_backlinks,
+      links::get_link_graph,
+      drafts::save_draft,
+      drafts::list_recoverable_drafts,
+      drafts::recover_draft,
+      drafts::discard_draft,
+      note_lock::lock_note,
+      note_lock::unlock_note,
+      note_lock::read_locked_note,
+      note_lock::save_locked_note,
+      history::enable_history,
+      history::get_history_status,
+      history::get_history_log,
+      history::read_note_version,
+      history::checkout_note_version,
+      history::purge_history,
+      sync::sync_folder,
+      merge::merge_note_versions,
+      backup::get_backup_settings,
+      backup::update_backup_settings,
+      backup::create_backup,
+      backup::list_backups,
+      backup::restore_backup,
+      archive::export_archive,
+      archive::import_archive,
       commands::delete_category,
       commands::delete_subcategory,
       commands::delete_note
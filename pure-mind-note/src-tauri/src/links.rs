@@ -0,0 +1,214 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::State;
+
+use crate::commands::NoteInfo;
+use crate::naming;
+use crate::nodes::{plain_text, walk_nodes};
+use crate::note_format;
+use crate::ordering::SortMode;
+use crate::workspace::{Workspace, WorkspaceState};
+
+// 节点中的一个链接，尚未解析到具体笔记
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    // 文字中的 [[笔记标题]]，也可以写笔记ID或路径，"|" 之后是显示文字
+    Title(String),
+    // link 字段：节点ID、笔记ID或笔记路径
+    Id(String),
+}
+
+// 指向某篇笔记（或其中某个节点）的节点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backlink {
+    pub note_id: String,
+    pub note_title: String,
+    pub path: String,
+    pub node_id: String,
+    pub text: String,
+    // 链接指向的节点，链接指向整篇笔记时为 None
+    pub target_node_id: Option<String>,
+}
+
+// 关系图中的一篇笔记
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraphNode {
+    pub id: String,
+    pub title: String,
+    pub path: String,
+    pub category_id: String,
+}
+
+// 关系图中两篇笔记之间的链接，count 为链接的数量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraphEdge {
+    pub source: String,
+    pub target: String,
+    pub count: usize,
+}
+
+// 整个工作区的笔记关系图
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraph {
+    pub nodes: Vec<LinkGraphNode>,
+    pub edges: Vec<LinkGraphEdge>,
+}
+
+// 解析节点中的链接：文字中的 [[...]] 和 link 字段
+pub fn node_links(data: &Map<String, Value>) -> Vec<LinkTarget> {
+    let mut links = Vec::new();
+    let text = plain_text(data);
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find("[[") {
+        let Some(end) = rest[start + 2..].find("]]") else { break };
+        let inner = &rest[start + 2..start + 2 + end];
+        let target = inner.split('|').next().unwrap_or_default().trim();
+        if !target.is_empty() && !target.contains('\n') {
+            links.push(LinkTarget::Title(target.to_string()));
+        }
+        rest = &rest[start + 4 + end..];
+    }
+    if let Some(id) = data.get("link").and_then(Value::as_str).map(str::trim).filter(|id| !id.is_empty()) {
+        links.push(LinkTarget::Id(id.to_string()));
+    }
+    links
+}
+
+// 工作区中的一个链接，已解析到目标笔记
+struct Link {
+    // 来源笔记在 notes 中的位置
+    source: usize,
+    node_id: String,
+    text: String,
+    target: usize,
+    target_node_id: Option<String>,
+}
+
+// 工作区的链接索引
+struct LinkIndex {
+    notes: Vec<NoteInfo>,
+    links: Vec<Link>,
+}
+
+impl Workspace {
+    // 扫描所有笔记，解析并索引其中的链接；加锁笔记的内容不参与索引，但仍可以被标题或路径链接
+    fn link_index(&self) -> Result<LinkIndex, String> {
+        let notes = self.get_all_notes(SortMode::default())?;
+        let documents: Vec<(usize, Value)> = self.indexable_contents(&notes)
+            .into_iter()
+            .filter_map(|(i, content)| Some((i, serde_json::from_str(&note_format::upgrade_for_read(content)).ok()?)))
+            .collect();
+
+        // 笔记ID和路径是唯一的，优先于标题；标题重名时取排在前面的笔记
+        let mut by_location: HashMap<&str, usize> = HashMap::new();
+        let mut by_name: HashMap<String, usize> = HashMap::new();
+        for (i, note) in notes.iter().enumerate().rev() {
+            by_location.insert(&note.path, i);
+            by_location.insert(&note.id, i);
+            by_name.insert(naming::fold_name(&note.title), i);
+        }
+        // 节点ID在移动和重命名后保持不变，但只在笔记内唯一（如每篇笔记都有 "root"），出现在多篇笔记中的节点ID不解析
+        let mut by_node: HashMap<&str, Option<usize>> = HashMap::new();
+        for (i, document) in &documents {
+            for (node_id, _, _) in walk_nodes(document) {
+                by_node.entry(node_id)
+                    .and_modify(|found| if *found != Some(*i) { *found = None })
+                    .or_insert(Some(*i));
+            }
+        }
+
+        let mut links = Vec::new();
+        for (source, document) in &documents {
+            for (node_id, data, _) in walk_nodes(document) {
+                for target in node_links(data) {
+                    let resolved = match &target {
+                        LinkTarget::Title(title) => by_location.get(title.as_str())
+                            .or_else(|| by_name.get(&naming::fold_name(title)))
+                            .map(|i| (*i, None)),
+                        LinkTarget::Id(id) => match by_location.get(id.as_str()) {
+                            Some(i) => Some((*i, None)),
+                            None => by_node.get(id.as_str()).copied().flatten().map(|i| (i, Some(id.clone()))),
+                        },
+                    };
+                    let Some((target, target_node_id)) = resolved else { continue };
+                    links.push(Link {
+                        source: *source,
+                        node_id: node_id.to_string(),
+                        text: plain_text(data),
+                        target,
+                        target_node_id,
+                    });
+                }
+            }
+        }
+        Ok(LinkIndex { notes, links })
+    }
+
+    // 指向某篇笔记的所有节点，按来源笔记的顺序排列；同一节点多次链接时只返回一次
+    pub fn get_backlinks(&self, note_id: &str) -> Result<Vec<Backlink>, String> {
+        let index = self.link_index()?;
+        let Some(target) = index.notes.iter().position(|note| note.id == note_id) else {
+            return Err(format!("笔记不存在: {}", note_id));
+        };
+        let mut seen = HashSet::new();
+        let backlinks: Vec<Backlink> = index.links
+            .iter()
+            .filter(|link| link.target == target)
+            .filter(|link| seen.insert((link.source, link.node_id.clone(), link.target_node_id.clone())))
+            .map(|link| {
+                let source = &index.notes[link.source];
+                Backlink {
+                    note_id: source.id.clone(),
+                    note_title: source.title.clone(),
+                    path: source.path.clone(),
+                    node_id: link.node_id.clone(),
+                    text: link.text.clone(),
+                    target_node_id: link.target_node_id.clone(),
+                }
+            })
+            .collect();
+        backend_log!("[后端] 笔记 {} 的反向链接: {} 个", note_id, backlinks.len());
+        Ok(backlinks)
+    }
+
+    // 整个工作区的笔记关系图：所有笔记和笔记之间的链接（不含指向自身的链接）
+    pub fn get_link_graph(&self) -> Result<LinkGraph, String> {
+        let index = self.link_index()?;
+        let mut counts: HashMap<(usize, usize), usize> = HashMap::new();
+        for link in index.links.iter().filter(|link| link.source != link.target) {
+            *counts.entry((link.source, link.target)).or_default() += 1;
+        }
+        let mut edges: Vec<((usize, usize), usize)> = counts.into_iter().collect();
+        edges.sort();
+
+        let edges = edges.into_iter().map(|((source, target), count)| LinkGraphEdge {
+            source: index.notes[source].id.clone(),
+            target: index.notes[target].id.clone(),
+            count,
+        }).collect();
+        let nodes = index.notes.into_iter().map(|note| LinkGraphNode {
+            id: note.id,
+            title: note.title,
+            path: note.path,
+            category_id: note.category_id,
+        }).collect();
+        Ok(LinkGraph { nodes, edges })
+    }
+}
+
+// 获取指向笔记的反向链接
+#[tauri::command]
+pub async fn get_backlinks(state: State<'_, WorkspaceState>, note_id: String) -> Result<Vec<Backlink>, String> {
+    state.run(move |workspace| workspace.get_backlinks(&note_id)).await
+}
+
+// 获取整个工作区的笔记关系图
+#[tauri::command]
+pub async fn get_link_graph(state: State<'_, WorkspaceState>) -> Result<LinkGraph, String> {
+    state.run(move |workspace| workspace.get_link_graph()).await
+}
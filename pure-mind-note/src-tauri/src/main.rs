@@ -13,4 +13,5 @@ fn main() {}
 
 // 引入测试模块，测试时使用
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
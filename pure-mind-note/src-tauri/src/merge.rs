@@ -0,0 +1,451 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::nodes::{child_nodes, node_id};
+use crate::note_format;
+
+// 冲突的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictKind {
+    // 两边把同一个字段改成了不同的值，保留我方的值
+    Field,
+    // 一边删除了节点，另一边修改了它，保留节点
+    DeleteModify,
+    // 两边调整了同一组子节点的顺序，保留我方的顺序
+    Order,
+    // 两边把节点移动到了不同的父节点下，保留我方的位置
+    Move,
+    // 合并后节点无法从根节点到达，挂到根节点下
+    Orphan,
+}
+
+// 某个节点上的一处冲突，node_id 为 None 时是笔记本身的字段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeConflict {
+    pub node_id: Option<String>,
+    // 冲突的字段，如 "data.text"、"children"
+    pub field: String,
+    pub kind: ConflictKind,
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+// 合并结果：合并后的笔记内容和无法自动解决的冲突
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeOutcome {
+    pub merged: String,
+    pub conflicts: Vec<NodeConflict>,
+}
+
+// 单个值的三方合并，Err 表示两边改成了不同的值
+fn merge_value<'a>(base: Option<&'a Value>, ours: Option<&'a Value>, theirs: Option<&'a Value>) -> Result<Option<&'a Value>, ()> {
+    if ours == theirs || theirs == base {
+        Ok(ours)
+    } else if ours == base {
+        Ok(theirs)
+    } else {
+        Err(())
+    }
+}
+
+// 两个 Map 的键按出现顺序合并
+fn union_keys<'a>(maps: &[Option<&'a Map<String, Value>>]) -> Vec<&'a String> {
+    let mut seen = HashSet::new();
+    maps.iter()
+        .flatten()
+        .flat_map(|map| map.keys())
+        .filter(|key| seen.insert(*key))
+        .collect()
+}
+
+struct Merger {
+    conflicts: Vec<NodeConflict>,
+}
+
+impl Merger {
+    fn conflict(&mut self, node_id: Option<&str>, field: &str, kind: ConflictKind, values: [Option<&Value>; 3]) {
+        let [base, ours, theirs] = values.map(|value| value.cloned());
+        self.conflicts.push(NodeConflict {
+            node_id: node_id.map(|id| id.to_string()),
+            field: field.to_string(),
+            kind,
+            base,
+            ours,
+            theirs,
+        });
+    }
+
+    // 逐字段合并对象，nested 中的字段（如节点的 data）再逐字段合并一层
+    fn merge_object(
+        &mut self,
+        node_id: Option<&str>,
+        prefix: &str,
+        base: Option<&Map<String, Value>>,
+        ours: Option<&Map<String, Value>>,
+        theirs: Option<&Map<String, Value>>,
+        nested: &[&str],
+    ) -> Map<String, Value> {
+        let mut merged = Map::new();
+        for key in union_keys(&[ours, theirs, base]) {
+            let field = format!("{}{}", prefix, key);
+            let values = [base, ours, theirs].map(|map| map.and_then(|map| map.get(key)));
+            let [b, o, t] = values;
+
+            if nested.contains(&key.as_str()) {
+                let objects = values.map(|value| value.and_then(Value::as_object));
+                if let [b_obj, Some(o_obj), Some(t_obj)] = objects {
+                    let value = self.merge_object(node_id, &format!("{}.", field), b_obj, Some(o_obj), Some(t_obj), &[]);
+                    merged.insert(key.clone(), Value::Object(value));
+                    continue;
+                }
+            }
+
+            if key == "children" && node_id.is_some() {
+                let lists = values.map(|value| value.and_then(Value::as_array).map(Vec::as_slice).unwrap_or(&[]));
+                let (children, order_conflict) = merge_children(lists[0], lists[1], lists[2]);
+                if order_conflict {
+                    self.conflict(node_id, &field, ConflictKind::Order, values);
+                }
+                if !children.is_empty() {
+                    merged.insert(key.clone(), Value::Array(children));
+                }
+                continue;
+            }
+
+            match merge_value(b, o, t) {
+                Ok(Some(value)) => {
+                    merged.insert(key.clone(), value.clone());
+                }
+                Ok(None) => {}
+                Err(()) => {
+                    self.conflict(node_id, &field, ConflictKind::Field, values);
+                    if let Some(value) = o.or(t) {
+                        merged.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        merged
+    }
+
+    // 按节点ID合并节点表中的节点
+    fn merge_nodes(
+        &mut self,
+        base: Option<&Map<String, Value>>,
+        ours: &Map<String, Value>,
+        theirs: &Map<String, Value>,
+    ) -> Map<String, Value> {
+        let mut merged = Map::new();
+        for node_id in union_keys(&[Some(ours), Some(theirs), base]) {
+            let b = base.and_then(|nodes| nodes.get(node_id));
+            let (o, t) = (ours.get(node_id), theirs.get(node_id));
+            let objects = [b, o, t].map(|node| node.and_then(Value::as_object));
+
+            let node = match (o, t) {
+                (Some(_), Some(_)) => Some(self.merge_object(Some(node_id), "", objects[0], objects[1], objects[2], &["data"])),
+                // 一边删除：另一边未修改时删除，修改过时保留修改后的节点
+                (Some(kept), None) | (None, Some(kept)) => match b {
+                    None => kept.as_object().cloned(),
+                    Some(b) if b == kept => None,
+                    Some(_) => {
+                        self.conflict(Some(node_id), "", ConflictKind::DeleteModify, [b, o, t]);
+                        kept.as_object().cloned()
+                    }
+                },
+                (None, None) => None,
+            };
+            if let Some(node) = node {
+                merged.insert(node_id.clone(), Value::Object(node));
+            }
+        }
+        merged
+    }
+
+    // 修正合并后的树结构：去掉指向已删除节点的引用，每个节点只保留一个父节点，并确保都能从根节点到达
+    fn repair_tree(
+        &mut self,
+        root_id: Option<&str>,
+        nodes: &mut Map<String, Value>,
+        ours: &Map<String, Value>,
+        theirs: &Map<String, Value>,
+    ) {
+        let existing: HashSet<String> = nodes.keys().cloned().collect();
+        for node in nodes.values_mut() {
+            if let Some(children) = node.get_mut("children").and_then(Value::as_array_mut) {
+                children.retain(|child| child.as_str().is_some_and(|id| existing.contains(id)));
+            }
+        }
+
+        // 两个版本中每个节点的父节点
+        let parent_map = |version: &Map<String, Value>| -> HashMap<String, String> {
+            version.iter()
+                .flat_map(|(parent, node)| {
+                    children_of(node).into_iter().map(move |child| (child.to_string(), parent.clone()))
+                })
+                .collect()
+        };
+        let (ours_parent, theirs_parent) = (parent_map(ours), parent_map(theirs));
+
+        // 同一个节点出现在多个父节点下
+        let mut parents: HashMap<String, Vec<String>> = HashMap::new();
+        for (parent, node) in nodes.iter() {
+            for child in children_of(node) {
+                parents.entry(child.to_string()).or_default().push(parent.clone());
+            }
+        }
+        let mut moved: Vec<(String, Vec<String>)> = parents.into_iter().filter(|(_, list)| list.len() > 1).collect();
+        moved.sort();
+        for (child, list) in moved {
+            let keep = ours_parent.get(&child)
+                .filter(|parent| list.contains(parent))
+                .unwrap_or(&list[0])
+                .clone();
+            let [ours_value, theirs_value] = [&ours_parent, &theirs_parent]
+                .map(|parents| parents.get(&child).map(|parent| Value::String(parent.clone())));
+            self.conflict(Some(&child), "parent", ConflictKind::Move, [None, ours_value.as_ref(), theirs_value.as_ref()]);
+            for parent in list.iter().filter(|parent| **parent != keep) {
+                remove_child(nodes, parent, &child);
+            }
+        }
+
+        // 无法从根节点到达的节点（如父节点被另一边删除、或两边的移动形成了环）挂到根节点下
+        let Some(root_id) = root_id.filter(|id| nodes.contains_key(*id)) else {
+            return;
+        };
+        loop {
+            let start = {
+                let reachable = reachable_from(nodes, root_id);
+                nodes.keys().find(|id| !reachable.contains(id.as_str())).cloned()
+            };
+            let Some(start) = start else {
+                break;
+            };
+
+            // 不可达节点的父节点也不可达，沿父节点向上找到子树的顶端；处在环中时从当前节点断开
+            let mut orphan = start;
+            let mut visited = HashSet::from([orphan.clone()]);
+            while let Some(parent) = parent_of(nodes, &orphan) {
+                if !visited.insert(parent.clone()) {
+                    break;
+                }
+                orphan = parent;
+            }
+
+            if let Some(parent) = parent_of(nodes, &orphan) {
+                remove_child(nodes, &parent, &orphan);
+            }
+            self.conflict(Some(&orphan), "parent", ConflictKind::Orphan, [None, None, None]);
+            if let Some(root) = nodes.get_mut(root_id).and_then(Value::as_object_mut) {
+                let children = root.entry("children").or_insert_with(|| Value::Array(Vec::new()));
+                if let Some(children) = children.as_array_mut() {
+                    children.push(Value::String(orphan));
+                }
+            }
+        }
+    }
+}
+
+fn parent_of(nodes: &Map<String, Value>, child: &str) -> Option<String> {
+    nodes.iter()
+        .find(|(_, node)| children_of(node).contains(&child))
+        .map(|(parent, _)| parent.clone())
+}
+
+fn children_of(node: &Value) -> Vec<&str> {
+    node.get("children")
+        .and_then(Value::as_array)
+        .map(|children| children.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+// 从父节点的子节点列表中移除，列表为空时去掉该字段
+fn remove_child(nodes: &mut Map<String, Value>, parent: &str, child: &str) {
+    let Some(node) = nodes.get_mut(parent).and_then(Value::as_object_mut) else {
+        return;
+    };
+    if let Some(children) = node.get_mut("children").and_then(Value::as_array_mut) {
+        children.retain(|id| id.as_str() != Some(child));
+        if children.is_empty() {
+            node.remove("children");
+        }
+    }
+}
+
+fn reachable_from<'a>(nodes: &'a Map<String, Value>, root_id: &'a str) -> HashSet<&'a str> {
+    let mut reachable = HashSet::new();
+    let mut pending = vec![root_id];
+    while let Some(id) = pending.pop() {
+        if reachable.insert(id) {
+            if let Some(node) = nodes.get(id) {
+                pending.extend(children_of(node));
+            }
+        }
+    }
+    reachable
+}
+
+// 列表中与基准共有的元素是否改变了相对顺序
+fn is_reordered(base: &[Value], side: &[Value]) -> bool {
+    let common_base: Vec<&Value> = base.iter().filter(|item| side.contains(item)).collect();
+    let common_side: Vec<&Value> = side.iter().filter(|item| base.contains(item)).collect();
+    common_base != common_side
+}
+
+// 子节点列表的三方合并：两边的增删都保留，顺序以调整过顺序的一边为准；返回值的第二项表示顺序冲突
+fn merge_children(base: &[Value], ours: &[Value], theirs: &[Value]) -> (Vec<Value>, bool) {
+    let ours_reordered = is_reordered(base, ours);
+    let theirs_reordered = is_reordered(base, theirs);
+    let (primary, secondary) = if theirs_reordered && !ours_reordered { (theirs, ours) } else { (ours, theirs) };
+    let order_conflict = ours_reordered && theirs_reordered && {
+        let common_ours: Vec<&Value> = ours.iter().filter(|item| base.contains(item) && theirs.contains(item)).collect();
+        let common_theirs: Vec<&Value> = theirs.iter().filter(|item| base.contains(item) && ours.contains(item)).collect();
+        common_ours != common_theirs
+    };
+
+    // 去掉另一边删除的元素
+    let mut merged: Vec<Value> = primary.iter()
+        .filter(|item| !base.contains(item) || secondary.contains(item))
+        .cloned()
+        .collect();
+
+    // 另一边新增的元素插在它在那一边的前一个元素之后
+    for (index, item) in secondary.iter().enumerate() {
+        if base.contains(item) || merged.contains(item) {
+            continue;
+        }
+        let position = secondary[..index].iter()
+            .rev()
+            .find_map(|previous| merged.iter().position(|existing| existing == previous))
+            .map(|position| position + 1)
+            .unwrap_or(0);
+        merged.insert(position, item.clone());
+    }
+
+    // 同一个元素不应出现两次
+    let mut seen = BTreeSet::new();
+    merged.retain(|item| seen.insert(item.to_string()));
+    (merged, order_conflict)
+}
+
+fn parse_version(content: &str, name: &str) -> Result<Map<String, Value>, String> {
+    let mut note = match serde_json::from_str(content) {
+        Ok(note @ Value::Object(_)) => note,
+        Ok(_) => return Err(format!("{}版本不是有效的笔记", name)),
+        Err(e) => return Err(format!("无法解析{}版本: {}", name, e)),
+    };
+    // 各版本可能保存于不同的格式版本，先统一迁移到当前的嵌套节点树
+    note_format::migrate_note(&mut note).map_err(|e| format!("无法读取{}版本: {}", name, e))?;
+    match note {
+        Value::Object(note) => Ok(note),
+        _ => Err(format!("{}版本不是有效的笔记", name)),
+    }
+}
+
+// 把嵌套的节点树展开为节点表 {节点ID: {data, children: [子节点ID]}}，便于按节点合并，返回节点在表中的键。
+// 没有ID的节点以 "父节点ID/位置" 为键；同一个ID重复出现时依次加上 "#2"、"#3"，各版本中得到的键相同
+fn flatten_node(node: &Value, fallback: String, table: &mut Map<String, Value>) -> String {
+    let id = node_id(node).filter(|id| !id.is_empty()).map_or(fallback, str::to_string);
+    let mut key = id.clone();
+    let mut count = 1;
+    while table.contains_key(&key) {
+        count += 1;
+        key = format!("{}#{}", id, count);
+    }
+    table.insert(key.clone(), Value::Null);
+
+    let children: Vec<Value> = child_nodes(node)
+        .iter()
+        .enumerate()
+        .map(|(index, child)| Value::String(flatten_node(child, format!("{}/{}", key, index), table)))
+        .collect();
+    let mut entry = node.as_object().cloned().unwrap_or_default();
+    entry.remove("children");
+    if !children.is_empty() {
+        entry.insert("children".to_string(), Value::Array(children));
+    }
+    table.insert(key.clone(), Value::Object(entry));
+    key
+}
+
+// 从节点表中取出节点及其子树，还原为嵌套的节点树
+fn nest_node(table: &mut Map<String, Value>, key: &str) -> Option<Value> {
+    let Value::Object(mut node) = table.remove(key)? else {
+        return None;
+    };
+    let children = match node.remove("children") {
+        Some(Value::Array(children)) => children,
+        _ => Vec::new(),
+    };
+    let children: Vec<Value> = children.iter()
+        .filter_map(Value::as_str)
+        .filter_map(|child| nest_node(table, child))
+        .collect();
+    node.insert("children".to_string(), Value::Array(children));
+    Some(Value::Object(node))
+}
+
+// 三方合并同一篇笔记的两个版本；没有共同祖先时 base 为 None，按两方合并处理
+pub fn merge_notes(base: Option<&str>, ours: &str, theirs: &str) -> Result<MergeOutcome, String> {
+    let base = base.map(|base| parse_version(base, "共同祖先")).transpose()?;
+    let ours = parse_version(ours, "我方")?;
+    let theirs = parse_version(theirs, "对方")?;
+
+    // 节点按ID合并，节点的父节点和位置由节点表中的子节点列表决定
+    let nodes = |note: &Map<String, Value>| {
+        let mut table = Map::new();
+        let root = note.get("data")
+            .filter(|root| root.is_object())
+            .map(|root| flatten_node(root, "root".to_string(), &mut table));
+        (root, table)
+    };
+    let (_, base_nodes) = base.as_ref().map(nodes).unzip();
+    let (ours_root, ours_nodes) = nodes(&ours);
+    let (theirs_root, theirs_nodes) = nodes(&theirs);
+
+    let mut merger = Merger { conflicts: Vec::new() };
+
+    // 笔记本身的字段（data 和 lastUpdated 单独处理）
+    let strip = |note: &Map<String, Value>| {
+        let mut note = note.clone();
+        note.remove("data");
+        note.remove("lastUpdated");
+        note
+    };
+    let base_fields = base.as_ref().map(strip);
+    let mut merged = merger.merge_object(None, "", base_fields.as_ref(), Some(&strip(&ours)), Some(&strip(&theirs)), &[]);
+
+    let mut merged_nodes = merger.merge_nodes(base_nodes.as_ref(), &ours_nodes, &theirs_nodes);
+    // 根节点以我方为准，我方没有节点树时使用对方的根节点
+    let root_id = [ours_root, theirs_root].into_iter().flatten().find(|id| merged_nodes.contains_key(id));
+    merger.repair_tree(root_id.as_deref(), &mut merged_nodes, &ours_nodes, &theirs_nodes);
+    if let Some(root) = root_id.and_then(|id| nest_node(&mut merged_nodes, &id)) {
+        merged.insert("data".to_string(), root);
+    }
+
+    // 更新时间取较晚的一方
+    let last_updated = [ours.get("lastUpdated"), theirs.get("lastUpdated")]
+        .into_iter()
+        .flatten()
+        .max_by(|a, b| a.as_str().cmp(&b.as_str()))
+        .cloned();
+    if let Some(last_updated) = last_updated {
+        merged.insert("lastUpdated".to_string(), last_updated);
+    }
+
+    let merged = serde_json::to_string_pretty(&Value::Object(merged))
+        .map_err(|e| format!("无法序列化合并结果: {}", e))?;
+    Ok(MergeOutcome { merged, conflicts: merger.conflicts })
+}
+
+// 合并同一篇笔记的两个分歧版本
+#[tauri::command]
+pub async fn merge_note_versions(base: Option<String>, ours: String, theirs: String) -> Result<MergeOutcome, String> {
+    tauri::async_runtime::spawn_blocking(move || merge_notes(base.as_deref(), &ours, &theirs))
+        .await
+        .map_err(|e| format!("后台任务执行失败: {}", e))?
+}
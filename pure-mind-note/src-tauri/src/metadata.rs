@@ -0,0 +1,113 @@
+use std::{fs, path::Path};
+use serde::{Deserialize, Serialize};
+
+use crate::naming;
+use crate::workspace::write_atomic;
+
+// 分类/子分类目录中保存元数据的文件名
+pub const METADATA_FILE: &str = "_category.json";
+
+// 分类元数据，目录名只是文件系统中的标识，显示相关的信息都保存在这里
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CategoryMetadata {
+    // 显示名称
+    pub name: Option<String>,
+    pub description: Option<String>,
+    // 颜色，格式为 #RGB 或 #RRGGBB
+    pub color: Option<String>,
+    // emoji 图标
+    pub icon: Option<String>,
+    // 创建时间（RFC 3339）
+    pub created_time: Option<String>,
+    // 排序位置，越小越靠前
+    pub position: Option<i64>,
+    // 目录下笔记的自定义顺序（笔记文件名，不含扩展名）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub note_order: Vec<String>,
+}
+
+// 元数据的部分更新，未提供的字段保持不变，空字符串表示清除该字段
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CategoryMetadataUpdate {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub position: Option<i64>,
+}
+
+impl CategoryMetadata {
+    // 笔记在自定义顺序中的位置
+    pub fn note_position(&self, file_stem: &str) -> Option<i64> {
+        self.note_order.iter().position(|stem| stem == file_stem).map(|index| index as i64)
+    }
+
+    // 新建分类时的元数据
+    pub fn new(name: String) -> Self {
+        Self {
+            name: Some(name),
+            created_time: Some(chrono::Local::now().to_rfc3339()),
+            ..Default::default()
+        }
+    }
+
+    // 校验并应用一次更新
+    pub fn apply(&mut self, update: CategoryMetadataUpdate) -> Result<(), String> {
+        if let Some(name) = update.name {
+            self.name = Some(naming::validate_display_name(&name)?);
+        }
+        if let Some(description) = update.description {
+            let description = description.trim();
+            self.description = (!description.is_empty()).then(|| description.to_string());
+        }
+        if let Some(color) = update.color {
+            let color = color.trim();
+            if !color.is_empty() && !is_valid_color(color) {
+                return Err(format!("无效的颜色值: '{}'，应为 #RGB 或 #RRGGBB", color));
+            }
+            self.color = (!color.is_empty()).then(|| color.to_lowercase());
+        }
+        if let Some(icon) = update.icon {
+            let icon = icon.trim();
+            if icon.chars().count() > 8 || icon.chars().any(char::is_control) {
+                return Err(format!("无效的图标: '{}'，应为单个 emoji", icon));
+            }
+            self.icon = (!icon.is_empty()).then(|| icon.to_string());
+        }
+        if let Some(position) = update.position {
+            self.position = Some(position);
+        }
+        Ok(())
+    }
+}
+
+// 颜色只接受十六进制格式
+fn is_valid_color(color: &str) -> bool {
+    match color.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+// 读取目录的元数据，文件不存在或无法解析时返回默认值
+pub fn read_metadata(dir: &Path) -> CategoryMetadata {
+    fs::read_to_string(dir.join(METADATA_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// 写入目录的元数据
+pub fn write_metadata(dir: &Path, metadata: &CategoryMetadata) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(metadata)
+        .map_err(|e| format!("无法序列化分类元数据: {}", e))?;
+    write_atomic(&dir.join(METADATA_FILE), content.as_bytes())
+        .map_err(|e| format!("无法写入分类元数据: {}", e))
+}
+
+// 判断路径是否为元数据文件，扫描笔记时需要跳过
+pub fn is_metadata_file(path: &Path) -> bool {
+    path.file_name().and_then(|name| name.to_str()) == Some(METADATA_FILE)
+}
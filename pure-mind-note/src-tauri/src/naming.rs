@@ -0,0 +1,96 @@
+use std::{fs, path::Path};
+use unicode_normalization::UnicodeNormalization;
+
+// Windows 保留的设备名，无论大小写或是否带扩展名都不能作为文件名
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// 在任一平台上不能出现在文件名中的字符，以及笔记ID中用作分隔符的 '#'
+const FORBIDDEN_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*', '#'];
+
+// 文件名的最大字节数，给扩展名和同步冲突后缀留出余量
+const MAX_NAME_BYTES: usize = 200;
+
+// 校验用户输入的显示名称：去除首尾空白，不能为空且不能包含控制字符
+pub fn validate_display_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("名称不能为空".to_string());
+    }
+    if name.chars().any(char::is_control) {
+        return Err(format!("名称 '{}' 包含不可见的控制字符", name.escape_debug()));
+    }
+    Ok(name.nfc().collect())
+}
+
+// 将显示名称转换为可在 Windows、macOS、Linux 间共享的文件名
+pub fn slugify(display_name: &str) -> Result<String, String> {
+    let name = validate_display_name(display_name)?;
+
+    // 替换各平台的非法字符
+    let mut slug: String = name
+        .chars()
+        .map(|c| if FORBIDDEN_CHARS.contains(&c) { '_' } else { c })
+        .collect();
+
+    // Windows 不允许以点或空格结尾
+    let trimmed_len = slug.trim_end_matches(['.', ' ']).len();
+    slug.truncate(trimmed_len);
+
+    // 以点开头的名称在类 Unix 系统上会被隐藏
+    if slug.starts_with('.') {
+        slug.replace_range(..1, "_");
+    }
+
+    if slug.is_empty() {
+        return Err(format!("名称 '{}' 不能转换为有效的文件名", name));
+    }
+
+    // 限制长度，按字符边界截断
+    if slug.len() > MAX_NAME_BYTES {
+        let mut end = MAX_NAME_BYTES;
+        while !slug.is_char_boundary(end) {
+            end -= 1;
+        }
+        slug.truncate(end);
+        let trimmed_len = slug.trim_end_matches(['.', ' ']).len();
+        slug.truncate(trimmed_len);
+    }
+
+    // 避开 Windows 保留设备名（如 CON、nul.txt）
+    let stem = slug.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem.trim_end())) {
+        slug.insert(stem.len(), '_');
+    }
+
+    Ok(slug)
+}
+
+// 比较文件名时使用的折叠形式：统一为 NFC 并忽略大小写
+pub fn fold_name(name: &str) -> String {
+    name.nfc().collect::<String>().to_lowercase()
+}
+
+// 检查目录中是否已存在与 name 冲突的条目（大小写或 Unicode 规范化形式不同也视为冲突）
+// extension 为 Some 时只比较带该扩展名的文件的主干名
+pub fn find_conflict(dir: &Path, name: &str, extension: Option<&str>) -> Option<String> {
+    let target = fold_name(name);
+    let entries = fs::read_dir(dir).ok()?;
+
+    entries.flatten().find_map(|entry| {
+        let path = entry.path();
+        let existing = match extension {
+            Some(ext) => {
+                if path.extension().and_then(|e| e.to_str()) != Some(ext) {
+                    return None;
+                }
+                path.file_stem()?.to_str()?.to_string()
+            }
+            None => entry.file_name().to_str()?.to_string(),
+        };
+        (fold_name(&existing) == target).then_some(existing)
+    })
+}
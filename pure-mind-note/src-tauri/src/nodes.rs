@@ -0,0 +1,448 @@
+use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tauri::State;
+
+use crate::note_format;
+use crate::patch::validate_note_tree;
+use crate::workspace::{Workspace, WorkspaceState};
+
+// 笔记中的一个节点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+    pub id: String,
+    // 根节点没有父节点
+    pub parent_id: Option<String>,
+    // 节点数据（text、tag 等）
+    pub data: Map<String, Value>,
+    pub children: Vec<String>,
+}
+
+// 节点的ID：脑图组件生成的 data.uid，没有时使用旧格式的 data.id
+pub fn node_id(node: &Value) -> Option<&str> {
+    let data = node.get("data")?;
+    data.get("uid").or_else(|| data.get("id")).and_then(Value::as_str)
+}
+
+// 节点的子节点
+pub(crate) fn child_nodes(node: &Value) -> &[Value] {
+    node.get("children").and_then(Value::as_array).map_or(&[], Vec::as_slice)
+}
+
+// 笔记的 data 是脑图组件 getData() 得到的嵌套树 {data: {uid, text, ...}, children: [...]}，
+// 从根节点开始按顺序遍历，返回 (节点ID, 节点数据, 深度)
+pub fn walk_nodes(note: &Value) -> Vec<(&str, &Map<String, Value>, usize)> {
+    let mut visited = Vec::new();
+    let mut pending: Vec<(&Value, usize)> = note.get("data").map(|root| (root, 0)).into_iter().collect();
+    while let Some((node, depth)) = pending.pop() {
+        if let (Some(id), Some(data)) = (node_id(node), node.get("data").and_then(Value::as_object)) {
+            visited.push((id, data, depth));
+        }
+        pending.extend(child_nodes(node).iter().rev().map(|child| (child, depth + 1)));
+    }
+    visited
+}
+
+// 按顺序修改树中每个节点的数据
+pub(crate) fn for_each_node_data(root: &mut Value, edit: &mut impl FnMut(&mut Map<String, Value>)) {
+    if let Some(data) = root.get_mut("data").and_then(Value::as_object_mut) {
+        edit(data);
+    }
+    if let Some(children) = root.get_mut("children").and_then(Value::as_array_mut) {
+        for child in children {
+            for_each_node_data(child, edit);
+        }
+    }
+}
+
+// 子树中所有节点的ID
+fn subtree_ids(node: &Value) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut pending = vec![node];
+    while let Some(node) = pending.pop() {
+        ids.extend(node_id(node).map(str::to_string));
+        pending.extend(child_nodes(node).iter().rev());
+    }
+    ids
+}
+
+// 节点的纯文字：富文本节点的 text 是 HTML，去掉标签并还原常见的实体
+pub fn plain_text(data: &Map<String, Value>) -> String {
+    let text = data.get("text").and_then(Value::as_str).unwrap_or_default();
+    if data.get("richText").and_then(Value::as_bool) != Some(true) && !text.starts_with('<') {
+        return text.to_string();
+    }
+    let mut plain = String::new();
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => plain.push(c),
+            _ => {}
+        }
+    }
+    plain.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+// 节点的标签
+pub fn node_tags(data: &Map<String, Value>) -> Vec<String> {
+    data.get("tag")
+        .and_then(Value::as_array)
+        .map(|tags| tags.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+// 生成笔记中尚未使用的节点ID
+pub(crate) fn new_node_id(used: &HashSet<String>) -> String {
+    loop {
+        let id = uuid::Uuid::new_v4().to_string();
+        if !used.contains(&id) {
+            return id;
+        }
+    }
+}
+
+// 笔记的节点树，节点按ID查找
+struct NoteTree<'a> {
+    root: &'a mut Value,
+}
+
+impl NoteTree<'_> {
+    // 从笔记中取出节点树，根节点必须有数据
+    fn of(note: &mut Value) -> Result<NoteTree<'_>, String> {
+        match note.get_mut("data") {
+            Some(root) if root.get("data").is_some_and(Value::is_object) => Ok(NoteTree { root }),
+            _ => Err("笔记缺少节点数据".to_string()),
+        }
+    }
+
+    fn root_id(&self) -> String {
+        node_id(self.root).unwrap_or_default().to_string()
+    }
+
+    // 节点在树中的位置：从根节点开始每一级子节点的下标
+    fn locate(&self, id: &str) -> Result<Vec<usize>, String> {
+        let mut pending = vec![(&*self.root, Vec::new())];
+        while let Some((node, position)) = pending.pop() {
+            if node_id(node) == Some(id) {
+                return Ok(position);
+            }
+            for (index, child) in child_nodes(node).iter().enumerate() {
+                let mut child_position = position.clone();
+                child_position.push(index);
+                pending.push((child, child_position));
+            }
+        }
+        Err(format!("节点不存在: {}", id))
+    }
+
+    fn at(&self, position: &[usize]) -> &Value {
+        position.iter().fold(&*self.root, |node, index| &child_nodes(node)[*index])
+    }
+
+    fn at_mut(&mut self, position: &[usize]) -> &mut Value {
+        let mut node = &mut *self.root;
+        for index in position {
+            node = &mut node["children"][*index];
+        }
+        node
+    }
+
+    fn node(&self, id: &str) -> Result<&Value, String> {
+        Ok(self.at(&self.locate(id)?))
+    }
+
+    fn ids(&self) -> HashSet<String> {
+        subtree_ids(self.root).into_iter().collect()
+    }
+
+    fn info(&self, id: &str) -> Result<NodeInfo, String> {
+        let position = self.locate(id)?;
+        let parent_id = position.split_last()
+            .and_then(|(_, parent)| node_id(self.at(parent)))
+            .map(str::to_string);
+        Ok(node_info(self.at(&position), parent_id))
+    }
+
+    // 插入到父节点的子节点列表中，index 超出范围或为 None 时放在最后
+    fn attach(&mut self, parent_id: &str, node: Value, index: Option<usize>) -> Result<(), String> {
+        let position = self.locate(parent_id)?;
+        let parent = self.at_mut(&position)
+            .as_object_mut()
+            .ok_or_else(|| format!("节点格式错误: {}", parent_id))?;
+        let children = parent.entry("children").or_insert_with(|| Value::Array(Vec::new()));
+        if !children.is_array() {
+            *children = Value::Array(Vec::new());
+        }
+        if let Some(children) = children.as_array_mut() {
+            let index = index.unwrap_or(children.len()).min(children.len());
+            children.insert(index, node);
+        }
+        Ok(())
+    }
+
+    // 从父节点下取出节点及其后代
+    fn detach(&mut self, id: &str) -> Result<Value, String> {
+        let position = self.locate(id)?;
+        let Some((index, parent)) = position.split_last() else {
+            return Err("不能移动或删除根节点".to_string());
+        };
+        match self.at_mut(parent).get_mut("children").and_then(Value::as_array_mut) {
+            Some(children) => Ok(children.remove(*index)),
+            None => Err(format!("节点不存在: {}", id)),
+        }
+    }
+
+    // 把另一篇笔记中取出的子树插入到 parent_id 下，与已有节点冲突的ID会被重新生成，返回子树顶端节点的ID
+    fn insert_subtree(&mut self, mut subtree: Value, parent_id: &str, index: Option<usize>) -> Result<String, String> {
+        let mut used = self.ids();
+        for_each_node_data(&mut subtree, &mut |data| {
+            let id = data.get("uid").or_else(|| data.get("id")).and_then(Value::as_str).map(str::to_string);
+            let id = match id {
+                Some(id) if !used.contains(&id) => id,
+                _ => new_node_id(&used),
+            };
+            data.remove("id");
+            data.insert("uid".to_string(), Value::String(id.clone()));
+            used.insert(id);
+        });
+        let top = node_id(&subtree).unwrap_or_default().to_string();
+        self.attach(parent_id, subtree, index)?;
+        Ok(top)
+    }
+}
+
+fn node_info(node: &Value, parent_id: Option<String>) -> NodeInfo {
+    NodeInfo {
+        id: node_id(node).unwrap_or_default().to_string(),
+        parent_id,
+        data: node.get("data").and_then(Value::as_object).cloned().unwrap_or_default(),
+        children: child_nodes(node).iter().filter_map(node_id).map(str::to_string).collect(),
+    }
+}
+
+impl Workspace {
+    pub(crate) fn read_note_value(&self, path: &str) -> Result<Value, String> {
+        serde_json::from_str(&self.read_note(path)?).map_err(|e| format!("笔记内容不是有效的JSON: {}", e))
+    }
+
+    // 读取笔记、修改节点树、检查树结构后写回
+    fn edit_nodes<T>(&self, path: &str, edit: impl FnOnce(&mut NoteTree) -> Result<T, String>) -> Result<T, String> {
+        let _guard = self.lock_edits();
+        self.edit_nodes_locked(path, edit)
+    }
+
+    // 同 edit_nodes，调用者已持有 lock_edits
+    fn edit_nodes_locked<T>(&self, path: &str, edit: impl FnOnce(&mut NoteTree) -> Result<T, String>) -> Result<T, String> {
+        let mut note = self.read_note_value(path)?;
+        let result = edit(&mut NoteTree::of(&mut note)?)?;
+
+        validate_note_tree(&note)?;
+        note_format::migrate_note(&mut note)?;
+        if let Some(object) = note.as_object_mut() {
+            let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+            object.insert("lastUpdated".to_string(), Value::String(now));
+        }
+        let content = serde_json::to_string_pretty(&note).map_err(|e| format!("无法序列化笔记内容: {}", e))?;
+        self.save_note_locked(path, &content)?;
+        Ok(result)
+    }
+
+    // 笔记根节点的ID
+    pub(crate) fn root_node_id(&self, path: &str) -> Result<String, String> {
+        Ok(NoteTree::of(&mut self.read_note_value(path)?)?.root_id())
+    }
+
+    // 读取一个节点
+    pub fn get_node(&self, path: &str, node_id: &str) -> Result<NodeInfo, String> {
+        NoteTree::of(&mut self.read_note_value(path)?)?.info(node_id)
+    }
+
+    // 读取一个节点的所有子节点，parent_id 为 None 时读取根节点的子节点，整篇笔记只读取一次
+    pub(crate) fn get_child_nodes(&self, path: &str, parent_id: Option<&str>) -> Result<Vec<NodeInfo>, String> {
+        let mut note = self.read_note_value(path)?;
+        let tree = NoteTree::of(&mut note)?;
+        let parent = match parent_id {
+            Some(parent_id) => tree.node(parent_id)?,
+            None => &*tree.root,
+        };
+        let parent_id = node_id(parent).map(str::to_string);
+        Ok(child_nodes(parent).iter().map(|child| node_info(child, parent_id.clone())).collect())
+    }
+
+    // 在 parent_id 下添加节点，返回新节点；data 中的 uid 由后端生成
+    pub fn add_node(
+        &self,
+        path: &str,
+        parent_id: &str,
+        mut data: Map<String, Value>,
+        index: Option<usize>,
+    ) -> Result<NodeInfo, String> {
+        if !data.get("text").is_some_and(Value::is_string) {
+            return Err("节点必须有文字".to_string());
+        }
+        self.edit_nodes(path, |tree| {
+            tree.node(parent_id)?;
+            let id = new_node_id(&tree.ids());
+            data.remove("id");
+            data.insert("uid".to_string(), Value::String(id.clone()));
+            tree.attach(parent_id, json!({ "data": data, "children": [] }), index)?;
+            tree.info(&id)
+        })
+    }
+
+    // 合并节点数据，值为 null 的字段会被删除；节点ID不能修改
+    pub fn update_node(&self, path: &str, node_id: &str, changes: Map<String, Value>) -> Result<NodeInfo, String> {
+        if changes.contains_key("uid") || changes.contains_key("id") {
+            return Err("不能修改节点ID".to_string());
+        }
+        if changes.get("text").is_some_and(|text| !text.is_string()) {
+            return Err("节点必须有文字".to_string());
+        }
+        self.edit_nodes(path, |tree| {
+            let position = tree.locate(node_id)?;
+            let data = tree.at_mut(&position)
+                .get_mut("data")
+                .and_then(Value::as_object_mut)
+                .ok_or_else(|| format!("节点格式错误: {}", node_id))?;
+            for (key, value) in changes {
+                if value.is_null() {
+                    data.remove(&key);
+                } else {
+                    data.insert(key, value);
+                }
+            }
+            tree.info(node_id)
+        })
+    }
+
+    // 删除节点及其所有后代，返回被删除的节点ID；根节点不能删除
+    pub fn delete_node(&self, path: &str, node_id: &str) -> Result<Vec<String>, String> {
+        self.edit_nodes(path, |tree| {
+            tree.node(node_id)?;
+            if node_id == tree.root_id() {
+                return Err("不能删除根节点".to_string());
+            }
+            Ok(subtree_ids(&tree.detach(node_id)?))
+        })
+    }
+
+    // 把节点移动到 parent_id 下的 index 位置；不能移动根节点，也不能移动到自己的后代下
+    pub fn move_node(&self, path: &str, node_id: &str, parent_id: &str, index: Option<usize>) -> Result<NodeInfo, String> {
+        self.edit_nodes(path, |tree| {
+            let node = tree.node(node_id)?;
+            tree.node(parent_id)?;
+            if node_id == tree.root_id() {
+                return Err("不能移动根节点".to_string());
+            }
+            if subtree_ids(node).iter().any(|id| id == parent_id) {
+                return Err("不能把节点移动到它自己的子节点下".to_string());
+            }
+            let node = tree.detach(node_id)?;
+            tree.attach(parent_id, node, index)?;
+            tree.info(node_id)
+        })
+    }
+
+    // 把节点及其后代移动到另一篇笔记的 parent_id 下，返回移动后的节点；
+    // 节点ID保持不变，与目标笔记中已有的ID冲突时重新生成
+    pub fn move_node_to_note(
+        &self,
+        path: &str,
+        node_id: &str,
+        target_path: &str,
+        parent_id: Option<&str>,
+        index: Option<usize>,
+    ) -> Result<NodeInfo, String> {
+        if self.resolve(path)? == self.resolve(target_path)? {
+            return match parent_id {
+                Some(parent_id) => self.move_node(path, node_id, parent_id, index),
+                None => Err("请指定目标父节点".to_string()),
+            };
+        }
+        let _guard = self.lock_edits();
+
+        // 先写入目标笔记，再从原笔记中删除，中途失败时不会丢失节点
+        let mut source = self.read_note_value(path)?;
+        let tree = NoteTree::of(&mut source)?;
+        let subtree = tree.node(node_id)?.clone();
+        if node_id == tree.root_id() {
+            return Err("不能移动根节点".to_string());
+        }
+
+        let moved = self.edit_nodes_locked(target_path, |tree| {
+            let parent_id = parent_id.map(str::to_string).unwrap_or_else(|| tree.root_id());
+            let top = tree.insert_subtree(subtree, &parent_id, index)?;
+            tree.info(&top)
+        })?;
+        self.edit_nodes_locked(path, |tree| tree.detach(node_id).map(|_| ()))?;
+        Ok(moved)
+    }
+}
+
+// 读取一个节点
+#[tauri::command]
+pub async fn get_node(state: State<'_, WorkspaceState>, path: String, node_id: String) -> Result<NodeInfo, String> {
+    state.run(move |workspace| workspace.get_node(&path, &node_id)).await
+}
+
+// 添加节点
+#[tauri::command]
+pub async fn add_node(
+    state: State<'_, WorkspaceState>,
+    path: String,
+    parent_id: String,
+    data: Map<String, Value>,
+    index: Option<usize>,
+) -> Result<NodeInfo, String> {
+    state.run(move |workspace| workspace.add_node(&path, &parent_id, data, index)).await
+}
+
+// 修改节点数据
+#[tauri::command]
+pub async fn update_node(
+    state: State<'_, WorkspaceState>,
+    path: String,
+    node_id: String,
+    changes: Map<String, Value>,
+) -> Result<NodeInfo, String> {
+    state.run(move |workspace| workspace.update_node(&path, &node_id, changes)).await
+}
+
+// 删除节点及其后代
+#[tauri::command]
+pub async fn delete_node(state: State<'_, WorkspaceState>, path: String, node_id: String) -> Result<Vec<String>, String> {
+    state.run(move |workspace| workspace.delete_node(&path, &node_id)).await
+}
+
+// 移动节点
+#[tauri::command]
+pub async fn move_node(
+    state: State<'_, WorkspaceState>,
+    path: String,
+    node_id: String,
+    parent_id: String,
+    index: Option<usize>,
+) -> Result<NodeInfo, String> {
+    state.run(move |workspace| workspace.move_node(&path, &node_id, &parent_id, index)).await
+}
+
+// 把节点移动到另一篇笔记
+#[tauri::command]
+pub async fn move_node_to_note(
+    state: State<'_, WorkspaceState>,
+    path: String,
+    node_id: String,
+    target_path: String,
+    parent_id: Option<String>,
+    index: Option<usize>,
+) -> Result<NodeInfo, String> {
+    state.run(move |workspace| workspace.move_node_to_note(&path, &node_id, &target_path, parent_id.as_deref(), index)).await
+}
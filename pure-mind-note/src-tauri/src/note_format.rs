@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tauri::State;
+
+use crate::nodes;
+use crate::note_lock;
+use crate::workspace::{Workspace, WorkspaceState};
+
+// 当前的笔记格式版本，每次调整结构时加一并在 MIGRATIONS 中补充迁移
+pub const NOTE_FORMAT_VERSION: u64 = 2;
+
+// 笔记中记录格式版本的字段
+pub const FORMAT_VERSION_KEY: &str = "formatVersion";
+
+// 笔记的格式版本，没有该字段的是版本 0
+pub fn format_version(note: &Value) -> u64 {
+    note.get(FORMAT_VERSION_KEY).and_then(Value::as_u64).unwrap_or(0)
+}
+
+// 旧版本的标签可能是带 text 或 value 字段的对象，取出其中的文字
+fn tag_text(tag: Value) -> Option<String> {
+    let text = match tag {
+        Value::String(text) => text,
+        Value::Object(mut object) => match object.remove("text").or_else(|| object.remove("value")) {
+            Some(Value::String(text)) => text,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+// 节点树是否为旧的节点表 {节点ID: {data, children: [节点ID]}}，而不是脑图组件的嵌套树
+pub(crate) fn is_node_table(tree: &Map<String, Value>) -> bool {
+    let nested = tree.get("data")
+        .and_then(Value::as_object)
+        .is_some_and(|data| !data.get("data").is_some_and(Value::is_object));
+    !nested && !tree.is_empty()
+}
+
+// 按顺序修改笔记中每个节点的数据，兼容节点表和嵌套树
+fn for_each_node_data(note: &mut Map<String, Value>, edit: &mut impl FnMut(&mut Map<String, Value>)) {
+    match note.get_mut("data") {
+        Some(Value::Object(tree)) if is_node_table(tree) => {
+            for node in tree.values_mut() {
+                if let Some(data) = node.get_mut("data").and_then(Value::as_object_mut) {
+                    edit(data);
+                }
+            }
+        }
+        Some(root) => nodes::for_each_node_data(root, edit),
+        None => {}
+    }
+}
+
+// 版本 0：节点标签可能写在 tags 数组或单个的 tag 中，统一为脑图组件使用的字符串 tag 数组
+fn migrate_v0(note: &mut Map<String, Value>) {
+    for_each_node_data(note, &mut |data| {
+        let mut tags: Vec<Value> = Vec::new();
+        for key in ["tag", "tags"] {
+            match data.remove(key) {
+                Some(Value::Array(values)) => tags.extend(values),
+                Some(tag) => tags.push(tag),
+                None => {}
+            }
+        }
+        let mut unique = Vec::new();
+        for tag in tags.into_iter().filter_map(tag_text).map(Value::String) {
+            if !unique.contains(&tag) {
+                unique.push(tag);
+            }
+        }
+        if !unique.is_empty() {
+            data.insert("tag".to_string(), Value::Array(unique));
+        }
+    });
+}
+
+// 从节点表中取出节点及其后代，组成嵌套的节点；取出的节点从表中删除，环中重复出现的节点只保留一次
+fn nest_node(table: &mut Map<String, Value>, id: &str) -> Option<Value> {
+    let mut node = match table.remove(id)? {
+        Value::Object(node) => node,
+        _ => Map::new(),
+    };
+    let mut data = match node.remove("data") {
+        Some(Value::Object(data)) => data,
+        _ => Map::new(),
+    };
+    data.remove("id");
+    data.insert("uid".to_string(), Value::String(id.to_string()));
+    let children: Vec<Value> = match node.remove("children") {
+        Some(Value::Array(children)) => children.iter()
+            .filter_map(Value::as_str)
+            .filter_map(|child| nest_node(table, child))
+            .collect(),
+        _ => Vec::new(),
+    };
+    node.insert("data".to_string(), Value::Object(data));
+    node.insert("children".to_string(), Value::Array(children));
+    Some(Value::Object(node))
+}
+
+// 版本 1：节点表加 rootId 改为脑图组件 getData() 的嵌套树 {data: {uid, ...}, children: [...]}，
+// 原来的节点ID作为 uid；无法从根节点到达的节点挂到根节点下，不丢弃任何节点。
+// 已是嵌套树的笔记只为缺少ID的节点补上 uid
+fn migrate_v1(note: &mut Map<String, Value>) {
+    let root_id = match note.remove("rootId") {
+        Some(Value::String(id)) => id,
+        _ => "root".to_string(),
+    };
+    let Some(Value::Object(tree)) = note.get_mut("data") else {
+        return;
+    };
+    if !is_node_table(tree) {
+        if let Some(root) = note.get_mut("data") {
+            nodes::for_each_node_data(root, &mut |data| {
+                if !data.get("uid").or_else(|| data.get("id")).is_some_and(Value::is_string) {
+                    data.insert("uid".to_string(), Value::String(uuid::Uuid::new_v4().to_string()));
+                }
+            });
+        }
+        return;
+    }
+
+    let mut table = std::mem::take(tree);
+    let mut root = nest_node(&mut table, &root_id).unwrap_or_else(|| {
+        let title = note.get("title").and_then(Value::as_str).unwrap_or_default();
+        json!({ "data": { "uid": root_id, "text": title }, "children": [] })
+    });
+    while let Some(id) = table.keys().next().cloned() {
+        if let (Some(node), Some(children)) = (nest_node(&mut table, &id), root["children"].as_array_mut()) {
+            children.push(node);
+        }
+    }
+    note.insert("data".to_string(), root);
+}
+
+// 第 n 项把版本 n 的笔记迁移到版本 n + 1
+// 前端保存时可能丢掉版本号，所以每个迁移对已是新格式的内容也必须无副作用
+const MIGRATIONS: [fn(&mut Map<String, Value>); NOTE_FORMAT_VERSION as usize] = [migrate_v0, migrate_v1];
+
+// 将笔记迁移到当前版本，返回迁移前的版本；新版本应用写入的笔记不做修改并返回错误
+pub fn migrate_note(note: &mut Value) -> Result<u64, String> {
+    let version = format_version(note);
+    if version > NOTE_FORMAT_VERSION {
+        return Err(format!("笔记格式版本 {} 高于当前支持的版本 {}，请升级应用", version, NOTE_FORMAT_VERSION));
+    }
+    let Value::Object(object) = note else {
+        return Err("笔记内容不是有效的JSON对象".to_string());
+    };
+    for migration in MIGRATIONS.iter().skip(version as usize) {
+        migration(object);
+    }
+    object.insert(FORMAT_VERSION_KEY.to_string(), Value::from(NOTE_FORMAT_VERSION));
+    Ok(version)
+}
+
+// 将笔记内容升级到当前版本，已是当前版本时返回 None，不改变原有的格式
+pub fn upgrade_content(content: &str) -> Result<Option<String>, String> {
+    let mut note: Value = serde_json::from_str(content)
+        .map_err(|e| format!("笔记内容不是有效的JSON: {}", e))?;
+    if format_version(&note) == NOTE_FORMAT_VERSION {
+        return Ok(None);
+    }
+    migrate_note(&mut note)?;
+    serde_json::to_string_pretty(&note)
+        .map(Some)
+        .map_err(|e| format!("无法序列化笔记内容: {}", e))
+}
+
+// 读取时在内存中升级笔记，无法升级时原样返回交给前端处理
+pub fn upgrade_for_read(content: String) -> String {
+    match upgrade_content(&content) {
+        Ok(Some(upgraded)) => upgraded,
+        Ok(None) => content,
+        Err(e) => {
+            backend_log!("[后端] 笔记格式未升级: {}", e);
+            content
+        }
+    }
+}
+
+// 批量迁移中被迁移的笔记
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigratedNote {
+    pub path: String,
+    pub from_version: u64,
+}
+
+// 批量迁移中跳过的笔记
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedNote {
+    pub path: String,
+    pub reason: String,
+}
+
+// 批量迁移的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReport {
+    pub dry_run: bool,
+    pub target_version: u64,
+    pub migrated: Vec<MigratedNote>,
+    // 已是当前版本的笔记数量
+    pub up_to_date: usize,
+    pub skipped: Vec<SkippedNote>,
+}
+
+impl Workspace {
+    // 将工作区中所有笔记写回为当前格式版本；dry_run 时只报告需要迁移的笔记
+    pub fn migrate_workspace(&self, dry_run: bool) -> Result<MigrationReport, String> {
+        let mut report = MigrationReport {
+            dry_run,
+            target_version: NOTE_FORMAT_VERSION,
+            migrated: Vec::new(),
+            up_to_date: 0,
+            skipped: Vec::new(),
+        };
+        if !dry_run {
+            // 迁移单独成为一次历史提交
+            self.flush_history()?;
+        }
+
+        for note_path in self.note_files()? {
+            let path = self.relative_path(&note_path);
+            let bytes = match self.read_note_bytes(&note_path) {
+                Ok(bytes) if note_lock::is_locked(&bytes) => {
+                    report.skipped.push(SkippedNote { path, reason: "笔记已加锁".to_string() });
+                    continue;
+                }
+                Ok(bytes) => bytes,
+                Err(reason) => {
+                    report.skipped.push(SkippedNote { path, reason });
+                    continue;
+                }
+            };
+            let mut note: Value = match serde_json::from_slice(&bytes) {
+                Ok(note) => note,
+                Err(e) => {
+                    report.skipped.push(SkippedNote { path, reason: format!("笔记内容不是有效的JSON: {}", e) });
+                    continue;
+                }
+            };
+            if format_version(&note) == NOTE_FORMAT_VERSION {
+                report.up_to_date += 1;
+                continue;
+            }
+            let from_version = match migrate_note(&mut note) {
+                Ok(version) => version,
+                Err(reason) => {
+                    report.skipped.push(SkippedNote { path, reason });
+                    continue;
+                }
+            };
+            if !dry_run {
+                let content = serde_json::to_string_pretty(&note)
+                    .map_err(|e| format!("无法序列化笔记内容: {}", e))?;
+                self.write_note_bytes(&note_path, content.as_bytes())?;
+            }
+            report.migrated.push(MigratedNote { path, from_version });
+        }
+
+        if !dry_run && !report.migrated.is_empty() {
+            backend_log!("[后端] 已迁移 {} 篇笔记到格式版本 {}", report.migrated.len(), NOTE_FORMAT_VERSION);
+            self.record_change(format!("迁移 {} 篇笔记到格式版本 {}", report.migrated.len(), NOTE_FORMAT_VERSION));
+            self.flush_history()?;
+        }
+        Ok(report)
+    }
+}
+
+// 批量迁移工作区中的笔记格式
+#[tauri::command]
+pub async fn migrate_workspace(state: State<'_, WorkspaceState>, dry_run: Option<bool>) -> Result<MigrationReport, String> {
+    state.run(move |workspace| workspace.migrate_workspace(dry_run.unwrap_or(true))).await
+}
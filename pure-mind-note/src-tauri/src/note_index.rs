@@ -0,0 +1,163 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::{mpsc, Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime},
+};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::metadata;
+use crate::workspace::Workspace;
+
+// 笔记在应用之外被修改时的通知，参数为笔记相对于工作区根目录的路径
+pub type ChangeNotifier = Arc<dyn Fn(String) + Send + Sync>;
+
+// 文件事件停止这么久之后才处理，一次保存（临时文件、改名）产生的多个事件合并处理
+const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+// 文件的修改时间和大小，用来判断索引中的内容是否仍然有效
+type FileStamp = (Option<SystemTime>, u64);
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok(), metadata.len()))
+}
+
+#[derive(Debug)]
+struct IndexEntry {
+    stamp: FileStamp,
+    // 加密的工作区不缓存明文，避免锁定后明文仍留在内存中
+    content: Option<String>,
+}
+
+// 工作区的笔记内容索引，供搜索和链接使用，每个打开的工作区各有一份；
+// 条目以读取或写入时文件的修改时间和大小校验，文件监视器发现外部修改时丢弃对应条目
+#[derive(Debug, Default)]
+pub struct NoteIndex {
+    entries: Mutex<HashMap<PathBuf, IndexEntry>>,
+}
+
+impl NoteIndex {
+    fn lock_entries(&self) -> MutexGuard<'_, HashMap<PathBuf, IndexEntry>> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // 索引中的笔记内容，文件在读取之后被修改过时返回 None
+    pub fn get(&self, path: &Path) -> Option<String> {
+        let stamp = file_stamp(path)?;
+        let entries = self.lock_entries();
+        let entry = entries.get(path).filter(|entry| entry.stamp == stamp)?;
+        entry.content.clone()
+    }
+
+    // 记录读取或写入的笔记内容，stamp 须在读取之前取得，读取期间文件被修改时下次会重新读取
+    pub fn record(&self, path: &Path, stamp: Option<FileStamp>, content: Option<String>) {
+        let mut entries = self.lock_entries();
+        match stamp {
+            Some(stamp) => entries.insert(path.to_path_buf(), IndexEntry { stamp, content }),
+            None => entries.remove(path),
+        };
+    }
+
+    // 文件是否与索引中记录的一致，即最近一次修改来自应用本身
+    fn is_current(&self, path: &Path) -> bool {
+        let stamp = file_stamp(path);
+        self.lock_entries().get(path).is_some_and(|entry| Some(entry.stamp) == stamp)
+    }
+
+    fn remove(&self, path: &Path) {
+        self.lock_entries().remove(path);
+    }
+
+    pub fn clear(&self) {
+        self.lock_entries().clear();
+    }
+}
+
+// 需要通知的笔记文件，返回相对路径；隐藏目录（.git、草稿日志等）、分类元数据和写入中的临时文件不通知
+fn watched_note(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        let Component::Normal(part) = component else { return None };
+        let part = part.to_str()?;
+        if part.starts_with('.') {
+            return None;
+        }
+        parts.push(part);
+    }
+    if path.extension().and_then(|ext| ext.to_str()) != Some("json") || metadata::is_metadata_file(path) {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+// 处理文件事件：事件停止一段时间后，与索引记录不一致的笔记才视为外部修改
+fn process_events(root: PathBuf, index: Arc<NoteIndex>, events: mpsc::Receiver<PathBuf>, notifier: ChangeNotifier) {
+    // 监视停止后发送端被丢弃，recv 返回错误，线程随之退出
+    while let Ok(path) = events.recv() {
+        let mut paths = HashSet::from([path]);
+        while let Ok(path) = events.recv_timeout(SETTLE_DELAY) {
+            paths.insert(path);
+        }
+        let mut changed: Vec<(String, PathBuf)> = paths
+            .into_iter()
+            .filter_map(|path| Some((watched_note(&root, &path)?, path)))
+            .filter(|(_, path)| !index.is_current(path))
+            .collect();
+        changed.sort();
+        for (relative, path) in changed {
+            index.remove(&path);
+            notifier(relative);
+        }
+    }
+}
+
+impl Workspace {
+    // 开始监视工作区目录，笔记在应用之外被修改时丢弃索引中的内容并发出通知；已在监视时替换之前的监视
+    pub fn watch(&self, notifier: ChangeNotifier) -> Result<(), String> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            let Ok(event) = result else { return };
+            if !matches!(event.kind, EventKind::Access(_)) {
+                for path in event.paths {
+                    let _ = sender.send(path);
+                }
+            }
+        })
+        .map_err(|e| format!("无法监视工作区: {}", e))?;
+        watcher.watch(self.root(), RecursiveMode::Recursive).map_err(|e| format!("无法监视工作区: {}", e))?;
+
+        let (root, index) = (self.root().to_path_buf(), self.index().clone());
+        std::thread::spawn(move || process_events(root, index, events, notifier));
+        *self.lock_watcher() = Some(watcher);
+        backend_log!("[后端] 开始监视工作区: {}", self.root().display());
+        Ok(())
+    }
+
+    // 停止监视工作区目录
+    pub fn unwatch(&self) {
+        if self.lock_watcher().take().is_some() {
+            backend_log!("[后端] 停止监视工作区: {}", self.root().display());
+        }
+    }
+
+    // 读取笔记内容，优先使用索引中仍然有效的内容
+    pub(crate) fn read_indexed_note(&self, path: &Path) -> Result<String, String> {
+        if let Some(content) = self.index().get(path) {
+            return Ok(content);
+        }
+        let stamp = file_stamp(path);
+        let content = String::from_utf8(self.read_note_bytes(path)?).map_err(|e| format!("无法读取笔记: {}", e))?;
+        let cached = (!self.vault().is_enabled()).then(|| content.clone());
+        self.index().record(path, stamp, cached);
+        Ok(content)
+    }
+
+    // 记录应用本身写入的笔记，文件监视器据此忽略这次修改
+    pub(crate) fn record_written_note(&self, path: &Path, content: &[u8]) {
+        let cached = if self.vault().is_enabled() { None } else { String::from_utf8(content.to_vec()).ok() };
+        self.index().record(path, file_stamp(path), cached);
+    }
+}
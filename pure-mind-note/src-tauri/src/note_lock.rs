@@ -0,0 +1,135 @@
+use argon2::Params;
+use tauri::State;
+use zeroize::Zeroizing;
+
+use crate::crypto::{self, SALT_LEN};
+use crate::note_format;
+use crate::workspace::{Workspace, WorkspaceState};
+
+// 加锁笔记的文件头；工作区启用加密时，加锁后的内容会再被工作区密钥加密一次
+const LOCK_MAGIC: &[u8] = b"PMNLOCK1\n";
+
+// 判断笔记内容（已去掉工作区加密层）是否被单独加锁
+pub fn is_locked(bytes: &[u8]) -> bool {
+    bytes.starts_with(LOCK_MAGIC)
+}
+
+// 用密码加锁：文件头 + 随机盐 + nonce + 密文
+pub(crate) fn lock_content(password: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    if password.is_empty() {
+        return Err("密码不能为空".to_string());
+    }
+    let salt = crypto::random_salt();
+    let key = crypto::derive_key(password, &salt, Params::default())?;
+
+    let mut locked = LOCK_MAGIC.to_vec();
+    locked.extend_from_slice(&salt);
+    locked.extend(crypto::encrypt_with(&[], &key, plaintext)?);
+    Ok(locked)
+}
+
+// 用密码解开加锁的内容
+pub(crate) fn unlock_content(password: &str, locked: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    let body = locked.strip_prefix(LOCK_MAGIC).ok_or_else(|| "笔记未加锁".to_string())?;
+    if body.len() < SALT_LEN {
+        return Err("加锁的笔记已损坏".to_string());
+    }
+    let (salt, sealed) = body.split_at(SALT_LEN);
+    let key = crypto::derive_key(password, salt, Params::default())?;
+    crypto::decrypt_with(&[], &key, sealed)
+        .map(Zeroizing::new)
+        .map_err(|_| "密码不正确".to_string())
+}
+
+impl Workspace {
+    // 用密码加锁一篇笔记
+    pub fn lock_note(&self, path: &str, password: &str) -> Result<(), String> {
+        let note_path = self.resolve(path)?;
+        let _guard = self.lock_edits();
+        let content = self.read_note_bytes(&note_path)?;
+        if is_locked(&content) {
+            return Err("笔记已经加锁".to_string());
+        }
+        self.write_note_bytes(&note_path, &lock_content(password, &content)?)?;
+        // 草稿是以工作区密钥保存的明文，加锁后不再保留
+        if let Err(e) = self.discard_draft_at(&note_path) {
+            backend_log!("[后端] {}", e);
+        }
+        // 加锁前的明文版本仍保留在历史记录中，需要清除历史才能移除（见 purge_history）
+        self.record_change(format!("加锁笔记《{}》", self.change_label(&note_path, None)));
+        Ok(())
+    }
+
+    // 解除笔记的密码锁，恢复为普通笔记并返回其内容
+    pub fn unlock_note(&self, path: &str, password: &str) -> Result<String, String> {
+        let note_path = self.resolve(path)?;
+        let _guard = self.lock_edits();
+        let content = unlock_content(password, &self.read_note_bytes(&note_path)?)?;
+        self.write_note_bytes(&note_path, &content)?;
+        self.record_change(format!("解锁笔记《{}》", self.change_label(&note_path, None)));
+        String::from_utf8(content.to_vec())
+            .map(note_format::upgrade_for_read)
+            .map_err(|e| format!("无法读取笔记: {}", e))
+    }
+
+    // 不解除锁，临时读取加锁笔记的内容
+    pub fn read_locked_note(&self, path: &str, password: &str) -> Result<String, String> {
+        let note_path = self.resolve(path)?;
+        let content = unlock_content(password, &self.read_note_bytes(&note_path)?)?;
+        String::from_utf8(content.to_vec())
+            .map(note_format::upgrade_for_read)
+            .map_err(|e| format!("无法读取笔记: {}", e))
+    }
+
+    // 保存加锁笔记，保存后仍保持加锁状态
+    pub fn save_locked_note(&self, path: &str, password: &str, content: &str) -> Result<(), String> {
+        let note_path = self.resolve(path)?;
+        let _guard = self.lock_edits();
+        // 先验证密码，避免用错误的密码覆盖
+        unlock_content(password, &self.read_note_bytes(&note_path)?)?;
+        self.write_note_bytes(&note_path, &lock_content(password, content.as_bytes())?)?;
+        self.record_change(format!("更新笔记《{}》", self.change_label(&note_path, None)));
+        Ok(())
+    }
+}
+
+// 加锁笔记；purge_history 为 true 时清除历史记录中的明文版本
+#[tauri::command]
+pub async fn lock_note(
+    state: State<'_, WorkspaceState>,
+    path: String,
+    password: String,
+    purge_history: Option<bool>,
+) -> Result<(), String> {
+    let password = Zeroizing::new(password);
+    state.run(move |workspace| {
+        workspace.lock_note(&path, &password)?;
+        workspace.handle_plaintext_history(purge_history.unwrap_or(false))
+    }).await
+}
+
+// 解除笔记的密码锁
+#[tauri::command]
+pub async fn unlock_note(state: State<'_, WorkspaceState>, path: String, password: String) -> Result<String, String> {
+    let password = Zeroizing::new(password);
+    state.run(move |workspace| workspace.unlock_note(&path, &password)).await
+}
+
+// 读取加锁笔记
+#[tauri::command]
+pub async fn read_locked_note(state: State<'_, WorkspaceState>, path: String, password: String) -> Result<String, String> {
+    let password = Zeroizing::new(password);
+    state.run(move |workspace| workspace.read_locked_note(&path, &password)).await
+}
+
+// 保存加锁笔记
+#[tauri::command]
+pub async fn save_locked_note(
+    state: State<'_, WorkspaceState>,
+    path: String,
+    password: String,
+    content: String,
+) -> Result<(), String> {
+    let password = Zeroizing::new(password);
+    state.run(move |workspace| workspace.save_locked_note(&path, &password, &content)).await
+}
@@ -0,0 +1,168 @@
+use std::{cmp::Ordering, fs, path::Path, time::SystemTime};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::{is_note_file, parse_note_id};
+use crate::metadata;
+use crate::naming;
+use crate::workspace::{Workspace, WorkspaceState};
+
+// 列表的排序方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortMode {
+    // 按标题（忽略大小写）升序
+    Title,
+    // 按修改时间，最新的在前
+    Modified,
+    // 按创建时间，最新的在前
+    #[default]
+    Created,
+    // 按用户拖拽确定的顺序，未排序的条目按创建时间排在后面
+    Custom,
+}
+
+// 排序时用到的各项属性
+pub struct SortKey {
+    pub title: String,
+    pub created: SystemTime,
+    pub modified: SystemTime,
+    pub position: Option<i64>,
+}
+
+impl SortKey {
+    // 从文件系统读取时间属性
+    pub fn from_path(path: &Path, title: &str, position: Option<i64>) -> Self {
+        let metadata = fs::metadata(path).ok();
+        Self {
+            title: naming::fold_name(title),
+            created: metadata.as_ref()
+                .and_then(|meta| meta.created().ok())
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+            modified: metadata.as_ref()
+                .and_then(|meta| meta.modified().ok())
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+            position,
+        }
+    }
+
+    fn compare(&self, other: &Self, mode: SortMode) -> Ordering {
+        match mode {
+            SortMode::Title => self.title.cmp(&other.title),
+            SortMode::Modified => other.modified.cmp(&self.modified),
+            SortMode::Created => other.created.cmp(&self.created),
+            SortMode::Custom => match (self.position, other.position) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => other.created.cmp(&self.created),
+            },
+        }
+    }
+}
+
+// 按指定方式排序，key 为每个条目计算排序属性（并行读取文件属性）
+pub fn sort_by_mode<T: Send>(items: &mut Vec<T>, mode: SortMode, key: impl Fn(&T) -> SortKey + Sync) {
+    let mut keyed: Vec<(SortKey, T)> = items.par_drain(..).map(|item| (key(&item), item)).collect();
+    keyed.par_sort_by(|(a, _), (b, _)| a.compare(b, mode));
+    items.extend(keyed.into_iter().map(|(_, item)| item));
+}
+
+impl Workspace {
+    // 保存分类的自定义顺序
+    pub fn reorder_categories(&self, ordered_ids: &[String]) -> Result<(), String> {
+        for (index, category_id) in ordered_ids.iter().enumerate() {
+            let dir_path = self.resolve_parts(&[category_id])?;
+            set_position(&dir_path, index)?;
+        }
+        self.record_change("调整分类顺序".to_string());
+        Ok(())
+    }
+
+    // 保存某个分类下子分类的自定义顺序
+    pub fn reorder_subcategories(&self, category_id: &str, ordered_ids: &[String]) -> Result<(), String> {
+        for (index, sub_id) in ordered_ids.iter().enumerate() {
+            let dir_path = self.resolve_parts(&[category_id, sub_id])?;
+            set_position(&dir_path, index)?;
+        }
+        self.record_change(format!("调整分类《{}》中子分类的顺序", category_id));
+        Ok(())
+    }
+
+    // 保存某个目录下笔记的自定义顺序，顺序记录在该目录的元数据中
+    pub fn reorder_notes(
+        &self,
+        category_id: &str,
+        sub_category_id: Option<&str>,
+        ordered_note_ids: &[String],
+    ) -> Result<(), String> {
+        let dir_path = match sub_category_id {
+            Some(sub_id) => self.resolve_parts(&[category_id, sub_id])?,
+            None => self.resolve_parts(&[category_id])?,
+        };
+        if !dir_path.is_dir() {
+            return Err(format!("分类 '{}' 不存在", sub_category_id.unwrap_or(category_id)));
+        }
+
+        // 笔记ID的最后一段是文件名（不含扩展名），按与其他路径相同的规则解析，且必须位于该目录中
+        let mut note_order = Vec::new();
+        for note_id in ordered_note_ids {
+            let (note_category, note_sub_category, file_stem) = parse_note_id(note_id)?;
+            if note_category != category_id || note_sub_category != sub_category_id {
+                return Err(format!("笔记 '{}' 不在该分类中", note_id));
+            }
+            let file_name = format!("{}.json", file_stem);
+            let mut parts = vec![category_id];
+            parts.extend(sub_category_id);
+            parts.push(&file_name);
+            if !is_note_file(&self.resolve_parts(&parts)?) {
+                return Err(format!("笔记 '{}' 不在该分类中", note_id));
+            }
+            note_order.push(file_stem.to_string());
+        }
+
+        let mut meta = metadata::read_metadata(&dir_path);
+        meta.note_order = note_order;
+        metadata::write_metadata(&dir_path, &meta)?;
+        self.record_change(format!("调整分类《{}》中笔记的顺序", sub_category_id.unwrap_or(category_id)));
+        Ok(())
+    }
+}
+
+// 更新目录元数据中的排序位置
+fn set_position(dir_path: &Path, index: usize) -> Result<(), String> {
+    if !dir_path.is_dir() {
+        return Err(format!("分类目录不存在: {}", dir_path.display()));
+    }
+    let mut meta = metadata::read_metadata(dir_path);
+    meta.position = Some(index as i64);
+    metadata::write_metadata(dir_path, &meta)
+}
+
+// 拖拽排序分类
+#[tauri::command]
+pub async fn reorder_categories(state: State<'_, WorkspaceState>, ordered_ids: Vec<String>) -> Result<(), String> {
+    state.run(move |workspace| workspace.reorder_categories(&ordered_ids)).await
+}
+
+// 拖拽排序子分类
+#[tauri::command]
+pub async fn reorder_subcategories(
+    state: State<'_, WorkspaceState>,
+    category_id: String,
+    ordered_ids: Vec<String>,
+) -> Result<(), String> {
+    state.run(move |workspace| workspace.reorder_subcategories(&category_id, &ordered_ids)).await
+}
+
+// 拖拽排序笔记
+#[tauri::command]
+pub async fn reorder_notes(
+    state: State<'_, WorkspaceState>,
+    category_id: String,
+    sub_category_id: Option<String>,
+    ordered_note_ids: Vec<String>,
+) -> Result<(), String> {
+    state.run(move |workspace| workspace.reorder_notes(&category_id, sub_category_id.as_deref(), &ordered_note_ids)).await
+}
@@ -0,0 +1,101 @@
+use serde_json::{json, Value};
+use tauri::State;
+
+use crate::nodes::{node_tags, plain_text, walk_nodes};
+use crate::workspace::{Workspace, WorkspaceState};
+
+// 把脑图转换为 Markdown 大纲：根节点为标题，其余节点为嵌套列表，标签写成 #标签
+pub fn note_to_markdown(note: &Value) -> String {
+    let mut markdown = String::new();
+    for (_, data, depth) in walk_nodes(note) {
+        // 多行文字合并为一行，避免破坏列表结构
+        let mut line = plain_text(data).lines().map(str::trim).collect::<Vec<_>>().join(" ");
+        for tag in node_tags(data) {
+            line.push_str(&format!(" #{}", tag.replace(' ', "_")));
+        }
+        if depth == 0 {
+            markdown.push_str(&format!("# {}\n\n", line));
+        } else {
+            markdown.push_str(&format!("{}- {}\n", "  ".repeat(depth - 1), line));
+        }
+    }
+    markdown
+}
+
+// 解析 Markdown 中的一行：标题返回 (级别, 文字)，列表项和普通段落返回 (10 + 缩进, 文字)
+fn outline_line(line: &str) -> Option<(usize, &str)> {
+    let indent = line.chars().take_while(|c| c.is_whitespace()).map(|c| if c == '\t' { 2 } else { 1 }).sum::<usize>();
+    let text = line.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let level = text.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&level) && text[level..].starts_with(' ') {
+        return Some((level, text[level..].trim()));
+    }
+    let item = ["- ", "* ", "+ "].iter().find_map(|marker| text.strip_prefix(marker)).or_else(|| {
+        let digits = text.chars().take_while(char::is_ascii_digit).count();
+        (digits > 0).then(|| text[digits..].strip_prefix(". ")).flatten()
+    });
+    Some((10 + indent, item.unwrap_or(text).trim()))
+}
+
+// 组成以 id 为根的嵌套节点，items 中是 (节点ID, 节点数据, 父节点ID)
+fn nest_outline(id: &str, data: Value, items: &[(String, Value, String)]) -> Value {
+    let children: Vec<Value> = items.iter()
+        .filter(|(_, _, parent)| parent == id)
+        .map(|(child, data, _)| nest_outline(child, data.clone(), items))
+        .collect();
+    json!({ "data": data, "children": children })
+}
+
+// 把 Markdown 大纲转换为脑图，是 note_to_markdown 的逆过程：
+// 第一个一级标题为根节点，其余标题和列表项按级别和缩进嵌套，行尾的 #标签 写入节点标签
+pub fn markdown_to_note(markdown: &str) -> Value {
+    let mut items: Vec<(String, Value, String)> = Vec::new();
+    let mut root_text = None;
+    // 当前路径上的 (级别, 节点ID)
+    let mut stack: Vec<(usize, String)> = Vec::new();
+
+    for line in markdown.lines() {
+        let Some((rank, text)) = outline_line(line) else { continue };
+        if rank == 1 && root_text.is_none() && items.is_empty() {
+            root_text = Some(text.to_string());
+            continue;
+        }
+
+        let mut words: Vec<&str> = text.split(' ').collect();
+        let mut tags = Vec::new();
+        while words.len() > 1 && words.last().is_some_and(|word| word.len() > 1 && word.starts_with('#')) {
+            tags.insert(0, words.pop().unwrap_or_default()[1..].to_string());
+        }
+        let id = format!("n{}", items.len() + 1);
+        let mut data = json!({ "uid": id, "text": words.join(" ") });
+        if !tags.is_empty() {
+            data["tag"] = json!(tags);
+        }
+
+        while stack.last().is_some_and(|(top, _)| *top >= rank) {
+            stack.pop();
+        }
+        let parent = stack.last().map(|(_, id)| id.clone()).unwrap_or_else(|| "root".to_string());
+        items.push((id.clone(), data, parent));
+        stack.push((rank, id));
+    }
+
+    let root = json!({ "uid": "root", "text": root_text.unwrap_or_default() });
+    json!({ "data": nest_outline("root", root, &items) })
+}
+
+impl Workspace {
+    // 导出笔记为 Markdown 大纲
+    pub fn export_markdown(&self, path: &str) -> Result<String, String> {
+        Ok(note_to_markdown(&self.read_note_value(path)?))
+    }
+}
+
+// 导出笔记为 Markdown 大纲
+#[tauri::command]
+pub async fn export_markdown(state: State<'_, WorkspaceState>, path: String) -> Result<String, String> {
+    state.run(move |workspace| workspace.export_markdown(&path)).await
+}
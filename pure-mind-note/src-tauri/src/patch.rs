@@ -0,0 +1,248 @@
+use std::collections::HashSet;
+use git2::{ObjectType, Oid};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::nodes::node_id;
+use crate::note_format;
+use crate::note_lock;
+use crate::workspace::{Workspace, WorkspaceState};
+
+// 基准版本与当前内容不一致时错误信息的前缀，前端据此重新读取笔记
+pub const REVISION_CONFLICT: &str = "版本冲突";
+
+// 笔记内容及其版本号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteRevision {
+    pub content: String,
+    pub revision: String,
+}
+
+// 笔记内容的版本号：内容的哈希，内容不变时版本号不变
+pub fn revision_of(content: &str) -> String {
+    Oid::hash_object(ObjectType::Blob, content.as_bytes())
+        .map(|oid| oid.to_string())
+        .unwrap_or_default()
+}
+
+// 解析 JSON Pointer（RFC 6901），返回各级的键
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(format!("无效的路径: {}", pointer));
+    };
+    Ok(rest.split('/').map(|token| token.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+fn array_index(array: &[Value], token: &str, allow_end: bool) -> Result<usize, String> {
+    if allow_end && token == "-" {
+        return Ok(array.len());
+    }
+    // 不允许前导零和符号
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("无效的数组下标: {}", token));
+    }
+    let index: usize = token.parse().map_err(|_| format!("无效的数组下标: {}", token))?;
+    let in_range = if allow_end { index <= array.len() } else { index < array.len() };
+    if !in_range {
+        return Err(format!("数组下标超出范围: {}", token));
+    }
+    Ok(index)
+}
+
+fn get_pointer<'a>(document: &'a Value, tokens: &[String]) -> Result<&'a Value, String> {
+    let mut current = document;
+    for token in tokens {
+        current = match current {
+            Value::Object(map) => map.get(token),
+            Value::Array(array) => array.get(array_index(array, token, false)?),
+            _ => None,
+        }
+        .ok_or_else(|| format!("路径不存在: /{}", tokens.join("/")))?;
+    }
+    Ok(current)
+}
+
+// 找到路径的父容器
+fn parent_mut<'a>(document: &'a mut Value, tokens: &[String]) -> Result<(&'a mut Value, String), String> {
+    let (last, parents) = tokens.split_last().ok_or_else(|| "不能修改文档根".to_string())?;
+    let mut current = document;
+    for token in parents {
+        current = match current {
+            Value::Object(map) => map.get_mut(token),
+            Value::Array(array) => {
+                let index = array_index(array, token, false)?;
+                array.get_mut(index)
+            }
+            _ => None,
+        }
+        .ok_or_else(|| format!("路径不存在: /{}", tokens.join("/")))?;
+    }
+    Ok((current, last.clone()))
+}
+
+fn add_value(document: &mut Value, tokens: &[String], value: Value) -> Result<(), String> {
+    if tokens.is_empty() {
+        *document = value;
+        return Ok(());
+    }
+    let (parent, key) = parent_mut(document, tokens)?;
+    match parent {
+        Value::Object(map) => {
+            map.insert(key, value);
+        }
+        Value::Array(array) => {
+            let index = array_index(array, &key, true)?;
+            array.insert(index, value);
+        }
+        _ => return Err(format!("路径的父级不是对象或数组: /{}", tokens.join("/"))),
+    }
+    Ok(())
+}
+
+fn remove_value(document: &mut Value, tokens: &[String]) -> Result<Value, String> {
+    let (parent, key) = parent_mut(document, tokens)?;
+    match parent {
+        Value::Object(map) => map.remove(&key),
+        Value::Array(array) => {
+            let index = array_index(array, &key, false)?;
+            Some(array.remove(index))
+        }
+        _ => None,
+    }
+    .ok_or_else(|| format!("路径不存在: /{}", tokens.join("/")))
+}
+
+fn op_field<'a>(operation: &'a Value, field: &str) -> Result<&'a str, String> {
+    operation.get(field)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("补丁操作缺少 {} 字段", field))
+}
+
+// 按 RFC 6902 应用 JSON Patch，任一操作失败时文档保持不变
+pub fn apply_json_patch(document: &mut Value, patch: &Value) -> Result<(), String> {
+    let operations = patch.as_array().ok_or_else(|| "补丁必须是操作数组".to_string())?;
+    let mut result = document.clone();
+    for (index, operation) in operations.iter().enumerate() {
+        apply_operation(&mut result, operation).map_err(|e| format!("第 {} 个补丁操作失败: {}", index + 1, e))?;
+    }
+    *document = result;
+    Ok(())
+}
+
+fn apply_operation(document: &mut Value, operation: &Value) -> Result<(), String> {
+    let op = op_field(operation, "op")?;
+    let path = parse_pointer(op_field(operation, "path")?)?;
+    let value = || operation.get("value").cloned().ok_or_else(|| "补丁操作缺少 value 字段".to_string());
+    match op {
+        "add" => add_value(document, &path, value()?),
+        "remove" => remove_value(document, &path).map(|_| ()),
+        "replace" => {
+            let value = value()?;
+            if path.is_empty() {
+                *document = value;
+                return Ok(());
+            }
+            remove_value(document, &path)?;
+            add_value(document, &path, value)
+        }
+        "move" => {
+            let from = parse_pointer(op_field(operation, "from")?)?;
+            if path.len() > from.len() && path[..from.len()] == from[..] {
+                return Err("不能把节点移动到它自己的子路径下".to_string());
+            }
+            let moved = remove_value(document, &from)?;
+            add_value(document, &path, moved)
+        }
+        "copy" => {
+            let from = parse_pointer(op_field(operation, "from")?)?;
+            let copied = get_pointer(document, &from)?.clone();
+            add_value(document, &path, copied)
+        }
+        "test" => {
+            if *get_pointer(document, &path)? == value()? {
+                Ok(())
+            } else {
+                Err(format!("测试失败: /{}", path.join("/")))
+            }
+        }
+        _ => Err(format!("未知的补丁操作: {}", op)),
+    }
+}
+
+// 检查笔记的节点树：根节点和所有子节点都有 data，children 是节点数组，每个节点都有不重复的ID
+pub fn validate_note_tree(note: &Value) -> Result<(), String> {
+    let root = note.get("data").ok_or_else(|| "笔记缺少节点数据".to_string())?;
+    let mut ids = HashSet::new();
+    let mut pending = vec![root];
+    while let Some(node) = pending.pop() {
+        if !node.get("data").is_some_and(Value::is_object) {
+            return Err("节点缺少 data".to_string());
+        }
+        let id = node_id(node).filter(|id| !id.is_empty()).ok_or_else(|| "节点缺少ID".to_string())?;
+        if !ids.insert(id) {
+            return Err(format!("节点ID {} 重复", id));
+        }
+        match node.get("children") {
+            None => {}
+            Some(Value::Array(children)) => pending.extend(children),
+            Some(_) => return Err(format!("节点 {} 的 children 不是数组", id)),
+        }
+    }
+    Ok(())
+}
+
+impl Workspace {
+    // 读取笔记内容及其版本号
+    pub fn read_note_with_revision(&self, path: &str) -> Result<NoteRevision, String> {
+        let content = self.read_note(path)?;
+        Ok(NoteRevision { revision: revision_of(&content), content })
+    }
+
+    // 在 base_revision 的基础上应用补丁并保存，返回新的版本号
+    pub fn apply_note_patch(&self, path: &str, base_revision: &str, patch: &Value) -> Result<String, String> {
+        let _guard = self.lock_edits();
+        let note_path = self.resolve(path)?;
+        let bytes = self.read_note_bytes(&note_path)?;
+        if note_lock::is_locked(&bytes) {
+            return Err("笔记已加锁，请输入密码".to_string());
+        }
+        let content = String::from_utf8(bytes).map_err(|e| format!("无法读取笔记: {}", e))?;
+        let content = note_format::upgrade_for_read(content);
+
+        let current = revision_of(&content);
+        if base_revision != current {
+            return Err(format!("{}: 笔记已被修改，当前版本为 {}", REVISION_CONFLICT, current));
+        }
+
+        let mut note: Value = serde_json::from_str(&content).map_err(|e| format!("笔记内容不是有效的JSON: {}", e))?;
+        apply_json_patch(&mut note, patch)?;
+        validate_note_tree(&note)?;
+        note_format::migrate_note(&mut note)?;
+
+        let updated = serde_json::to_string_pretty(&note).map_err(|e| format!("无法序列化笔记内容: {}", e))?;
+        self.save_note_locked(path, &updated)?;
+        Ok(revision_of(&updated))
+    }
+}
+
+// 读取笔记及其版本号
+#[tauri::command]
+pub async fn read_note_with_revision(state: State<'_, WorkspaceState>, path: String) -> Result<NoteRevision, String> {
+    state.run(move |workspace| workspace.read_note_with_revision(&path)).await
+}
+
+// 应用 JSON Patch 增量更新笔记
+#[tauri::command]
+pub async fn apply_note_patch(
+    state: State<'_, WorkspaceState>,
+    path: String,
+    base_revision: String,
+    patch: Value,
+) -> Result<String, String> {
+    state.run(move |workspace| workspace.apply_note_patch(&path, &base_revision, &patch)).await
+}
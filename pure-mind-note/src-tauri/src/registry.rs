@@ -0,0 +1,277 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager, State};
+
+use crate::naming;
+use crate::workspace::{write_atomic, Workspace, WorkspaceState};
+
+// 应用配置目录中保存工作区列表的文件
+pub const REGISTRY_FILE: &str = "workspaces.json";
+
+// 已登记的一个工作区
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceEntry {
+    pub id: String,
+    // 显示名称
+    pub name: String,
+    // 规范化后的根目录
+    pub path: String,
+    // 上次打开时间（RFC 3339）
+    #[serde(default)]
+    pub last_opened: Option<String>,
+    // 工作区独立的界面设置，由前端决定内容
+    #[serde(default)]
+    pub settings: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct RegistryData {
+    // 上次打开的工作区，启动时自动打开
+    active: Option<String>,
+    workspaces: Vec<WorkspaceEntry>,
+}
+
+// 工作区列表，保存在应用配置目录中
+#[derive(Debug)]
+pub struct WorkspaceRegistry {
+    file: PathBuf,
+    data: Mutex<RegistryData>,
+}
+
+// 规范化工作区目录，目录不存在时创建
+fn canonical_root(path: &str) -> Result<PathBuf, String> {
+    if path.trim().is_empty() {
+        return Err("工作区路径不能为空".to_string());
+    }
+    fs::create_dir_all(path).map_err(|e| format!("无法创建工作区目录: {}", e))?;
+    fs::canonicalize(path).map_err(|e| format!("无法解析工作区路径: {}", e))
+}
+
+// 未指定名称时使用目录名
+fn default_name(root: &Path) -> String {
+    root.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.to_string_lossy().to_string())
+}
+
+impl WorkspaceRegistry {
+    // 读取工作区列表，文件不存在或损坏时从空列表开始
+    pub fn load(file: impl Into<PathBuf>) -> Self {
+        let file = file.into();
+        let data = fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { file, data: Mutex::new(data) }
+    }
+
+    fn lock_data(&self) -> MutexGuard<'_, RegistryData> {
+        self.data.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn save(&self, data: &RegistryData) -> Result<(), String> {
+        if let Some(parent) = self.file.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("无法创建配置目录: {}", e))?;
+        }
+        let content = serde_json::to_string_pretty(data)
+            .map_err(|e| format!("无法序列化工作区列表: {}", e))?;
+        write_atomic(&self.file, content.as_bytes())
+    }
+
+    // 全部工作区，最近打开的排在前面
+    pub fn list(&self) -> Vec<WorkspaceEntry> {
+        let mut workspaces = self.lock_data().workspaces.clone();
+        workspaces.sort_by(|a, b| b.last_opened.cmp(&a.last_opened).then_with(|| a.name.cmp(&b.name)));
+        workspaces
+    }
+
+    pub fn get(&self, id: &str) -> Result<WorkspaceEntry, String> {
+        self.lock_data()
+            .workspaces
+            .iter()
+            .find(|entry| entry.id == id)
+            .cloned()
+            .ok_or_else(|| format!("找不到工作区: {}", id))
+    }
+
+    // 当前（上次）打开的工作区
+    pub fn active(&self) -> Option<WorkspaceEntry> {
+        let data = self.lock_data();
+        let active = data.active.as_ref()?;
+        data.workspaces.iter().find(|entry| &entry.id == active).cloned()
+    }
+
+    // 登记工作区，同一目录只能登记一次
+    pub fn add(&self, name: Option<&str>, path: &str) -> Result<WorkspaceEntry, String> {
+        let root = canonical_root(path)?;
+        let name = match name {
+            Some(name) => naming::validate_display_name(name)?,
+            None => default_name(&root),
+        };
+        let path = root.to_string_lossy().to_string();
+
+        let mut data = self.lock_data();
+        if let Some(existing) = data.workspaces.iter().find(|entry| entry.path == path) {
+            return Err(format!("该目录已登记为工作区「{}」", existing.name));
+        }
+
+        // 以创建时间生成ID，同一毫秒内重复时追加序号
+        let base = format!("ws-{}", chrono::Local::now().format("%Y%m%d%H%M%S%3f"));
+        let mut id = base.clone();
+        let mut counter = 2;
+        while data.workspaces.iter().any(|entry| entry.id == id) {
+            id = format!("{}-{}", base, counter);
+            counter += 1;
+        }
+
+        let entry = WorkspaceEntry { id, name, path, last_opened: None, settings: Map::new() };
+        data.workspaces.push(entry.clone());
+        self.save(&data)?;
+        backend_log!("[后端] 登记工作区: {} ({})", entry.name, entry.path);
+        Ok(entry)
+    }
+
+    // 从列表中移除工作区，不会删除目录中的文件
+    pub fn remove(&self, id: &str) -> Result<(), String> {
+        let mut data = self.lock_data();
+        let before = data.workspaces.len();
+        data.workspaces.retain(|entry| entry.id != id);
+        if data.workspaces.len() == before {
+            return Err(format!("找不到工作区: {}", id));
+        }
+        if data.active.as_deref() == Some(id) {
+            data.active = None;
+        }
+        self.save(&data)
+    }
+
+    // 修改显示名称
+    pub fn rename(&self, id: &str, name: &str) -> Result<WorkspaceEntry, String> {
+        let name = naming::validate_display_name(name)?;
+        self.modify(id, |entry| entry.name = name)
+    }
+
+    // 合并工作区设置，值为 null 的键会被删除
+    pub fn update_settings(&self, id: &str, settings: Map<String, Value>) -> Result<WorkspaceEntry, String> {
+        self.modify(id, |entry| {
+            for (key, value) in settings {
+                if value.is_null() {
+                    entry.settings.remove(&key);
+                } else {
+                    entry.settings.insert(key, value);
+                }
+            }
+        })
+    }
+
+    // 记录工作区被打开，并设为启动时自动打开的工作区
+    pub fn mark_opened(&self, id: &str) -> Result<WorkspaceEntry, String> {
+        let entry = self.modify(id, |entry| entry.last_opened = Some(chrono::Local::now().to_rfc3339()))?;
+        let mut data = self.lock_data();
+        data.active = Some(entry.id.clone());
+        self.save(&data)?;
+        Ok(entry)
+    }
+
+    // 按目录查找工作区，未登记时以目录名登记
+    pub fn find_or_add(&self, path: &str) -> Result<WorkspaceEntry, String> {
+        let root = canonical_root(path)?.to_string_lossy().to_string();
+        let existing = self.lock_data().workspaces.iter().find(|entry| entry.path == root).cloned();
+        match existing {
+            Some(entry) => Ok(entry),
+            None => self.add(None, &root),
+        }
+    }
+
+    fn modify(&self, id: &str, change: impl FnOnce(&mut WorkspaceEntry)) -> Result<WorkspaceEntry, String> {
+        let mut data = self.lock_data();
+        let entry = data.workspaces
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| format!("找不到工作区: {}", id))?;
+        change(entry);
+        let entry = entry.clone();
+        self.save(&data)?;
+        Ok(entry)
+    }
+
+    // 打开登记的工作区并替换当前工作区，旧工作区会先提交历史并锁定
+    pub fn switch(&self, state: &WorkspaceState, id: &str) -> Result<WorkspaceEntry, String> {
+        let entry = self.get(id)?;
+        if !Path::new(&entry.path).is_dir() {
+            return Err(format!("工作区目录不存在: {}", entry.path));
+        }
+        let workspace = Workspace::open(&entry.path)?;
+        state.set(workspace)?;
+        backend_log!("[后端] 切换到工作区: {}", entry.name);
+        self.mark_opened(id)
+    }
+}
+
+// 列出已登记的工作区
+#[tauri::command]
+pub fn list_workspaces(registry: State<'_, WorkspaceRegistry>) -> Vec<WorkspaceEntry> {
+    registry.list()
+}
+
+// 获取当前工作区的登记信息
+#[tauri::command]
+pub fn get_active_workspace(registry: State<'_, WorkspaceRegistry>) -> Option<WorkspaceEntry> {
+    registry.active()
+}
+
+// 登记新的工作区
+#[tauri::command]
+pub async fn add_workspace(app: AppHandle, name: Option<String>, path: String) -> Result<WorkspaceEntry, String> {
+    run_blocking(app, move |app| app.state::<WorkspaceRegistry>().add(name.as_deref(), &path)).await
+}
+
+// 从列表中移除工作区（不删除文件）
+#[tauri::command]
+pub async fn remove_workspace(registry: State<'_, WorkspaceRegistry>, id: String) -> Result<(), String> {
+    registry.remove(&id)
+}
+
+// 修改工作区的显示名称
+#[tauri::command]
+pub async fn rename_workspace(
+    registry: State<'_, WorkspaceRegistry>,
+    id: String,
+    name: String,
+) -> Result<WorkspaceEntry, String> {
+    registry.rename(&id, &name)
+}
+
+// 更新工作区独立的设置
+#[tauri::command]
+pub async fn update_workspace_settings(
+    registry: State<'_, WorkspaceRegistry>,
+    id: String,
+    settings: Map<String, Value>,
+) -> Result<WorkspaceEntry, String> {
+    registry.update_settings(&id, settings)
+}
+
+// 切换到另一个已登记的工作区
+#[tauri::command]
+pub async fn switch_workspace(app: AppHandle, id: String) -> Result<WorkspaceEntry, String> {
+    run_blocking(app, move |app| app.state::<WorkspaceRegistry>().switch(&app.state::<WorkspaceState>(), &id)).await
+}
+
+// 创建目录、扫描和提交历史都是阻塞操作，放到后台线程池中执行
+pub(crate) async fn run_blocking<T, F>(app: AppHandle, task: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&AppHandle) -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || task(&app))
+        .await
+        .map_err(|e| format!("后台任务执行失败: {}", e))?
+}
@@ -0,0 +1,260 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime},
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::ordering::SortMode;
+use crate::workspace::Workspace;
+
+// 检查笔记文件变化的间隔
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+// 笔记文件变化时发出的通知
+pub const NOTES_CHANGED_NOTIFICATION: &str = "notesChanged";
+
+// JSON-RPC 2.0 定义的错误码，应用自身的错误使用 -32000
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const APP_ERROR: i64 = -32000;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl From<String> for RpcError {
+    fn from(message: String) -> Self {
+        Self { code: APP_ERROR, message }
+    }
+}
+
+fn invalid_params(e: impl std::fmt::Display) -> RpcError {
+    RpcError { code: INVALID_PARAMS, message: format!("参数错误: {}", e) }
+}
+
+fn params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    // 省略 params 时按空对象处理
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(invalid_params)
+}
+
+fn to_json(value: impl serde::Serialize) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::from(format!("无法序列化结果: {}", e)))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListParams {
+    sort: Option<SortMode>,
+    category: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PathParams {
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    query: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddNodeParams {
+    path: String,
+    // 省略时添加到根节点下
+    parent_id: Option<String>,
+    text: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    index: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    path: String,
+    // markdown（默认）或 json
+    format: Option<String>,
+}
+
+fn call(workspace: &Workspace, method: &str, raw_params: Value) -> Result<Value, RpcError> {
+    match method {
+        "list" => {
+            let ListParams { sort, category } = params(raw_params)?;
+            let mut notes = workspace.get_all_notes(sort.unwrap_or_default())?;
+            if let Some(category) = category {
+                notes.retain(|note| note.category_id == category);
+            }
+            to_json(notes)
+        }
+        "read" => {
+            let PathParams { path } = params(raw_params)?;
+            let content = workspace.read_note(&path)?;
+            Ok(serde_json::from_str(&content).unwrap_or(Value::String(content)))
+        }
+        "search" => {
+            let SearchParams { query } = params(raw_params)?;
+            to_json(workspace.search_notes(&query)?)
+        }
+        "addNode" => {
+            let request: AddNodeParams = params(raw_params)?;
+            let parent_id = match request.parent_id {
+                Some(parent_id) => parent_id,
+                None => workspace.root_node_id(&request.path)?,
+            };
+            let mut data = Map::new();
+            data.insert("text".to_string(), Value::String(request.text));
+            if !request.tags.is_empty() {
+                data.insert("tag".to_string(), json!(request.tags));
+            }
+            to_json(workspace.add_node(&request.path, &parent_id, data, request.index)?)
+        }
+        "export" => {
+            let ExportParams { path, format } = params(raw_params)?;
+            let content = match format.as_deref().unwrap_or("markdown") {
+                "markdown" => workspace.export_markdown(&path)?,
+                "json" => workspace.read_note(&path)?,
+                format => return Err(invalid_params(format!("不支持的导出格式: {}", format))),
+            };
+            Ok(json!({ "content": content }))
+        }
+        _ => Err(RpcError { code: METHOD_NOT_FOUND, message: format!("未知的方法: {}", method) }),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+// 处理单个请求，通知（没有 id 的请求）不返回响应
+fn handle_request(workspace: &Workspace, request: Value) -> Option<Value> {
+    let Value::Object(mut request) = request else {
+        return Some(error_response(Value::Null, INVALID_REQUEST, "请求必须是对象".to_string()));
+    };
+    let id = request.remove("id");
+    let valid_version = request.get("jsonrpc").is_some_and(|version| version == "2.0");
+    let method = match request.remove("method") {
+        Some(Value::String(method)) if valid_version => method,
+        _ => return Some(error_response(id.unwrap_or(Value::Null), INVALID_REQUEST, "无效的 JSON-RPC 请求".to_string())),
+    };
+    let result = call(workspace, &method, request.remove("params").unwrap_or(Value::Null));
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error_response(id, e.code, e.message),
+    })
+}
+
+// 处理一行输入（单个请求或批量请求），返回要写回的响应
+pub fn handle_message(workspace: &Workspace, line: &str) -> Option<Value> {
+    match serde_json::from_str::<Value>(line) {
+        Ok(Value::Array(batch)) if batch.is_empty() => {
+            Some(error_response(Value::Null, INVALID_REQUEST, "批量请求不能为空".to_string()))
+        }
+        Ok(Value::Array(batch)) => {
+            let responses: Vec<Value> = batch.into_iter().filter_map(|request| handle_request(workspace, request)).collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        Ok(request) => handle_request(workspace, request),
+        Err(e) => Some(error_response(Value::Null, PARSE_ERROR, format!("无法解析请求: {}", e))),
+    }
+}
+
+// 笔记文件及其修改时间
+fn snapshot(workspace: &Workspace) -> HashMap<String, Option<SystemTime>> {
+    workspace.note_files()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+            (workspace.relative_path(&path), modified)
+        })
+        .collect()
+}
+
+// 新增、修改或删除的笔记路径
+fn changed_paths(before: &HashMap<String, Option<SystemTime>>, after: &HashMap<String, Option<SystemTime>>) -> Vec<String> {
+    let mut changed: Vec<String> = after.iter()
+        .filter(|(path, modified)| before.get(*path) != Some(modified))
+        .map(|(path, _)| path.clone())
+        .chain(before.keys().filter(|path| !after.contains_key(*path)).cloned())
+        .collect();
+    changed.sort();
+    changed
+}
+
+fn write_line(output: &Mutex<impl Write>, message: &Value) -> std::io::Result<()> {
+    let mut output = output.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    writeln!(output, "{}", message)?;
+    output.flush()
+}
+
+// 以每行一条 JSON 消息的方式提供 JSON-RPC 2.0 服务，直到输入结束；
+// 期间定期检查笔记文件，发现变化时发出 notesChanged 通知
+pub fn serve<R, W>(workspace: Arc<Workspace>, input: R, output: W) -> Result<(), String>
+where
+    R: BufRead,
+    W: Write + Send + 'static,
+{
+    let output = Arc::new(Mutex::new(output));
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    let watcher = {
+        let (workspace, output, stopped) = (workspace.clone(), output.clone(), stopped.clone());
+        std::thread::spawn(move || {
+            let mut known = snapshot(&workspace);
+            while !stopped.load(Ordering::Relaxed) {
+                std::thread::sleep(WATCH_INTERVAL);
+                let current = snapshot(&workspace);
+                let changed = changed_paths(&known, &current);
+                known = current;
+                if changed.is_empty() {
+                    continue;
+                }
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": NOTES_CHANGED_NOTIFICATION,
+                    "params": { "paths": changed },
+                });
+                if write_line(&output, &notification).is_err() {
+                    break;
+                }
+            }
+        })
+    };
+
+    let mut result = Ok(());
+    for line in input.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                result = Err(format!("无法读取输入: {}", e));
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_message(&workspace, &line) {
+            if let Err(e) = write_line(&output, &response) {
+                result = Err(format!("无法写入输出: {}", e));
+                break;
+            }
+        }
+    }
+
+    stopped.store(true, Ordering::Relaxed);
+    let _ = watcher.join();
+    result
+}
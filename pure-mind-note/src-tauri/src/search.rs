@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::nodes::{node_tags, plain_text, walk_nodes};
+use crate::note_format;
+use crate::workspace::{Workspace, WorkspaceState};
+
+// 搜索命中的一个节点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub note_id: String,
+    pub note_title: String,
+    pub path: String,
+    pub node_id: String,
+    pub text: String,
+    pub tags: Vec<String>,
+}
+
+impl Workspace {
+    // 在未加锁笔记的节点文字和标签中搜索，多个关键词（空格分隔）须全部出现，不区分大小写
+    pub fn search_notes(&self, query: &str) -> Result<Vec<SearchHit>, String> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut hits = Vec::new();
+        for (note, content) in self.indexable_notes()? {
+            let Ok(document) = serde_json::from_str::<Value>(&note_format::upgrade_for_read(content)) else {
+                continue;
+            };
+            for (node_id, data, _) in walk_nodes(&document) {
+                let text = plain_text(data);
+                let tags = node_tags(data);
+                let haystack = format!("{} {}", text, tags.join(" ")).to_lowercase();
+                if terms.iter().all(|term| haystack.contains(term)) {
+                    hits.push(SearchHit {
+                        note_id: note.id.clone(),
+                        note_title: note.title.clone(),
+                        path: note.path.clone(),
+                        node_id: node_id.to_string(),
+                        text,
+                        tags,
+                    });
+                }
+            }
+        }
+        backend_log!("[后端] 搜索 \"{}\": {} 个结果", query, hits.len());
+        Ok(hits)
+    }
+}
+
+// 搜索笔记节点
+#[tauri::command]
+pub async fn search_notes(state: State<'_, WorkspaceState>, query: String) -> Result<Vec<SearchHit>, String> {
+    state.run(move |workspace| workspace.search_notes(&query)).await
+}
@@ -0,0 +1,338 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use git2::{ObjectType, Oid};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::backup::BACKUP_SETTINGS_FILE;
+use crate::config::ConfigState;
+use crate::crypto::{ENCRYPTION_FILE, REKEY_SUFFIX};
+use crate::drafts::DRAFTS_DIR;
+use crate::metadata;
+use crate::workspace::{ensure_within, write_atomic, Workspace, WorkspaceState, TEMP_SUFFIX};
+
+// 工作区根目录下记录每个同步目标上次同步状态的文件
+pub const SYNC_STATE_FILE: &str = ".pmn-sync.json";
+
+// 状态中表示目录的标记，文件记录内容哈希
+const DIR_MARKER: &str = "dir";
+
+// 同步和备份时跳过的条目
+pub(crate) fn is_ignored(name: &str) -> bool {
+    name == ".git"
+        || name == DRAFTS_DIR
+        || name == SYNC_STATE_FILE
+        || name == BACKUP_SETTINGS_FILE
+        || name.ends_with(TEMP_SUFFIX)
+        || name.ends_with(REKEY_SUFFIX)
+}
+
+// 某个同步目标上次同步完成时两边一致的条目
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SyncTargetState {
+    last_synced: Option<String>,
+    // 相对路径 -> 内容哈希（目录为 "dir"）
+    entries: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct SyncState {
+    // 规范化后的目标目录 -> 同步状态
+    targets: BTreeMap<String, SyncTargetState>,
+}
+
+// 两边都修改过的条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    pub path: String,
+    // 保存另一边版本的冲突副本；一边删除一边修改时保留修改，没有副本
+    pub conflict_copy: Option<String>,
+}
+
+// 一次同步的结果汇总
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncSummary {
+    pub target: String,
+    // 从目标目录复制到工作区的条目
+    pub pulled: Vec<String>,
+    // 从工作区复制到目标目录的条目
+    pub pushed: Vec<String>,
+    pub deleted_in_workspace: Vec<String>,
+    pub deleted_in_target: Vec<String>,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+// 目录树中的一个条目
+pub(crate) struct TreeEntry {
+    // 以 "/" 分隔的相对路径
+    pub relative: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+// 遍历目录树，跳过仓库、临时文件和本机状态文件；符号链接（包括指向目录的）不跟随也不列出
+pub(crate) fn walk_tree(root: &Path) -> Result<Vec<TreeEntry>, String> {
+    let mut entries = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        let read_dir = fs::read_dir(&dir).map_err(|e| format!("无法读取目录 {}: {}", dir.display(), e))?;
+        for entry in read_dir.flatten() {
+            let name = match entry.file_name().to_str() {
+                Some(name) if !is_ignored(name) => name.to_string(),
+                _ => continue,
+            };
+            let relative = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+            let file_type = entry.file_type().map_err(|e| format!("无法读取文件类型: {}", e))?;
+            if file_type.is_dir() {
+                pending.push((entry.path(), relative.clone()));
+            } else if !file_type.is_file() {
+                continue;
+            }
+            entries.push(TreeEntry { relative, path: entry.path(), is_dir: file_type.is_dir() });
+        }
+    }
+    Ok(entries)
+}
+
+// 扫描目录树，返回相对路径到内容哈希的映射；每个工作区自己的加密参数不同步，避免覆盖另一边的密钥
+fn scan_tree(root: &Path) -> Result<BTreeMap<String, String>, String> {
+    let mut entries = BTreeMap::new();
+    for entry in walk_tree(root)? {
+        if entry.relative == ENCRYPTION_FILE {
+            continue;
+        }
+        if entry.is_dir {
+            entries.insert(entry.relative, DIR_MARKER.to_string());
+            continue;
+        }
+        let bytes = fs::read(&entry.path).map_err(|e| format!("无法读取文件 {}: {}", entry.relative, e))?;
+        let hash = Oid::hash_object(ObjectType::Blob, &bytes).map_err(|e| format!("无法计算哈希: {}", e))?;
+        entries.insert(entry.relative, hash.to_string());
+    }
+    Ok(entries)
+}
+
+// 冲突副本的路径，如 "分类/笔记.conflict-20240101-120000.json"
+fn conflict_path(relative: &str, timestamp: &str) -> String {
+    let (dir, name) = match relative.rsplit_once('/') {
+        Some((dir, name)) => (Some(dir), name),
+        None => (None, relative),
+    };
+    let name = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}.conflict-{}.{}", stem, timestamp, ext),
+        _ => format!("{}.conflict-{}", name, timestamp),
+    };
+    match dir {
+        Some(dir) => format!("{}/{}", dir, name),
+        None => name,
+    }
+}
+
+pub(crate) fn join_relative(root: &Path, relative: &str) -> PathBuf {
+    let mut path = root.to_path_buf();
+    path.extend(relative.split('/'));
+    path
+}
+
+// 同 join_relative，并检查结果（包括路径中的符号链接）仍位于 root 内，root 须是规范化的路径
+fn resolve_within(root: &Path, relative: &str) -> Result<PathBuf, String> {
+    if relative.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+        return Err(format!("非法的路径: {}", relative));
+    }
+    let path = join_relative(root, relative);
+    ensure_within(root, &path)?;
+    Ok(path)
+}
+
+// 把一边的文件复制到另一边，自动创建父目录
+fn copy_entry(from_root: &Path, to_root: &Path, relative: &str, to_relative: &str) -> Result<(), String> {
+    let source = resolve_within(from_root, relative)?;
+    let bytes = fs::read(source).map_err(|e| format!("无法读取文件 {}: {}", relative, e))?;
+    let target = resolve_within(to_root, to_relative)?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("无法创建目录: {}", e))?;
+    }
+    write_atomic(&target, &bytes)
+}
+
+fn create_dir(root: &Path, relative: &str) -> Result<(), String> {
+    fs::create_dir_all(resolve_within(root, relative)?).map_err(|e| format!("无法创建目录: {}", e))
+}
+
+fn remove_file(root: &Path, relative: &str) -> Result<(), String> {
+    match fs::remove_file(resolve_within(root, relative)?) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("无法删除文件 {}: {}", relative, e)),
+        _ => Ok(()),
+    }
+}
+
+impl Workspace {
+    fn read_sync_state(&self) -> SyncState {
+        fs::read_to_string(self.root().join(SYNC_STATE_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    // 与另一个本地目录双向同步
+    pub fn sync_folder(&self, target: &str) -> Result<SyncSummary, String> {
+        let target = Path::new(target);
+        if target.as_os_str().is_empty() {
+            return Err("同步目录不能为空".to_string());
+        }
+        fs::create_dir_all(target).map_err(|e| format!("无法创建同步目录: {}", e))?;
+        let target = fs::canonicalize(target).map_err(|e| format!("无法解析同步目录: {}", e))?;
+        if target.starts_with(self.root()) || self.root().starts_with(&target) {
+            return Err("同步目录不能与工作区互相包含".to_string());
+        }
+        let target_key = target.to_string_lossy().to_string();
+        // 同步期间写入工作区的笔记不能与保存、补丁等修改交错
+        let _guard = self.lock_edits();
+        backend_log!("[后端] 开始同步: {} <-> {}", self.root().display(), target.display());
+
+        let mut state = self.read_sync_state();
+        let base = state.targets.get(&target_key).cloned().unwrap_or_default().entries;
+        let local = scan_tree(self.root())?;
+        let remote = scan_tree(&target)?;
+
+        let mut summary = SyncSummary { target: target_key.clone(), ..Default::default() };
+        let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        // 目录在文件处理完之后再删除，只删除已经变空的目录
+        let mut local_dirs_to_remove = Vec::new();
+        let mut remote_dirs_to_remove = Vec::new();
+
+        let paths: BTreeSet<&String> = local.keys().chain(remote.keys()).chain(base.keys()).collect();
+        for path in paths {
+            let (l, r, b) = (local.get(path), remote.get(path), base.get(path));
+            if l == r {
+                continue;
+            }
+            let is_dir = |hash: Option<&String>| hash.is_some_and(|hash| hash == DIR_MARKER);
+
+            if l == b {
+                // 只有目标目录变化
+                match r {
+                    Some(_) if is_dir(r) => {
+                        create_dir(self.root(), path)?;
+                        summary.pulled.push(path.clone());
+                    }
+                    Some(_) => {
+                        copy_entry(&target, self.root(), path, path)?;
+                        summary.pulled.push(path.clone());
+                    }
+                    None if is_dir(l) => local_dirs_to_remove.push(path.clone()),
+                    None => {
+                        remove_file(self.root(), path)?;
+                        summary.deleted_in_workspace.push(path.clone());
+                    }
+                }
+            } else if r == b {
+                // 只有工作区变化
+                match l {
+                    Some(_) if is_dir(l) => {
+                        create_dir(&target, path)?;
+                        summary.pushed.push(path.clone());
+                    }
+                    Some(_) => {
+                        copy_entry(self.root(), &target, path, path)?;
+                        summary.pushed.push(path.clone());
+                    }
+                    None if is_dir(r) => remote_dirs_to_remove.push(path.clone()),
+                    None => {
+                        remove_file(&target, path)?;
+                        summary.deleted_in_target.push(path.clone());
+                    }
+                }
+            } else if is_dir(l) || is_dir(r) {
+                // 目录与文件同名等无法自动处理的情况，保持两边不变
+                summary.conflicts.push(SyncConflict { path: path.clone(), conflict_copy: None });
+            } else {
+                // 两边都修改了
+                match (l, r) {
+                    (Some(_), Some(_)) if metadata::is_metadata_file(Path::new(path.as_str())) => {
+                        // 冲突副本会被当作笔记扫描，分类元数据以工作区为准
+                        copy_entry(self.root(), &target, path, path)?;
+                        summary.conflicts.push(SyncConflict { path: path.clone(), conflict_copy: None });
+                    }
+                    (Some(_), Some(_)) => {
+                        // 工作区版本保留原名，目标目录的版本另存为冲突副本，两边都保留两份
+                        let copy = conflict_path(path, &timestamp);
+                        copy_entry(&target, self.root(), path, &copy)?;
+                        copy_entry(&target, &target, path, &copy)?;
+                        copy_entry(self.root(), &target, path, path)?;
+                        summary.conflicts.push(SyncConflict { path: path.clone(), conflict_copy: Some(copy) });
+                    }
+                    (Some(_), None) => {
+                        // 目标目录删除、工作区修改：保留修改
+                        copy_entry(self.root(), &target, path, path)?;
+                        summary.conflicts.push(SyncConflict { path: path.clone(), conflict_copy: None });
+                    }
+                    (None, Some(_)) => {
+                        copy_entry(&target, self.root(), path, path)?;
+                        summary.conflicts.push(SyncConflict { path: path.clone(), conflict_copy: None });
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+
+        // 从最深的目录开始删除，目录中还有新文件时保留
+        for (root, dirs, deleted) in [
+            (self.root(), &mut local_dirs_to_remove, &mut summary.deleted_in_workspace),
+            (target.as_path(), &mut remote_dirs_to_remove, &mut summary.deleted_in_target),
+        ] {
+            dirs.sort_by(|a, b| b.cmp(a));
+            for dir in dirs.iter() {
+                if resolve_within(root, dir).is_ok_and(|path| fs::remove_dir(path).is_ok()) {
+                    deleted.push(dir.clone());
+                }
+            }
+        }
+
+        // 同步后两边一致的条目作为下次同步的基准
+        let local = scan_tree(self.root())?;
+        let remote = scan_tree(&target)?;
+        let entries = local
+            .into_iter()
+            .filter(|(path, hash)| remote.get(path) == Some(hash))
+            .collect();
+        state.targets.insert(target_key, SyncTargetState {
+            last_synced: Some(chrono::Local::now().to_rfc3339()),
+            entries,
+        });
+        let content = serde_json::to_string_pretty(&state).map_err(|e| format!("无法序列化同步状态: {}", e))?;
+        write_atomic(&self.root().join(SYNC_STATE_FILE), content.as_bytes())?;
+
+        if !summary.pulled.is_empty() || !summary.deleted_in_workspace.is_empty() || !summary.conflicts.is_empty() {
+            self.record_change(format!("与 {} 同步", summary.target));
+        }
+        backend_log!(
+            "[后端] 同步完成: 拉取 {} 项，推送 {} 项，冲突 {} 项",
+            summary.pulled.len(),
+            summary.pushed.len(),
+            summary.conflicts.len()
+        );
+        Ok(summary)
+    }
+}
+
+// 与另一个本地目录双向同步
+#[tauri::command]
+pub async fn sync_folder(
+    state: State<'_, WorkspaceState>,
+    config: State<'_, ConfigState>,
+    target: Option<String>,
+) -> Result<SyncSummary, String> {
+    // 未指定目录时使用配置中的同步目录
+    let target = target
+        .or_else(|| config.get().sync.folder)
+        .ok_or_else(|| "尚未设置同步目录".to_string())?;
+    state.run(move |workspace| workspace.sync_folder(&target)).await
+}
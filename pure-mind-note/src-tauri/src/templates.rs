@@ -0,0 +1,259 @@
+use std::{collections::{HashMap, HashSet}, fs, path::PathBuf};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::commands::{note_title, NoteInfo};
+use crate::metadata;
+use crate::naming;
+use crate::note_format;
+use crate::note_lock;
+use crate::nodes::{for_each_node_data, new_node_id};
+use crate::outline::markdown_to_note;
+use crate::patch::validate_note_tree;
+use crate::workspace::{Workspace, WorkspaceState};
+
+// 工作区根目录下保存模板的目录，模板是脑图（.json）或 Markdown 大纲（.md）
+pub const TEMPLATES_DIR: &str = ".pmn-templates";
+
+// 模板信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateInfo {
+    // 模板文件名，创建笔记时用它指定模板
+    pub file: String,
+    // 显示名称：脑图模板的标题，Markdown 模板的文件名
+    pub name: String,
+    // "mindmap" 或 "markdown"
+    pub format: String,
+}
+
+// 模板文件的格式
+fn template_format(file: &str) -> Option<&'static str> {
+    match file.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).as_deref() {
+        Some("json") => Some("mindmap"),
+        Some("md") => Some("markdown"),
+        _ => None,
+    }
+}
+
+// 把文字中的 {{变量}} 替换为对应的值，未知的变量保持原样
+fn substitute(text: &str, variables: &HashMap<&str, String>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else { break };
+        let name = rest[start + 2..start + 2 + end].trim();
+        result.push_str(&rest[..start]);
+        match variables.get(name) {
+            Some(value) => result.push_str(value),
+            None => result.push_str(&rest[start..start + 4 + end]),
+        }
+        rest = &rest[start + 4 + end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+// 替换节点数据中所有字符串里的变量（包括富文本和备注）
+fn substitute_value(value: &mut Value, variables: &HashMap<&str, String>) {
+    match value {
+        Value::String(text) => *text = substitute(text, variables),
+        Value::Array(items) => items.iter_mut().for_each(|item| substitute_value(item, variables)),
+        Value::Object(object) => object.values_mut().for_each(|item| substitute_value(item, variables)),
+        _ => {}
+    }
+}
+
+// 富文本节点的文字是 HTML，代入的值需要转义，避免标题等内容被当作标签
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// 为模板中的每个节点生成新的ID
+fn regenerate_node_ids(note: &mut Value) -> Result<(), String> {
+    let root = note.get_mut("data")
+        .filter(|root| root.get("data").is_some_and(Value::is_object))
+        .ok_or_else(|| "模板缺少节点数据".to_string())?;
+    let mut used = HashSet::new();
+    for_each_node_data(root, &mut |data| {
+        let id = new_node_id(&used);
+        data.remove("id");
+        data.insert("uid".to_string(), Value::String(id.clone()));
+        used.insert(id);
+    });
+    Ok(())
+}
+
+impl Workspace {
+    fn templates_dir(&self) -> PathBuf {
+        self.root().join(TEMPLATES_DIR)
+    }
+
+    // 模板文件的路径，只接受模板目录中的文件名
+    fn template_path(&self, file: &str) -> Result<PathBuf, String> {
+        if file.is_empty() || file.contains(['/', '\\']) || file.starts_with('.') || template_format(file).is_none() {
+            return Err(format!("无效的模板: {}", file));
+        }
+        let path = self.templates_dir().join(file);
+        if !path.is_file() {
+            return Err(format!("模板不存在: {}", file));
+        }
+        self.ensure_inside(&path)?;
+        Ok(path)
+    }
+
+    // 读取模板，统一转换为当前格式的脑图
+    fn read_template(&self, file: &str) -> Result<Value, String> {
+        let bytes = self.read_note_bytes(&self.template_path(file)?)?;
+        if note_lock::is_locked(&bytes) {
+            return Err("模板已加锁，不能使用".to_string());
+        }
+        let content = String::from_utf8(bytes).map_err(|e| format!("无法读取模板: {}", e))?;
+        let mut note = if template_format(file) == Some("markdown") {
+            markdown_to_note(&content)
+        } else {
+            serde_json::from_str(&content).map_err(|e| format!("模板不是有效的JSON: {}", e))?
+        };
+        note_format::migrate_note(&mut note)?;
+        Ok(note)
+    }
+
+    // 列出模板目录中的模板，按名称排序
+    pub fn list_templates(&self) -> Result<Vec<TemplateInfo>, String> {
+        let entries = match fs::read_dir(self.templates_dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("无法读取模板目录: {}", e)),
+        };
+        let mut templates: Vec<TemplateInfo> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let file = entry.file_name().to_string_lossy().to_string();
+                let format = template_format(&file)?;
+                if file.starts_with('.') || !entry.path().is_file() {
+                    return None;
+                }
+                let stem = file.rsplit_once('.').map_or(file.as_str(), |(stem, _)| stem).to_string();
+                let name = match format {
+                    "mindmap" => self.read_note_bytes(&entry.path()).ok()
+                        .and_then(|bytes| note_title(&bytes))
+                        .unwrap_or(stem),
+                    _ => stem,
+                };
+                Some(TemplateInfo { file, name, format: format.to_string() })
+            })
+            .collect();
+        templates.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.file.cmp(&b.file)));
+        Ok(templates)
+    }
+
+    // 用模板创建笔记：替换 {{date}}、{{title}}、{{category}} 等变量并重新生成节点ID
+    pub fn create_note_from_template(
+        &self,
+        template: &str,
+        category_id: &str,
+        sub_category_id: Option<&str>,
+        title: &str,
+    ) -> Result<NoteInfo, String> {
+        let title = naming::validate_display_name(title)?;
+        let mut note = self.read_template(template)?;
+
+        let mut dir = vec![category_id];
+        dir.extend(sub_category_id);
+        let dir_path = self.resolve_parts(&dir)?;
+        if !dir_path.is_dir() {
+            return Err(format!("分类不存在: {}", dir.join("/")));
+        }
+        // {{category}} 是笔记所在分类（或子分类）的显示名称
+        let category = metadata::read_metadata(&dir_path).name.unwrap_or_else(|| dir.last().copied().unwrap_or_default().to_string());
+        let now = Local::now();
+        let variables = HashMap::from([
+            ("date", now.format("%Y-%m-%d").to_string()),
+            ("time", now.format("%H:%M").to_string()),
+            ("title", title.clone()),
+            ("category", category),
+        ]);
+
+        let escaped: HashMap<&str, String> = variables.iter().map(|(name, value)| (*name, escape_html(value))).collect();
+
+        regenerate_node_ids(&mut note)?;
+        if let Some(root) = note.get_mut("data") {
+            for_each_node_data(root, &mut |data| {
+                let rich_text = data.get("richText").and_then(Value::as_bool) == Some(true);
+                for value in data.values_mut() {
+                    substitute_value(value, if rich_text { &escaped } else { &variables });
+                }
+            });
+        }
+        // 根节点没有文字时使用笔记标题
+        let root = &mut note["data"]["data"];
+        let root_title = match root["richText"].as_bool() {
+            Some(true) => escape_html(&title),
+            _ => title.clone(),
+        };
+        if let Some(text) = root.get_mut("text") {
+            if text.as_str().map_or(true, |text| text.trim().is_empty()) {
+                *text = Value::String(root_title);
+            }
+        }
+        note["lastUpdated"] = Value::String(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+        validate_note_tree(&note).map_err(|e| format!("模板的节点树结构错误: {}", e))?;
+
+        let content = serde_json::to_string(&note).map_err(|e| format!("无法序列化笔记内容: {}", e))?;
+        let info = self.create_note(category_id, sub_category_id, &title, &content)?;
+        backend_log!("[后端] 已用模板 {} 创建笔记: {}", template, info.path);
+        Ok(info)
+    }
+
+    // 把笔记保存为模板，返回模板信息
+    pub fn save_note_as_template(&self, path: &str, name: &str) -> Result<TemplateInfo, String> {
+        let name = naming::validate_display_name(name)?;
+        let file = format!("{}.json", naming::slugify(&name)?);
+        let dir = self.templates_dir();
+        if let Some(existing) = naming::find_conflict(&dir, file.trim_end_matches(".json"), Some("json")) {
+            return Err(format!("模板 '{}' 与已有的模板 '{}' 冲突", name, existing));
+        }
+
+        let mut note = self.read_note_value(path)?;
+        if let Some(object) = note.as_object_mut() {
+            object.remove("id");
+            object.insert("title".to_string(), Value::String(name.clone()));
+        }
+        let content = serde_json::to_string_pretty(&note).map_err(|e| format!("无法序列化模板: {}", e))?;
+        self.write_note_bytes(&dir.join(&file), content.as_bytes())?;
+        self.record_change(format!("保存模板《{}》", name));
+        Ok(TemplateInfo { file, name, format: "mindmap".to_string() })
+    }
+}
+
+// 列出模板
+#[tauri::command]
+pub async fn list_templates(state: State<'_, WorkspaceState>) -> Result<Vec<TemplateInfo>, String> {
+    state.run(move |workspace| workspace.list_templates()).await
+}
+
+// 用模板创建笔记
+#[tauri::command]
+pub async fn create_note_from_template(
+    state: State<'_, WorkspaceState>,
+    template: String,
+    category_id: String,
+    sub_category_id: Option<String>,
+    title: String,
+) -> Result<NoteInfo, String> {
+    state.run(move |workspace| {
+        workspace.create_note_from_template(&template, &category_id, sub_category_id.as_deref(), &title)
+    }).await
+}
+
+// 把笔记保存为模板
+#[tauri::command]
+pub async fn save_note_as_template(state: State<'_, WorkspaceState>, path: String, name: String) -> Result<TemplateInfo, String> {
+    state.run(move |workspace| workspace.save_note_as_template(&path, &name)).await
+}
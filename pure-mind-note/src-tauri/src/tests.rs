@@ -92,19 +92,20 @@ mod tests {
     // 测试分类管理相关功能
     #[test]
     fn test_category_operations() {
-        use app_lib::commands::{get_all_categories, create_category, create_subcategory};
+        use app_lib::ordering::SortMode;
+        use app_lib::workspace::Workspace;
         
         // 创建临时目录作为数据目录
         let temp_dir = tempdir().expect("无法创建临时目录");
-        let data_dir = temp_dir.path().to_string_lossy().to_string();
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
         
         // 初始状态应该没有分类
-        let categories = get_all_categories(data_dir.clone()).expect("获取分类失败");
+        let categories = workspace.get_all_categories(SortMode::Created).expect("获取分类失败");
         assert!(categories.is_empty(), "新创建的目录应该没有任何分类");
         
         // 测试创建分类
         let category_name = "学习笔记";
-        let category_id = create_category(data_dir.clone(), category_name.to_string())
+        let category_id = workspace.create_category(category_name)
             .expect("创建分类失败");
         
         assert_eq!(category_id, category_name, "分类ID应该与名称相同");
@@ -114,7 +115,7 @@ mod tests {
         assert!(category_path.exists() && category_path.is_dir(), "分类目录未创建");
         
         // 获取更新后的分类列表
-        let categories = get_all_categories(data_dir.clone()).expect("获取分类失败");
+        let categories = workspace.get_all_categories(SortMode::Created).expect("获取分类失败");
         assert_eq!(categories.len(), 1, "应该有一个分类");
         assert_eq!(categories[0].id, category_name);
         assert_eq!(categories[0].name, category_name);
@@ -122,11 +123,7 @@ mod tests {
         
         // 测试创建子分类
         let subcategory_name = "编程语言";
-        let subcategory_id = create_subcategory(
-            data_dir.clone(), 
-            category_name.to_string(), 
-            subcategory_name.to_string()
-        ).expect("创建子分类失败");
+        let subcategory_id = workspace.create_subcategory(category_name, subcategory_name).expect("创建子分类失败");
         
         assert_eq!(subcategory_id, subcategory_name, "子分类ID应该与名称相同");
         
@@ -135,7 +132,7 @@ mod tests {
         assert!(subcategory_path.exists() && subcategory_path.is_dir(), "子分类目录未创建");
         
         // 验证更新后的分类列表
-        let categories = get_all_categories(data_dir.clone()).expect("获取分类失败");
+        let categories = workspace.get_all_categories(SortMode::Created).expect("获取分类失败");
         assert_eq!(categories.len(), 1, "应该只有一个分类");
         assert_eq!(categories[0].sub_categories.len(), 1, "分类应该有一个子分类");
         
@@ -146,25 +143,17 @@ mod tests {
         
         // 测试创建多个分类和子分类
         let category_name2 = "工作文档";
-        create_category(data_dir.clone(), category_name2.to_string())
+        workspace.create_category(category_name2)
             .expect("创建第二个分类失败");
             
         let subcategory_name2 = "会议记录";
-        create_subcategory(
-            data_dir.clone(), 
-            category_name2.to_string(), 
-            subcategory_name2.to_string()
-        ).expect("创建第二个子分类失败");
+        workspace.create_subcategory(category_name2, subcategory_name2).expect("创建第二个子分类失败");
         
         let subcategory_name3 = "项目计划";
-        create_subcategory(
-            data_dir.clone(), 
-            category_name2.to_string(), 
-            subcategory_name3.to_string()
-        ).expect("创建第三个子分类失败");
+        workspace.create_subcategory(category_name2, subcategory_name3).expect("创建第三个子分类失败");
         
         // 验证最终的分类结构
-        let categories = get_all_categories(data_dir.clone()).expect("获取分类失败");
+        let categories = workspace.get_all_categories(SortMode::Created).expect("获取分类失败");
         assert_eq!(categories.len(), 2, "应该有两个分类");
         
         // 找到工作文档分类
@@ -175,13 +164,1866 @@ mod tests {
         assert_eq!(work_category.sub_categories.len(), 2, "工作文档分类应该有两个子分类");
         
         // 测试错误处理：无效的分类路径
-        let result = create_subcategory(
-            data_dir.clone(), 
-            "不存在的分类".to_string(), 
-            "测试子分类".to_string()
-        );
+        let result = workspace.create_subcategory("不存在的分类", "测试子分类");
         
         // 这里应该成功创建目录，因为create_dir_all会创建所有必要的父目录
         assert!(result.is_ok(), "在不存在的分类下创建子分类应该能成功");
     }
+
+    // 测试工作区路径边界：相对路径越界、非法名称与符号链接逃逸
+    #[test]
+    fn test_workspace_path_confinement() {
+        use app_lib::note_format::NOTE_FORMAT_VERSION;
+        use app_lib::ordering::SortMode;
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let outside_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path().join("workspace")).expect("无法打开工作区");
+
+        // 正常的相对路径可以读写
+        workspace.save_note("学习笔记/Rust.json", "{}").expect("保存笔记失败");
+        let content = workspace.read_note("学习笔记/Rust.json").expect("读取笔记失败");
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&content).unwrap(), json!({"formatVersion": NOTE_FORMAT_VERSION}));
+        let notes = workspace.get_all_notes(SortMode::Created).expect("获取笔记失败");
+        assert_eq!(notes[0].path, "学习笔记/Rust.json", "笔记路径应相对于工作区根目录");
+
+        // 绝对路径与 ../ 都应被拒绝
+        let absolute = outside_dir.path().join("secret.json");
+        assert!(workspace.save_note(&absolute.to_string_lossy(), "{}").is_err(), "绝对路径应被拒绝");
+        assert!(workspace.read_note("../secret.json").is_err(), "../ 应被拒绝");
+        assert!(workspace.save_note("学习笔记/../../secret.json", "{}").is_err(), "中间的 ../ 应被拒绝");
+        assert!(!temp_dir.path().join("secret.json").exists(), "不应在工作区外写入文件");
+
+        // 分类名称中的路径分隔符会被替换，无法借此逃出工作区
+        let escaped = workspace.create_category("../逃逸").expect("创建分类失败");
+        assert!(workspace.root().join(&escaped).is_dir(), "分类应创建在工作区内");
+        assert!(workspace.create_subcategory("学习笔记", "..").is_err());
+        assert!(workspace.create_subcategory("../学习笔记", "子分类").is_err());
+        assert!(workspace.delete_category("..").is_err());
+        assert!(workspace.delete_note("..#..#secret").is_err());
+
+        // 指向工作区外的符号链接不能被跟随
+        #[cfg(unix)]
+        {
+            fs::write(outside_dir.path().join("secret.json"), "机密").expect("无法写入测试文件");
+            std::os::unix::fs::symlink(outside_dir.path(), workspace.root().join("链接分类"))
+                .expect("无法创建符号链接");
+
+            assert!(workspace.read_note("链接分类/secret.json").is_err(), "不应通过符号链接读取工作区外的文件");
+            assert!(workspace.save_note("链接分类/new.json", "{}").is_err(), "不应通过符号链接写入工作区外");
+            let notes = workspace.get_all_notes(SortMode::Created).expect("获取笔记失败");
+            assert!(notes.iter().all(|note| note.category_id != "链接分类"), "扫描时应跳过逃逸的符号链接");
+        }
+    }
+
+    // 测试跨平台的名称校验与转换
+    #[test]
+    fn test_name_slugging() {
+        use app_lib::naming::{slugify, fold_name};
+
+        assert_eq!(slugify("学习笔记").unwrap(), "学习笔记");
+        assert_eq!(slugify("  前后空格  ").unwrap(), "前后空格");
+        assert_eq!(slugify("a/b\\c:d?e*f").unwrap(), "a_b_c_d_e_f");
+        assert_eq!(slugify("<会议>|\"纪要\"").unwrap(), "_会议___纪要_");
+        assert_eq!(slugify("结尾的点...").unwrap(), "结尾的点");
+        assert_eq!(slugify(".隐藏").unwrap(), "_隐藏");
+        assert_eq!(slugify("CON").unwrap(), "CON_");
+        assert_eq!(slugify("nul.txt").unwrap(), "nul_.txt");
+        assert_eq!(slugify("Lpt1").unwrap(), "Lpt1_");
+        assert_eq!(slugify("CONSOLE").unwrap(), "CONSOLE");
+
+        // 无法转换为文件名的输入给出错误
+        assert!(slugify("").is_err());
+        assert!(slugify("   ").is_err());
+        assert!(slugify("...").is_err());
+        assert!(slugify("换行\n").is_ok(), "首尾空白会被去除");
+        assert!(slugify("中间\t制表符").is_err());
+
+        // 超长名称按字符边界截断
+        let long_name = "长".repeat(200);
+        let slug = slugify(&long_name).unwrap();
+        assert!(slug.len() <= 200 && slug.chars().all(|c| c == '长'));
+
+        // NFD 输入会被规范化为 NFC
+        assert_eq!(slugify("Cafe\u{301}").unwrap(), "Caf\u{e9}");
+        assert_eq!(fold_name("CAFE\u{301}"), fold_name("caf\u{e9}"));
+    }
+
+    // 测试分类和笔记的显示名称与冲突检测
+    #[test]
+    fn test_display_names_and_collisions() {
+        use app_lib::ordering::SortMode;
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+
+        // 目录名经过转换，显示名称保持原样
+        let category_id = workspace.create_category("项目: A/B").expect("创建分类失败");
+        assert_eq!(category_id, "项目_ A_B");
+        let sub_id = workspace.create_subcategory(&category_id, "Q1?").expect("创建子分类失败");
+        assert_eq!(sub_id, "Q1_");
+
+        let categories = workspace.get_all_categories(SortMode::Created).expect("获取分类失败");
+        assert_eq!(categories[0].name, "项目: A/B");
+        assert_eq!(categories[0].sub_categories[0].name, "Q1?");
+
+        // 大小写或 Unicode 规范化不同的名称视为冲突
+        workspace.create_category("Café").expect("创建分类失败");
+        let error = workspace.create_category("CAFE\u{301}").unwrap_err();
+        assert!(error.contains("冲突"), "应报告冲突: {}", error);
+        assert!(workspace.create_category("项目: A/B").is_err(), "重复名称应报告冲突");
+        assert!(workspace.create_category("项目_ a_b").is_err(), "转换后相同的名称应报告冲突");
+
+        // 笔记标题转换为文件名，原标题保存在内容中
+        let note = workspace
+            .create_note(&category_id, Some(&sub_id), "会议: 周报?", r#"{"id":"临时","title":"临时"}"#)
+            .expect("创建笔记失败");
+        assert_eq!(note.id, format!("{}#{}#会议_ 周报_", category_id, sub_id));
+        assert_eq!(note.title, "会议: 周报?");
+
+        let content: serde_json::Value = serde_json::from_str(&workspace.read_note(&note.path).unwrap()).unwrap();
+        assert_eq!(content["id"], note.id.as_str());
+        assert_eq!(content["title"], "会议: 周报?");
+
+        let notes = workspace.get_all_notes(SortMode::Created).expect("获取笔记失败");
+        assert_eq!(notes.len(), 1, "元数据文件不应被当作笔记");
+        assert_eq!(notes[0].title, "会议: 周报?");
+
+        let error = workspace
+            .create_note(&category_id, Some(&sub_id), "会议_ 周报_", "{}")
+            .unwrap_err();
+        assert!(error.contains("冲突"), "应报告笔记冲突: {}", error);
+
+        // 只有元数据文件的分类视为空分类，可以删除
+        let empty_id = workspace.create_category("空分类").expect("创建分类失败");
+        workspace.delete_category(&empty_id).expect("删除空分类失败");
+        assert!(!workspace.root().join(&empty_id).exists());
+    }
+
+    // 测试分类元数据文件的读取与更新
+    #[test]
+    fn test_category_metadata() {
+        use app_lib::metadata::{CategoryMetadataUpdate, METADATA_FILE};
+        use app_lib::ordering::SortMode;
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+
+        let category_id = workspace.create_category("工作").expect("创建分类失败");
+        let sub_id = workspace.create_subcategory(&category_id, "会议").expect("创建子分类失败");
+        assert!(temp_dir.path().join(&category_id).join(METADATA_FILE).exists(), "创建分类时应写入元数据文件");
+
+        // 更新分类的各项显示信息
+        let updated = workspace
+            .update_category_metadata(&category_id, None, CategoryMetadataUpdate {
+                name: Some("工作事项".to_string()),
+                description: Some("  日常工作  ".to_string()),
+                color: Some("#FF8800".to_string()),
+                icon: Some("💼".to_string()),
+                position: Some(2),
+            })
+            .expect("更新元数据失败");
+        assert_eq!(updated.description.as_deref(), Some("日常工作"));
+        assert_eq!(updated.color.as_deref(), Some("#ff8800"));
+
+        workspace
+            .update_category_metadata(&category_id, Some(&sub_id), CategoryMetadataUpdate {
+                icon: Some("📅".to_string()),
+                ..Default::default()
+            })
+            .expect("更新子分类元数据失败");
+
+        let categories = workspace.get_all_categories(SortMode::Created).expect("获取分类失败");
+        let category = &categories[0];
+        assert_eq!(category.id, category_id, "更新显示名称不应改变目录名");
+        assert_eq!(category.name, "工作事项");
+        assert_eq!(category.description.as_deref(), Some("日常工作"));
+        assert_eq!(category.icon.as_deref(), Some("💼"));
+        assert_eq!(category.position, Some(2));
+        assert!(category.created_time.contains('T'), "创建时间应来自元数据: {}", category.created_time);
+        assert_eq!(category.sub_categories[0].name, "会议");
+        assert_eq!(category.sub_categories[0].icon.as_deref(), Some("📅"));
+
+        // 空字符串清除字段，无效值被拒绝
+        let cleared = workspace
+            .update_category_metadata(&category_id, None, CategoryMetadataUpdate {
+                color: Some(String::new()),
+                ..Default::default()
+            })
+            .expect("清除颜色失败");
+        assert!(cleared.color.is_none());
+        assert_eq!(cleared.name.as_deref(), Some("工作事项"), "未提供的字段应保持不变");
+
+        let invalid_color = CategoryMetadataUpdate { color: Some("red".to_string()), ..Default::default() };
+        assert!(workspace.update_category_metadata(&category_id, None, invalid_color).is_err());
+        let invalid_name = CategoryMetadataUpdate { name: Some("  ".to_string()), ..Default::default() };
+        assert!(workspace.update_category_metadata(&category_id, None, invalid_name).is_err());
+        assert!(workspace.update_category_metadata("不存在", None, CategoryMetadataUpdate::default()).is_err());
+
+        // 没有元数据文件的旧分类仍使用目录名
+        fs::create_dir(temp_dir.path().join("旧分类")).expect("无法创建目录");
+        let categories = workspace.get_all_categories(SortMode::Created).expect("获取分类失败");
+        let legacy = categories.iter().find(|c| c.id == "旧分类").expect("未找到旧分类");
+        assert_eq!(legacy.name, "旧分类");
+        assert!(legacy.color.is_none());
+    }
+
+    // 测试排序方式与拖拽后的自定义顺序
+    #[test]
+    fn test_sort_modes_and_manual_order() {
+        use app_lib::ordering::SortMode;
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+
+        for name in ["banana", "Apple", "cherry"] {
+            workspace.create_category(name).expect("创建分类失败");
+        }
+        let ids = |sort| -> Vec<String> {
+            workspace.get_all_categories(sort).expect("获取分类失败").into_iter().map(|c| c.id).collect()
+        };
+
+        // 按标题排序忽略大小写
+        assert_eq!(ids(SortMode::Title), vec!["Apple", "banana", "cherry"]);
+
+        // 自定义顺序保存在工作区中，重新打开后仍然有效
+        workspace
+            .reorder_categories(&["cherry".to_string(), "Apple".to_string(), "banana".to_string()])
+            .expect("保存分类顺序失败");
+        let reopened = Workspace::open(temp_dir.path()).expect("无法重新打开工作区");
+        let custom: Vec<String> = reopened.get_all_categories(SortMode::Custom).unwrap().into_iter().map(|c| c.id).collect();
+        assert_eq!(custom, vec!["cherry", "Apple", "banana"]);
+
+        // 未参与排序的新分类排在后面
+        workspace.create_category("date").expect("创建分类失败");
+        assert_eq!(ids(SortMode::Custom).last().map(String::as_str), Some("date"));
+        assert!(workspace.reorder_categories(&["不存在".to_string()]).is_err());
+
+        // 子分类排序
+        for name in ["x", "y", "z"] {
+            workspace.create_subcategory("Apple", name).expect("创建子分类失败");
+        }
+        workspace
+            .reorder_subcategories("Apple", &["z".to_string(), "x".to_string(), "y".to_string()])
+            .expect("保存子分类顺序失败");
+        let categories = workspace.get_all_categories(SortMode::Custom).unwrap();
+        let apple = categories.iter().find(|c| c.id == "Apple").unwrap();
+        let sub_ids: Vec<&str> = apple.sub_categories.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(sub_ids, vec!["z", "x", "y"]);
+
+        // 笔记排序
+        let mut note_ids = Vec::new();
+        for title in ["第一", "第二", "第三"] {
+            let note = workspace.create_note("Apple", Some("x"), title, "{}").expect("创建笔记失败");
+            note_ids.push(note.id);
+        }
+        note_ids.reverse();
+        workspace.reorder_notes("Apple", Some("x"), &note_ids).expect("保存笔记顺序失败");
+        let notes = workspace.get_all_notes(SortMode::Custom).unwrap();
+        let titles: Vec<&str> = notes.iter().map(|n| n.title.as_str()).collect();
+        assert_eq!(titles, vec!["第三", "第二", "第一"]);
+        assert_eq!(notes[0].position, Some(0));
+
+        let titles: Vec<String> = workspace.get_all_notes(SortMode::Title).unwrap().into_iter().map(|n| n.title).collect();
+        let mut expected = titles.clone();
+        expected.sort();
+        assert_eq!(titles, expected);
+
+        assert!(workspace.reorder_notes("Apple", Some("y"), &note_ids).is_err(), "其他目录的笔记应被拒绝");
+
+        // 笔记ID中的文件名不能逃出分类目录，也不能指向元数据文件
+        fs::write(temp_dir.path().join("outside.json"), "{}").unwrap();
+        for invalid in ["Apple#x#../../outside", "Apple#x#../y/第一", "Apple#x#_category", "Apple##第一", "banana#x#第一"] {
+            assert!(workspace.reorder_notes("Apple", Some("x"), &[invalid.to_string()]).is_err(), "应拒绝 {}", invalid);
+        }
+        assert_eq!(workspace.get_all_notes(SortMode::Custom).unwrap()[0].title, "第三");
+    }
+
+    // 基准测试：扫描生成的 10000 个笔记的工作区，耗时超出上限即失败
+    // 生成文件较慢，默认不运行：cargo test test_scan_benchmark -- --ignored
+    #[test]
+    #[ignore]
+    fn test_scan_benchmark_10k_notes() {
+        use app_lib::ordering::SortMode;
+        use app_lib::workspace::Workspace;
+        use std::time::{Duration, Instant};
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let root = temp_dir.path();
+
+        // 20 个分类 × 10 个子分类 × 50 个笔记
+        for category in 0..20 {
+            for sub in 0..10 {
+                let dir = root.join(format!("分类{}", category)).join(format!("子分类{}", sub));
+                fs::create_dir_all(&dir).expect("无法创建目录");
+                for index in 0..50 {
+                    let title = format!("笔记{}-{}-{}", category, sub, index);
+                    let mut data = serde_json::Map::new();
+                    for node in 0..20 {
+                        data.insert(format!("node{}", node), json!({ "data": { "id": format!("node{}", node), "text": "节点内容" } }));
+                    }
+                    let content = json!({ "id": title, "title": title, "rootId": "node0", "data": data });
+                    fs::write(dir.join(format!("{}.json", title)), content.to_string()).expect("无法写入笔记");
+                }
+            }
+        }
+
+        let workspace = Workspace::open(root).expect("无法打开工作区");
+
+        // 上限按未优化的调试构建估算，留有数倍余量
+        let started = Instant::now();
+        let notes = workspace.get_all_notes(SortMode::Created).expect("获取笔记失败");
+        let elapsed = started.elapsed();
+        assert!(elapsed < Duration::from_secs(5), "扫描笔记耗时 {:?} 超出上限", elapsed);
+        assert_eq!(notes.len(), 10_000);
+        assert!(notes.iter().all(|note| note.title.starts_with("笔记")), "标题应从笔记内容中读取");
+
+        let started = Instant::now();
+        let categories = workspace.get_all_categories(SortMode::Title).expect("获取分类失败");
+        let elapsed = started.elapsed();
+        assert!(elapsed < Duration::from_secs(1), "扫描分类耗时 {:?} 超出上限", elapsed);
+        assert_eq!(categories.len(), 20);
+        assert!(categories.iter().all(|category| category.sub_categories.len() == 10));
+    }
+
+    // 测试工作区加密：启用、透明读写、锁定解锁与更换口令
+    #[test]
+    fn test_workspace_encryption() {
+        use app_lib::crypto::{ENCRYPTION_FILE, REKEY_SUFFIX};
+        use app_lib::ordering::SortMode;
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category_id = workspace.create_category("客户").expect("创建分类失败");
+        let existing = workspace
+            .create_note(&category_id, None, "已有笔记", r#"{"title":"已有笔记","secret":"客户电话"}"#)
+            .expect("创建笔记失败");
+
+        // 启用加密后已有笔记被加密，内容仍可透明读取
+        workspace.enable_encryption("正确的口令", Some(0)).expect("启用加密失败");
+        assert!(temp_dir.path().join(ENCRYPTION_FILE).exists());
+        let raw = fs::read(temp_dir.path().join(&existing.path)).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("客户电话"), "磁盘上不应有明文");
+        assert!(workspace.read_note(&existing.path).unwrap().contains("客户电话"));
+
+        workspace.save_note(&existing.path, r#"{"title":"已有笔记","secret":"新电话"}"#).expect("保存失败");
+        let raw = fs::read(temp_dir.path().join(&existing.path)).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("新电话"), "保存时应写入密文");
+        assert!(workspace.enable_encryption("另一个口令", None).is_err(), "不能重复启用");
+
+        // 锁定后无法读写，列表中标题退回为文件名
+        workspace.vault().lock();
+        assert!(workspace.read_note(&existing.path).is_err());
+        assert!(workspace.save_note(&existing.path, "{}").is_err());
+        assert_eq!(workspace.get_all_notes(SortMode::Created).unwrap()[0].title, "已有笔记");
+
+        // 重新打开的工作区需要用口令解锁
+        let reopened = Workspace::open(temp_dir.path()).expect("无法重新打开工作区");
+        assert!(reopened.vault().status().enabled);
+        assert!(!reopened.vault().status().unlocked);
+        assert!(reopened.unlock("错误的口令").is_err());
+        reopened.unlock("正确的口令").expect("解锁失败");
+        assert!(reopened.read_note(&existing.path).unwrap().contains("新电话"));
+
+        // 更换口令后旧口令失效，内容不变
+        reopened.change_passphrase("正确的口令", "新口令").expect("更换口令失败");
+        assert!(reopened.note_files_with_suffix(REKEY_SUFFIX).unwrap().is_empty(), "不应留下临时文件");
+        let after_change = Workspace::open(temp_dir.path()).unwrap();
+        assert!(after_change.unlock("正确的口令").is_err());
+        after_change.unlock("新口令").expect("用新口令解锁失败");
+        assert!(after_change.read_note(&existing.path).unwrap().contains("新电话"));
+
+        // 自动锁定时间为 0 时不自动锁定
+        assert!(!after_change.vault().lock_if_idle());
+    }
+
+    // 测试单篇笔记加锁：加锁后普通读写被拒绝，密码读写与解锁，索引跳过加锁笔记
+    #[test]
+    fn test_note_locking() {
+        use app_lib::ordering::SortMode;
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category_id = workspace.create_category("日记").expect("创建分类失败");
+        let private = workspace
+            .create_note(&category_id, None, "私密日记", r#"{"title":"私密日记","text":"秘密内容"}"#)
+            .expect("创建笔记失败");
+        workspace
+            .create_note(&category_id, None, "公开笔记", r#"{"title":"公开笔记","text":"公开内容"}"#)
+            .expect("创建笔记失败");
+
+        workspace.lock_note(&private.path, "密码").expect("加锁失败");
+        assert!(workspace.lock_note(&private.path, "密码").is_err(), "不能重复加锁");
+        let raw = fs::read(temp_dir.path().join(&private.path)).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("秘密内容"), "磁盘上不应有明文");
+
+        // 普通读写被拒绝，列表中标记为加锁并显示文件名
+        assert!(workspace.read_note(&private.path).is_err());
+        assert!(workspace.save_note(&private.path, "{}").is_err(), "不能用明文覆盖加锁笔记");
+        let notes = workspace.get_all_notes(SortMode::Title).unwrap();
+        let listed = notes.iter().find(|note| note.path == private.path).unwrap();
+        assert!(listed.locked);
+        assert_eq!(listed.title, "私密日记");
+
+        // 索引只包含未加锁的笔记
+        let indexable = workspace.indexable_notes().unwrap();
+        assert_eq!(indexable.len(), 1);
+        assert!(indexable[0].1.contains("公开内容"));
+
+        // 用密码读写，保存后仍保持加锁
+        assert!(workspace.read_locked_note(&private.path, "错误密码").is_err());
+        assert!(workspace.read_locked_note(&private.path, "密码").unwrap().contains("秘密内容"));
+        assert!(workspace.save_locked_note(&private.path, "错误密码", "{}").is_err());
+        workspace
+            .save_locked_note(&private.path, "密码", r#"{"title":"私密日记","text":"修改后"}"#)
+            .expect("保存加锁笔记失败");
+        assert!(workspace.read_note(&private.path).is_err());
+
+        // 解锁后恢复为普通笔记
+        assert!(workspace.unlock_note(&private.path, "密码").unwrap().contains("修改后"));
+        assert!(workspace.read_note(&private.path).unwrap().contains("修改后"));
+        assert_eq!(workspace.indexable_notes().unwrap().len(), 2);
+    }
+
+    // 测试 git 历史：启用后修改被合并提交，可按笔记查看历史并恢复旧版本
+    #[test]
+    fn test_git_history() {
+        use std::time::Duration;
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category_id = workspace.create_category("读书").expect("创建分类失败");
+        assert!(!workspace.history().status().enabled);
+        assert!(workspace.history_log(None, 10).is_err(), "未启用时没有历史");
+
+        // 已有的 .gitignore 保留原有规则并补充缺少的规则，草稿不会被提交
+        fs::write(temp_dir.path().join(".gitignore"), "node_modules/").unwrap();
+        fs::create_dir_all(temp_dir.path().join(".pmn-drafts")).unwrap();
+        fs::write(temp_dir.path().join(".pmn-drafts/a.journal"), "草稿").unwrap();
+        workspace.enable_history().expect("启用历史失败");
+        assert!(temp_dir.path().join(".git").is_dir());
+        let ignore = fs::read_to_string(temp_dir.path().join(".gitignore")).unwrap();
+        assert!(ignore.starts_with("node_modules/\n") && ignore.contains("/.pmn-drafts/\n"), "{}", ignore);
+        let repo = git2::Repository::open(temp_dir.path()).unwrap();
+        let head = repo.head().unwrap().peel_to_tree().unwrap();
+        assert!(head.get_path(std::path::Path::new(".pmn-drafts/a.journal")).is_err());
+        assert!(workspace.enable_history().is_err(), "不能重复启用");
+
+        let note = workspace
+            .create_note(&category_id, None, "三体", r#"{"title":"三体","text":"第一版"}"#)
+            .expect("创建笔记失败");
+        workspace.save_note(&note.path, r#"{"title":"三体","text":"第二版"}"#).expect("保存失败");
+
+        // 防抖时间内不提交，连续的修改合并为一次提交
+        assert_eq!(workspace.history().status().pending_changes, 2);
+        assert!(workspace.commit_if_idle(Duration::from_secs(3600)).unwrap().is_none());
+        let first = workspace.flush_history().unwrap().expect("应该产生提交");
+        assert_eq!(workspace.history().status().pending_changes, 0);
+        assert!(workspace.flush_history().unwrap().is_none(), "没有修改时不提交");
+
+        workspace.save_note(&note.path, r#"{"title":"三体","text":"第三版"}"#).expect("保存失败");
+        workspace.flush_history().unwrap().expect("应该产生提交");
+
+        // 按笔记过滤的历史只包含修改过它的提交，说明中带有标题
+        let log = workspace.history_log(Some(&note.path), 10).unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].summary, "更新笔记《三体》");
+        assert!(log[1].message.contains("新建笔记《三体》"));
+        assert_eq!(log[1].id, first);
+        assert_eq!(workspace.history_log(None, 10).unwrap().len(), 3, "包括初始化提交");
+
+        // 查看并恢复旧版本，恢复操作本身也会被提交
+        assert!(workspace.read_note_version(&note.path, &first, None).unwrap().contains("第二版"));
+        workspace.save_draft(&note.path, r#"{"title":"三体","text":"未保存的草稿"}"#).unwrap();
+        workspace.checkout_note_version(&note.path, &first[..7], None).expect("恢复失败");
+        assert!(workspace.read_note(&note.path).unwrap().contains("第二版"));
+        assert!(workspace.recover_draft(&note.path).is_err(), "恢复版本后旧的草稿被清除");
+        assert!(workspace.history_log(Some(&note.path), 1).unwrap()[0].summary.starts_with("恢复笔记《三体》"));
+
+        // 笔记加锁后，旧的明文版本也需要密码才能查看，恢复后仍保持加锁
+        workspace.lock_note(&note.path, "密码").unwrap();
+        workspace.flush_history().unwrap();
+        assert!(workspace.read_note_version(&note.path, &first, None).is_err());
+        assert!(workspace.read_note_version(&note.path, &first, Some("错误密码")).is_err());
+        assert!(workspace.read_note_version(&note.path, &first, Some("密码")).unwrap().contains("第二版"));
+        assert!(workspace.checkout_note_version(&note.path, &first, None).is_err());
+        assert!(workspace.checkout_note_version(&note.path, &first, Some("错误密码")).is_err());
+        workspace.save_note(&note.path, "{}").expect_err("加锁的笔记不能直接保存");
+        workspace.checkout_note_version(&note.path, &first, Some("密码")).expect("恢复失败");
+        assert!(workspace.read_note(&note.path).is_err(), "恢复后应仍然加锁");
+        assert!(workspace.read_locked_note(&note.path, "密码").unwrap().contains("第二版"));
+
+        // 清除历史后旧的明文版本不再保留
+        workspace.purge_history().expect("清除历史失败");
+        assert_eq!(workspace.history_log(None, 10).unwrap().len(), 1);
+        assert!(workspace.read_note_version(&note.path, &first, Some("密码")).is_err());
+        assert!(workspace.read_locked_note(&note.path, "密码").unwrap().contains("第二版"));
+
+        // 重新打开的工作区自动识别已有仓库
+        assert!(Workspace::open(temp_dir.path()).unwrap().history().is_enabled());
+    }
+
+    // 测试目录同步：双向传播新建、修改、删除，冲突时两边都保留两个版本
+    #[test]
+    fn test_folder_sync() {
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let mirror_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let mirror = mirror_dir.path();
+        let category_id = workspace.create_category("工作").expect("创建分类失败");
+        let note = workspace
+            .create_note(&category_id, None, "周报", r#"{"title":"周报","text":"初稿"}"#)
+            .expect("创建笔记失败");
+        assert!(workspace.sync_folder(temp_dir.path().join("工作").to_str().unwrap()).is_err(), "不能同步到工作区内部");
+
+        // 首次同步把工作区复制到目标目录
+        let summary = workspace.sync_folder(mirror.to_str().unwrap()).expect("同步失败");
+        assert!(summary.pushed.contains(&note.path));
+        assert!(summary.conflicts.is_empty());
+        assert_eq!(fs::read(mirror.join(&note.path)).unwrap(), fs::read(temp_dir.path().join(&note.path)).unwrap());
+
+        // 加密参数属于各自的工作区，不会传播到另一边
+        fs::write(mirror.join(".pmn-encryption.json"), "{}").unwrap();
+        let summary = workspace.sync_folder(mirror.to_str().unwrap()).expect("同步失败");
+        assert!(summary.pulled.is_empty());
+        assert!(!temp_dir.path().join(".pmn-encryption.json").exists());
+        fs::remove_file(mirror.join(".pmn-encryption.json")).unwrap();
+
+        // 目标目录中的新建和工作区中的删除分别传播到另一边
+        fs::create_dir_all(mirror.join("工作/会议")).unwrap();
+        fs::write(mirror.join("工作/会议/纪要.json"), r#"{"title":"纪要"}"#).unwrap();
+        let other = workspace.create_note(&category_id, None, "草稿", r#"{"title":"草稿"}"#).unwrap();
+        workspace.sync_folder(mirror.to_str().unwrap()).unwrap();
+        workspace.delete_note(&other.id).unwrap();
+        let summary = workspace.sync_folder(mirror.to_str().unwrap()).expect("同步失败");
+        assert!(temp_dir.path().join("工作/会议/纪要.json").exists());
+        assert_eq!(summary.deleted_in_target, vec![other.path.clone()]);
+        assert!(!mirror.join(&other.path).exists());
+
+        // 没有修改时再次同步不做任何事
+        let summary = workspace.sync_folder(mirror.to_str().unwrap()).unwrap();
+        assert!(summary.pulled.is_empty() && summary.pushed.is_empty());
+
+        // 两边同时修改同一篇笔记
+        workspace.save_note(&note.path, r#"{"title":"周报","text":"本地修改"}"#).unwrap();
+        fs::write(mirror.join(&note.path), r#"{"title":"周报","text":"远端修改"}"#).unwrap();
+        let summary = workspace.sync_folder(mirror.to_str().unwrap()).expect("同步失败");
+        assert_eq!(summary.conflicts.len(), 1);
+        let copy = summary.conflicts[0].conflict_copy.clone().expect("应该生成冲突副本");
+        assert!(copy.starts_with("工作/周报.conflict-") && copy.ends_with(".json"));
+        for root in [temp_dir.path(), mirror] {
+            assert!(fs::read_to_string(root.join(&note.path)).unwrap().contains("本地修改"));
+            assert!(fs::read_to_string(root.join(&copy)).unwrap().contains("远端修改"));
+        }
+
+        // 删除整个子分类目录也会同步
+        fs::remove_dir_all(mirror.join("工作/会议")).unwrap();
+        let summary = workspace.sync_folder(mirror.to_str().unwrap()).unwrap();
+        assert!(summary.deleted_in_workspace.contains(&"工作/会议".to_string()));
+        assert!(!temp_dir.path().join("工作/会议").exists());
+
+        // 任一边的符号链接目录都不能让同步写到两个目录之外
+        #[cfg(unix)]
+        {
+            let outside_dir = tempdir().expect("无法创建临时目录");
+            let other_dir = tempdir().expect("无法创建临时目录");
+            std::os::unix::fs::symlink(outside_dir.path(), other_dir.path().join("工作")).unwrap();
+            assert!(workspace.sync_folder(other_dir.path().to_str().unwrap()).is_err());
+            assert_eq!(fs::read_dir(outside_dir.path()).unwrap().count(), 0, "不应写入目标目录之外");
+
+            let linked_dir = tempdir().expect("无法创建临时目录");
+            fs::create_dir_all(linked_dir.path().join("链接")).unwrap();
+            fs::write(linked_dir.path().join("链接/外部.json"), "{}").unwrap();
+            std::os::unix::fs::symlink(outside_dir.path(), temp_dir.path().join("链接")).unwrap();
+            assert!(workspace.sync_folder(linked_dir.path().to_str().unwrap()).is_err());
+            assert_eq!(fs::read_dir(outside_dir.path()).unwrap().count(), 0, "不应写入工作区之外");
+        }
+    }
+
+    // 测试脑图笔记的三方合并：不重叠的修改自动合并，真正的冲突按节点报告
+    #[test]
+    fn test_merge_note_versions() {
+        use app_lib::merge::{merge_notes, ConflictKind};
+        use serde_json::{json, Value};
+
+        // 编辑器保存的嵌套节点树，节点以 data.uid 标识
+        fn node(uid: &str, text: &str, children: Vec<Value>) -> Value {
+            json!({"data": {"uid": uid, "text": text, "expand": true}, "children": children})
+        }
+        // 按 uid 查找节点
+        fn find<'a>(node: &'a Value, uid: &str) -> Option<&'a Value> {
+            if node["data"]["uid"] == json!(uid) {
+                return Some(node);
+            }
+            node["children"].as_array()?.iter().find_map(|child| find(child, uid))
+        }
+        fn child_uids(node: &Value) -> Vec<&str> {
+            node["children"].as_array().unwrap().iter().map(|child| child["data"]["uid"].as_str().unwrap()).collect()
+        }
+        let merge = |base: &Value, ours: &Value, theirs: &Value| {
+            let outcome = merge_notes(Some(&base.to_string()), &ours.to_string(), &theirs.to_string()).expect("合并失败");
+            let merged: Value = serde_json::from_str(&outcome.merged).unwrap();
+            (merged, outcome.conflicts)
+        };
+
+        let base = json!({
+            "formatVersion": 2, "id": "n", "title": "计划", "lastUpdated": "2024-01-01T00:00:00Z",
+            "data": node("root", "计划", vec![
+                node("a", "阅读", vec![]),
+                node("b", "运动", vec![node("b1", "跑步", vec![])]),
+                node("c", "旅行", vec![]),
+            ])
+        });
+        // 我方：修改 a 的文字，在 b 之后新增 d，删除 c
+        let ours = json!({
+            "formatVersion": 2, "id": "n", "title": "计划", "lastUpdated": "2024-01-02T00:00:00Z",
+            "data": node("root", "计划", vec![
+                node("a", "阅读三本书", vec![]),
+                node("b", "运动", vec![node("b1", "跑步", vec![])]),
+                node("d", "学习", vec![]),
+            ])
+        });
+        // 对方：给 a 加标签，在 b 下新增 b2，修改标题
+        let mut theirs = base.clone();
+        theirs["title"] = json!("年度计划");
+        theirs["data"]["children"][0]["data"]["tag"] = json!(["重要"]);
+        theirs["data"]["children"][1]["children"].as_array_mut().unwrap().push(node("b2", "游泳", vec![]));
+
+        let (merged, conflicts) = merge(&base, &ours, &theirs);
+        assert!(conflicts.is_empty(), "不重叠的修改不应冲突: {:?}", conflicts);
+        assert_eq!(merged["title"], "年度计划");
+        assert_eq!(merged["lastUpdated"], "2024-01-02T00:00:00Z");
+        let root = &merged["data"];
+        assert_eq!(root["data"]["uid"], "root", "合并结果仍是编辑器的嵌套节点树");
+        assert_eq!(child_uids(root), ["a", "b", "d"], "节点保持原来的父节点和位置");
+        assert_eq!(child_uids(&root["children"][1]), ["b1", "b2"]);
+        assert_eq!(root["children"][0]["data"], json!({"uid": "a", "text": "阅读三本书", "expand": true, "tag": ["重要"]}));
+        assert!(find(root, "c").is_none());
+        assert_eq!(root["children"][2]["children"], json!([]));
+
+        // 同一字段的不同修改、删除与修改冲突、移动到不同父节点
+        let ours = json!({
+            "formatVersion": 2, "id": "n", "title": "计划", "lastUpdated": "2024-01-01T00:00:00Z",
+            "data": node("root", "计划", vec![
+                node("a", "我方文字", vec![node("b1", "跑步", vec![])]),
+                node("b", "运动", vec![]),
+            ])
+        });
+        let theirs = json!({
+            "formatVersion": 2, "id": "n", "title": "计划", "lastUpdated": "2024-01-01T00:00:00Z",
+            "data": node("root", "计划", vec![
+                node("a", "对方文字", vec![]),
+                node("b", "运动", vec![]),
+                node("c", "出国旅行", vec![node("b1", "跑步", vec![])]),
+            ])
+        });
+
+        let (merged, conflicts) = merge(&base, &ours, &theirs);
+        let kind_of = |node: &str| conflicts.iter().find(|c| c.node_id.as_deref() == Some(node)).map(|c| c.kind);
+        let root = &merged["data"];
+        assert_eq!(kind_of("a"), Some(ConflictKind::Field));
+        assert_eq!(find(root, "a").unwrap()["data"]["text"], "我方文字", "字段冲突保留我方");
+        assert_eq!(kind_of("c"), Some(ConflictKind::DeleteModify));
+        assert_eq!(find(root, "c").unwrap()["data"]["text"], "出国旅行", "被修改的节点不会被删除");
+        assert_eq!(kind_of("b1"), Some(ConflictKind::Move));
+        assert_eq!(child_uids(find(root, "a").unwrap()), ["b1"], "移动冲突保留我方位置");
+        assert_eq!(find(root, "c").unwrap()["children"], json!([]));
+        // c 被我方从根节点下移除，合并后重新挂回根节点
+        assert_eq!(child_uids(root), ["a", "b", "c"]);
+
+        // 旧格式的节点表先迁移为嵌套树再合并，旧的节点ID即 uid
+        let flat = json!({
+            "formatVersion": 1, "id": "n", "title": "计划", "rootId": "root", "lastUpdated": "2024-01-01T00:00:00Z",
+            "data": {
+                "root": {"data": {"id": "root", "text": "计划"}, "children": ["a"]},
+                "a": {"data": {"id": "a", "text": "阅读"}}
+            }
+        });
+        let ours = json!({
+            "formatVersion": 2, "id": "n", "title": "计划", "lastUpdated": "2024-01-01T00:00:00Z",
+            "data": {"data": {"uid": "root", "text": "计划"}, "children": [
+                {"data": {"uid": "a", "text": "阅读"}, "children": []},
+                {"data": {"uid": "e", "text": "新增"}, "children": []}
+            ]}
+        });
+        let (merged, conflicts) = merge(&flat, &ours, &flat);
+        assert!(conflicts.is_empty(), "{:?}", conflicts);
+        assert!(merged.get("rootId").is_none());
+        assert_eq!(child_uids(&merged["data"]), ["a", "e"]);
+
+        assert!(merge_notes(None, "不是JSON", "{}").is_err());
+    }
+
+    // 测试备份：打包校验、按保留策略清理、恢复到新目录和原地恢复
+    #[test]
+    fn test_backups() {
+        use app_lib::backup::{retained_backups, BackupSettings};
+        use app_lib::workspace::Workspace;
+        use chrono::{Duration, Local, TimeZone};
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let backup_dir = tempdir().expect("无法创建临时目录");
+        let restore_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path().join("我的笔记")).expect("无法打开工作区");
+        let category_id = workspace.create_category("生活").expect("创建分类失败");
+        let note = workspace
+            .create_note(&category_id, None, "清单", r#"{"title":"清单","text":"原始内容"}"#)
+            .expect("创建笔记失败");
+
+        assert!(workspace.create_backup().is_err(), "未设置备份目录时不能备份");
+        let inside = workspace.root().join("backups");
+        let settings = BackupSettings { folder: Some(inside.to_string_lossy().to_string()), ..Default::default() };
+        assert!(workspace.update_backup_settings(settings).is_err(), "备份目录不能位于工作区内");
+        let settings = BackupSettings {
+            enabled: true,
+            folder: Some(backup_dir.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        workspace.update_backup_settings(settings).expect("保存备份设置失败");
+
+        // 首次自动备份立即执行，之后在间隔内不再备份
+        let backup = workspace.run_scheduled_backup().unwrap().expect("应该执行自动备份");
+        assert!(backup.file_name.ends_with(".zip"));
+        assert!(workspace.run_scheduled_backup().unwrap().is_none());
+        assert_eq!(workspace.list_backups().unwrap().len(), 1);
+
+        // 恢复到新目录
+        let target = restore_dir.path().join("恢复");
+        workspace.restore_backup(&backup.file_name, Some(target.to_str().unwrap())).expect("恢复失败");
+        assert!(fs::read_to_string(target.join(&note.path)).unwrap().contains("原始内容"));
+        assert!(workspace.restore_backup(&backup.file_name, Some(target.to_str().unwrap())).is_err(), "目标必须为空");
+        let inside = workspace.root().join("恢复");
+        assert!(workspace.restore_backup(&backup.file_name, Some(inside.to_str().unwrap())).is_err(), "不能恢复到工作区内");
+        assert!(!inside.exists());
+
+        // 原地恢复：修改和新建的内容回到备份时的状态，恢复前的状态也被备份
+        workspace.save_note(&note.path, r#"{"title":"清单","text":"修改后"}"#).unwrap();
+        let extra = workspace.create_note(&category_id, None, "新笔记", r#"{"title":"新笔记"}"#).unwrap();
+        workspace.restore_backup(&backup.file_name, None).expect("原地恢复失败");
+        assert!(workspace.read_note(&note.path).unwrap().contains("原始内容"));
+        assert!(!workspace.root().join(&extra.path).exists());
+        assert_eq!(workspace.list_backups().unwrap().len(), 2);
+        assert!(workspace.backup_settings().enabled, "备份设置不受恢复影响");
+
+        // 保留策略：每天、每周、每月各保留最新的一个
+        let settings = BackupSettings { keep_daily: 3, keep_weekly: 2, keep_monthly: 2, ..Default::default() };
+        let newest = Local.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap();
+        let times: Vec<_> = (0..60).map(|hours| newest - Duration::hours(hours * 12)).collect();
+        let kept: Vec<_> = times.iter().zip(retained_backups(&times, &settings))
+            .filter(|(_, keep)| *keep)
+            .map(|(time, _)| time.format("%m-%d %H").to_string())
+            .collect();
+        assert_eq!(kept, vec!["03-20 12", "03-19 12", "03-18 12", "03-17 12", "02-29 12"]);
+
+        // 旧备份按策略清理
+        let name = backup.file_name.split('_').take(2).collect::<Vec<_>>().join("_");
+        for days in 1..=20 {
+            let time = Local::now() - Duration::days(days * 40);
+            fs::write(backup_dir.path().join(format!("{}_{}.zip", name, time.format("%Y%m%d-%H%M%S-%3f"))), b"").unwrap();
+        }
+        let newest = workspace.list_backups().unwrap()[0].file_name.clone();
+        let removed = workspace.prune_backups().unwrap();
+        assert!(removed.contains(&backup.file_name), "同一天只保留最新的备份");
+        let remaining = workspace.list_backups().unwrap();
+        assert_eq!(remaining[0].file_name, newest);
+        assert!(remaining.len() <= 12, "按月最多保留 12 个");
+    }
+
+    // 测试 .pmn 归档：按范围导出（含附件和元数据），导入时处理重名
+    #[test]
+    fn test_archive_export_import() {
+        use app_lib::archive::{ArchiveSelection, CollisionStrategy};
+        use app_lib::metadata::CategoryMetadataUpdate;
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let other_dir = tempdir().expect("无法创建临时目录");
+        let export_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category_id = workspace.create_category("项目").expect("创建分类失败");
+        workspace.create_category("私人").expect("创建分类失败");
+        let update = CategoryMetadataUpdate { color: Some("#ff0000".to_string()), ..Default::default() };
+        workspace.update_category_metadata(&category_id, None, update).unwrap();
+        let note = workspace
+            .create_note(&category_id, None, "方案", r#"{"title":"方案","text":"内容"}"#)
+            .expect("创建笔记失败");
+        fs::create_dir_all(temp_dir.path().join("项目/方案.assets")).unwrap();
+        fs::write(temp_dir.path().join("项目/方案.assets/图.png"), b"png").unwrap();
+
+        // 只导出一篇笔记：带上附件和分类元数据
+        let selection = ArchiveSelection { notes: vec![note.path.clone()], ..Default::default() };
+        let archive_path = export_dir.path().join("分享");
+        let manifest = workspace.export_archive(archive_path.to_str().unwrap(), &selection).expect("导出失败");
+        let archive_file = export_dir.path().join("分享.pmn");
+        assert!(archive_file.exists());
+        assert_eq!(manifest.notes.len(), 1);
+        assert_eq!(manifest.notes[0].title, "方案");
+        let paths: Vec<&str> = manifest.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["项目/_category.json", "项目/方案.assets/图.png", "项目/方案.json"]);
+
+        // 导入到另一个工作区
+        let other = Workspace::open(other_dir.path()).expect("无法打开工作区");
+        let summary = other.import_archive(archive_file.to_str().unwrap(), CollisionStrategy::Rename).expect("导入失败");
+        assert_eq!(summary.imported.len(), 3);
+        assert_eq!(other.read_note(&note.path).unwrap(), workspace.read_note(&note.path).unwrap());
+        let categories = other.get_all_categories(Default::default()).unwrap();
+        assert_eq!(categories[0].color.as_deref(), Some("#ff0000"));
+        assert!(categories[0].sub_categories.is_empty(), "附件目录不是子分类");
+
+        // 再次导入：笔记和附件重命名，笔记ID随之更新
+        let summary = other.import_archive(archive_file.to_str().unwrap(), CollisionStrategy::Rename).expect("导入失败");
+        assert_eq!(summary.skipped, vec!["项目/_category.json"]);
+        assert_eq!(summary.renamed.len(), 1);
+        assert_eq!(summary.renamed[0].to, "项目/方案 (2).json");
+        assert!(other_dir.path().join("项目/方案 (2).assets/图.png").exists());
+        let renamed = other.read_note("项目/方案 (2).json").unwrap();
+        assert!(renamed.contains("项目##方案 (2)"));
+
+        // 跳过与覆盖
+        let summary = other.import_archive(archive_file.to_str().unwrap(), CollisionStrategy::Skip).unwrap();
+        assert!(summary.imported.is_empty());
+        let summary = other.import_archive(archive_file.to_str().unwrap(), CollisionStrategy::Overwrite).unwrap();
+        assert_eq!(summary.overwritten.len(), 3);
+
+        // 整个工作区导出包含所有分类
+        let manifest = workspace.export_archive(export_dir.path().join("全部.pmn").to_str().unwrap(), &ArchiveSelection::default()).unwrap();
+        assert!(manifest.files.iter().any(|file| file.path == "私人/_category.json"));
+
+        // 不是归档的文件
+        fs::write(export_dir.path().join("坏.pmn"), b"not a zip").unwrap();
+        assert!(other.import_archive(export_dir.path().join("坏.pmn").to_str().unwrap(), CollisionStrategy::Rename).is_err());
+
+        // 清单中的文件不能写入 .git、模板等隐藏目录或根目录下的设置文件
+        for entry in [".git/hooks/post-commit", ".pmn-templates/模板.json", "项目/.pmn-x/a.json", ".pmn-encryption.json"] {
+            use std::io::Write;
+            let manifest = json!({
+                "format": "pure-mind-note-archive",
+                "version": 1,
+                "appVersion": "0.0.0",
+                "created": "2024-01-01T00:00:00+08:00",
+                "notes": [],
+                "files": [{ "path": entry, "size": 2 }],
+            });
+            let malicious = export_dir.path().join("恶意.pmn");
+            let mut writer = zip::ZipWriter::new(fs::File::create(&malicious).unwrap());
+            let options = zip::write::SimpleFileOptions::default();
+            writer.start_file(format!("content/{}", entry), options).unwrap();
+            writer.write_all(b"{}").unwrap();
+            writer.start_file("manifest.json", options).unwrap();
+            writer.write_all(manifest.to_string().as_bytes()).unwrap();
+            writer.finish().unwrap();
+
+            assert!(other.import_archive(malicious.to_str().unwrap(), CollisionStrategy::Overwrite).is_err(), "{}", entry);
+            assert!(!other_dir.path().join(entry).exists(), "{}", entry);
+        }
+    }
+
+    // 测试工作区列表的登记、切换与持久化
+    #[test]
+    fn test_workspace_registry() {
+        use app_lib::registry::WorkspaceRegistry;
+        use app_lib::workspace::WorkspaceState;
+        use serde_json::Map;
+
+        let config_dir = tempdir().expect("无法创建临时目录");
+        let first_dir = tempdir().expect("无法创建临时目录");
+        let second_dir = tempdir().expect("无法创建临时目录");
+        let registry_file = config_dir.path().join("config/workspaces.json");
+        let registry = WorkspaceRegistry::load(&registry_file);
+        assert!(registry.list().is_empty());
+
+        let first = registry.add(Some("工作"), first_dir.path().to_str().unwrap()).expect("登记失败");
+        let second = registry.add(None, second_dir.path().to_str().unwrap()).expect("登记失败");
+        assert_ne!(first.id, second.id);
+        assert_eq!(second.name, second_dir.path().file_name().unwrap().to_string_lossy());
+        // 同一目录不能重复登记
+        assert!(registry.add(Some("重复"), first_dir.path().to_str().unwrap()).is_err());
+
+        // 切换工作区：旧工作区被关闭，新工作区成为当前工作区
+        let state = WorkspaceState::default();
+        registry.switch(&state, &first.id).expect("切换失败");
+        let note_category = state.current().unwrap().create_category("项目").unwrap();
+        assert_eq!(note_category, "项目");
+        let switched = registry.switch(&state, &second.id).expect("切换失败");
+        assert!(switched.last_opened.is_some());
+        assert_eq!(state.current().unwrap().root(), fs::canonicalize(second_dir.path()).unwrap());
+        assert!(state.current().unwrap().get_all_categories(app_lib::ordering::SortMode::Created).unwrap().is_empty());
+        assert_eq!(registry.list()[0].id, second.id);
+
+        // 设置按键合并，null 删除键
+        let mut settings = Map::new();
+        settings.insert("theme".to_string(), json!("dark"));
+        settings.insert("sort".to_string(), json!("name"));
+        registry.update_settings(&second.id, settings).unwrap();
+        let mut settings = Map::new();
+        settings.insert("sort".to_string(), serde_json::Value::Null);
+        let updated = registry.update_settings(&second.id, settings).unwrap();
+        assert_eq!(updated.settings.get("theme"), Some(&json!("dark")));
+        assert!(!updated.settings.contains_key("sort"));
+
+        // 重新加载后列表、设置和当前工作区都保留
+        let reloaded = WorkspaceRegistry::load(&registry_file);
+        assert_eq!(reloaded.list().len(), 2);
+        assert_eq!(reloaded.active().unwrap().id, second.id);
+        assert_eq!(reloaded.get(&second.id).unwrap().settings.get("theme"), Some(&json!("dark")));
+
+        // 移除只影响列表，不删除文件
+        reloaded.remove(&first.id).unwrap();
+        assert_eq!(reloaded.list().len(), 1);
+        assert!(first_dir.path().join("项目").is_dir());
+        assert!(reloaded.remove(&first.id).is_err());
+    }
+
+    // 测试文件监视和笔记索引：只通知当前工作区中来自应用之外的修改，索引随之失效
+    #[test]
+    fn test_workspace_watcher() {
+        use app_lib::workspace::{Workspace, WorkspaceState};
+        use std::sync::{mpsc, Arc, Mutex};
+        use std::time::Duration;
+
+        let first_dir = tempdir().expect("无法创建临时目录");
+        let second_dir = tempdir().expect("无法创建临时目录");
+        let (sender, changes) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let state = WorkspaceState::default();
+        state.watch_changes(Arc::new(move |path| {
+            let _ = sender.lock().unwrap().send(path);
+        }));
+
+        let first = state.set(Workspace::open(first_dir.path()).unwrap()).unwrap();
+        let category = first.create_category("监视").unwrap();
+        let content = |text: &str| json!({"title": "笔记", "data": {"data": {"uid": "root", "text": text}, "children": []}}).to_string();
+        let note = first.create_note(&category, None, "笔记", &content("原来的文字")).unwrap();
+        assert_eq!(first.search_notes("原来").unwrap().len(), 1);
+        // 应用本身的写入不通知
+        first.save_note(&note.path, &content("应用写入")).unwrap();
+        assert!(changes.recv_timeout(Duration::from_millis(800)).is_err());
+
+        // 外部修改：发出通知，搜索读到新内容
+        fs::write(first_dir.path().join(&note.path), content("外部修改")).unwrap();
+        assert_eq!(changes.recv_timeout(Duration::from_secs(5)).expect("没有收到修改通知"), note.path);
+        assert_eq!(first.search_notes("外部").unwrap().len(), 1);
+        assert!(first.search_notes("应用写入").unwrap().is_empty());
+        // 隐藏目录中的文件不通知
+        fs::create_dir_all(first_dir.path().join(".pmn-drafts")).unwrap();
+        fs::write(first_dir.path().join(".pmn-drafts/x.json"), "{}").unwrap();
+        assert!(changes.recv_timeout(Duration::from_millis(800)).is_err());
+
+        // 切换后旧工作区不再监视，新工作区开始监视
+        // 监视开始后新建的目录要等监视器加入后才能收到其中的事件，这里先建好分类
+        let second = Workspace::open(second_dir.path()).unwrap();
+        let category = second.create_category("第二").unwrap();
+        state.set(second).unwrap();
+        fs::write(first_dir.path().join(&note.path), content("切换之后的修改")).unwrap();
+        assert!(changes.recv_timeout(Duration::from_millis(800)).is_err());
+        assert_eq!(first.search_notes("切换之后").unwrap().len(), 1, "不再监视时索引仍按文件的修改时间和大小校验");
+        fs::write(second_dir.path().join(&category).join("外部.json"), content("新建")).unwrap();
+        assert_eq!(changes.recv_timeout(Duration::from_secs(5)).expect("没有收到修改通知"), format!("{}/外部.json", category));
+    }
+
+    // 测试应用配置的默认值、迁移、合并更新与持久化
+    #[test]
+    fn test_app_config() {
+        use app_lib::config::{migrate_config, ConfigState, CONFIG_VERSION};
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let config_file = temp_dir.path().join("config/app-config.json");
+
+        // 旧版前端写入的配置：没有版本号
+        let legacy_file = temp_dir.path().join("legacy.json");
+        fs::write(&legacy_file, r#"{"workspacePath":"/notes","theme":{"mode":"dark","fontSize":"large"},"_testTimestamp":1}"#).unwrap();
+        let state = ConfigState::load(&config_file, Some(&legacy_file));
+        let config = state.get();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.workspace_path, "/notes");
+        assert_eq!(config.theme.mode, "dark");
+        assert_eq!(config.locale, "zh-CN");
+        assert!(!config.extra.contains_key("_testTimestamp"));
+        // 迁移后的配置已写入新位置
+        let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&config_file).unwrap()).unwrap();
+        assert_eq!(saved["version"], json!(CONFIG_VERSION));
+
+        // 存储测试写入的占位工作区路径被清除，已是当前版本的配置不再迁移
+        let migrated = migrate_config(json!({"workspacePath": "__test__"})).unwrap();
+        assert_eq!(migrated.workspace_path, "");
+        let current = migrate_config(json!({"version": CONFIG_VERSION, "workspacePath": "__test__"})).unwrap();
+        assert_eq!(current.workspace_path, "__test__");
+        assert!(migrate_config(json!([1, 2])).is_err());
+
+        // 部分更新：嵌套对象逐键合并，未知字段原样保留
+        let updated = state.update(json!({"theme": {"fontSize": "small"}, "sync": {"folder": "/backup"}, "customFlag": true})).unwrap();
+        assert_eq!(updated.theme.mode, "dark");
+        assert_eq!(updated.theme.font_size, "small");
+        assert_eq!(updated.extra.get("customFlag"), Some(&json!(true)));
+        // 非法的值不会被保存
+        assert!(state.update(json!({"theme": {"mode": "neon"}})).is_err());
+        assert_eq!(state.get().theme.mode, "dark");
+        // 版本号由后端维护
+        state.update(json!({"version": 99})).unwrap();
+        assert_eq!(state.get().version, CONFIG_VERSION);
+
+        // 重新加载时不再读取旧文件
+        fs::write(&legacy_file, r#"{"workspacePath":"/other"}"#).unwrap();
+        let reloaded = ConfigState::load(&config_file, Some(&legacy_file));
+        assert_eq!(reloaded.get(), state.get());
+
+        // 损坏的配置文件回退到默认值并保留副本
+        fs::write(&config_file, "{ 损坏").unwrap();
+        let recovered = ConfigState::load(&config_file, None);
+        assert_eq!(recovered.get().workspace_path, "");
+        assert!(temp_dir.path().join("config/app-config.json.corrupt").exists());
+    }
+
+    // 测试笔记格式版本：各历史版本的样例文件都能迁移到当前版本
+    #[test]
+    fn test_note_format_migrations() {
+        use app_lib::note_format::{format_version, migrate_note, upgrade_content, NOTE_FORMAT_VERSION};
+        use app_lib::workspace::Workspace;
+
+        let fixtures = [
+            ("v0-tag-string.json", include_str!("../tests/fixtures/notes/v0-tag-string.json")),
+            ("v0-tags-array.json", include_str!("../tests/fixtures/notes/v0-tags-array.json")),
+            ("v1.json", include_str!("../tests/fixtures/notes/v1.json")),
+            ("v0-tag-objects.json", include_str!("../tests/fixtures/notes/v0-tag-objects.json")),
+            ("v0-editor.json", include_str!("../tests/fixtures/notes/v0-editor.json")),
+            ("v2-editor.json", include_str!("../tests/fixtures/notes/v2-editor.json")),
+        ];
+        let migrated = |content: &str| {
+            let mut note: serde_json::Value = serde_json::from_str(content).unwrap();
+            migrate_note(&mut note).unwrap();
+            note
+        };
+
+        // 版本 0：单个字符串的 tag 和 tags 数组都统一为 tag 数组
+        let mut note: serde_json::Value = serde_json::from_str(fixtures[0].1).unwrap();
+        assert_eq!(migrate_note(&mut note).unwrap(), 0);
+        assert_eq!(format_version(&note), NOTE_FORMAT_VERSION);
+        assert_eq!(note["data"]["children"][0]["data"]["tag"], json!(["todo"]));
+        assert!(note["data"]["children"][1]["data"].get("tag").is_none());
+        let note = migrated(fixtures[1].1);
+        assert_eq!(note["data"]["data"]["tag"], json!(["project"]));
+        assert_eq!(note["data"]["children"][0]["data"]["tag"], json!(["progress", "question"]));
+        assert!(note["data"]["children"][0]["data"].get("tags").is_none());
+        // 对象形式的标签取出 text 或 value，没有文字的丢弃
+        let note = migrated(fixtures[3].1);
+        assert_eq!(note["data"]["data"]["tag"], json!(["重要"]));
+        assert_eq!(note["data"]["children"][0]["data"]["tag"], json!(["bug"]));
+        // 编辑器保存的嵌套树中的标签同样统一
+        let note = migrated(fixtures[4].1);
+        assert_eq!(note["data"]["children"][0]["data"]["tag"], json!(["todo"]));
+        assert_eq!(note["data"]["children"][0]["data"]["uid"], json!("8a4e2f10-6b7c-4d8e-a9f0-b1c2d3e4f5a6"));
+        assert!(note.get("rootId").is_none());
+
+        // 版本 1：节点表转为编辑器的嵌套树，原来的节点ID作为 uid
+        let mut note: serde_json::Value = serde_json::from_str(fixtures[2].1).unwrap();
+        assert_eq!(migrate_note(&mut note).unwrap(), 1);
+        assert_eq!(note["data"], json!({
+            "data": {"uid": "root", "text": "计划"},
+            "children": [{"data": {"uid": "q", "text": "预算？", "tag": ["question"]}, "children": []}]
+        }));
+        assert!(note.get("rootId").is_none());
+        // 不存在的子节点被去掉，无法到达的节点挂到根节点下
+        let note = migrated(&json!({
+            "formatVersion": 1, "title": "残缺", "rootId": "root",
+            "data": {
+                "root": {"data": {"id": "root", "text": "残缺"}, "children": ["gone", "a"]},
+                "a": {"data": {"id": "a", "text": "甲"}, "children": ["root"]},
+                "orphan": {"data": {"id": "orphan", "text": "孤立"}}
+            }
+        }).to_string());
+        let ids: Vec<&str> = note["data"]["children"].as_array().unwrap().iter().map(|child| child["data"]["uid"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["a", "orphan"]);
+        assert_eq!(note["data"]["children"][0]["children"], json!([]));
+        // 根节点不存在时以标题作为根节点，保留所有节点
+        let note = migrated(&json!({"formatVersion": 1, "title": "无根", "rootId": "root", "data": {"x": {"data": {"id": "x", "text": "乙"}}}}).to_string());
+        assert_eq!(note["data"]["data"]["text"], json!("无根"));
+        assert_eq!(note["data"]["children"][0]["data"]["uid"], json!("x"));
+
+        // 当前版本不改动内容；迁移对已是新格式的内容没有副作用
+        assert_eq!(upgrade_content(fixtures[5].1).unwrap(), None);
+        let mut current: serde_json::Value = serde_json::from_str(fixtures[5].1).unwrap();
+        let expected = current.clone();
+        current.as_object_mut().unwrap().remove("formatVersion");
+        migrate_note(&mut current).unwrap();
+        assert_eq!(current, expected);
+
+        // 新版本应用写入的笔记不会被降级
+        let newer = json!({"formatVersion": NOTE_FORMAT_VERSION + 1, "data": {}}).to_string();
+        assert!(upgrade_content(&newer).is_err());
+
+        // 工作区批量迁移：先预览，再写回
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category = workspace.create_category("样例").unwrap();
+        for (name, content) in fixtures {
+            fs::write(temp_dir.path().join(&category).join(name), content).unwrap();
+        }
+        fs::write(temp_dir.path().join(&category).join("newer.json"), &newer).unwrap();
+
+        // 读取时在内存中升级，文件保持不变
+        let read = workspace.read_note("样例/v0-tag-string.json").unwrap();
+        assert_eq!(format_version(&serde_json::from_str(&read).unwrap()), NOTE_FORMAT_VERSION);
+        assert_eq!(fs::read_to_string(temp_dir.path().join("样例/v0-tag-string.json")).unwrap(), fixtures[0].1);
+
+        let preview = workspace.migrate_workspace(true).expect("预览迁移失败");
+        assert_eq!(preview.migrated.len(), 5);
+        assert_eq!(preview.up_to_date, 1);
+        assert_eq!(preview.skipped.len(), 1);
+        assert_eq!(preview.skipped[0].path, "样例/newer.json");
+        assert_eq!(fs::read_to_string(temp_dir.path().join("样例/v0-tag-string.json")).unwrap(), fixtures[0].1);
+
+        let report = workspace.migrate_workspace(false).expect("迁移失败");
+        assert_eq!(report.migrated.len(), 5);
+        assert_eq!(report.migrated.iter().filter(|note| note.from_version == 0).count(), 4);
+        let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(temp_dir.path().join("样例/v0-tags-array.json")).unwrap()).unwrap();
+        assert_eq!(format_version(&saved), NOTE_FORMAT_VERSION);
+        assert!(workspace.migrate_workspace(true).unwrap().migrated.is_empty());
+
+        // 保存时补上版本号，新版本的内容不能保存
+        workspace.save_note("样例/v1.json", r#"{"title":"计划","data":{}}"#).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&workspace.read_note("样例/v1.json").unwrap()).unwrap();
+        assert_eq!(format_version(&saved), NOTE_FORMAT_VERSION);
+        assert!(workspace.save_note("样例/v1.json", &newer).is_err());
+    }
+
+    // 测试工作区完整性检查与修复
+    #[test]
+    fn test_check_and_repair_workspace() {
+        use app_lib::check::IssueKind;
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category = workspace.create_category("检查").unwrap();
+        let dir = temp_dir.path().join(&category);
+
+        let healthy = json!({
+            "formatVersion": 1, "id": "检查##正常", "title": "正常", "rootId": "root",
+            "data": {"root": {"data": {"id": "root", "text": "正常"}, "children": ["a"]}, "a": {"data": {"id": "a", "text": "A"}}}
+        });
+        fs::write(dir.join("正常.json"), healthy.to_string()).unwrap();
+        // 悬空的子节点引用、不可达的子树和一个环
+        let broken = json!({
+            "formatVersion": 1, "id": "检查##正常", "title": "损坏", "rootId": "root",
+            "data": {
+                "root": {"data": {"id": "root", "text": "损坏"}, "children": ["a", "missing"]},
+                "a": {"data": {"id": "a", "text": "A"}},
+                "lost": {"data": {"id": "lost", "text": "丢失"}, "children": ["lost-child"]},
+                "lost-child": {"data": {"id": "lost-child", "text": "丢失的子节点"}},
+                "x": {"data": {"id": "x", "text": "X"}, "children": ["y"]},
+                "y": {"data": {"id": "y", "text": "Y"}, "children": ["x"]}
+            }
+        });
+        fs::write(dir.join("损坏.json"), broken.to_string()).unwrap();
+        fs::write(dir.join("乱码.json"), "{ 不是JSON").unwrap();
+        fs::write(dir.join("正常.json.pmn-tmp"), "半个文件").unwrap();
+        // 编辑器保存的嵌套节点树：正常的不报告问题，损坏的报告重复ID、缺少ID和不是节点的子项
+        let editor = json!({
+            "id": "检查##编辑器", "title": "编辑器",
+            "data": {"data": {"text": "编辑器", "uid": "r"}, "children": [
+                {"data": {"text": "A", "uid": "a"}, "children": [{"data": {"text": "A1", "uid": "a1"}, "children": []}]},
+                {"data": {"text": "B", "uid": "b"}}
+            ]}
+        });
+        fs::write(dir.join("编辑器.json"), editor.to_string()).unwrap();
+        let nested = json!({
+            "id": "检查##嵌套", "title": "嵌套",
+            "data": {"data": {"text": "嵌套", "uid": "r"}, "children": [
+                {"data": {"text": "A", "uid": "a"}, "children": [{"data": {"text": "重复", "uid": "a"}, "children": []}]},
+                {"data": {"text": "无ID"}, "children": "坏"},
+                "不是节点",
+                {"children": [{"data": {"text": "孤儿", "uid": "c"}, "children": []}]}
+            ]}
+        });
+        fs::write(dir.join("嵌套.json"), nested.to_string()).unwrap();
+
+        let report = workspace.check_workspace(false).expect("检查失败");
+        assert_eq!(report.notes_checked, 5);
+        assert!(report.issues.iter().all(|issue| issue.path != format!("{}/编辑器.json", category)), "{:?}", report.issues);
+        let count = |kind: IssueKind| report.issues.iter().filter(|issue| issue.kind == kind).count();
+        assert_eq!(count(IssueKind::MissingRoot), 0);
+        assert_eq!(count(IssueKind::DuplicateNodeId), 1);
+        assert_eq!(count(IssueKind::MissingNodeId), 1);
+        assert_eq!(count(IssueKind::InvalidNode), 3, "children 不是数组、子项不是对象、子项缺少节点数据");
+        assert_eq!(count(IssueKind::InvalidJson), 1);
+        assert_eq!(count(IssueKind::DanglingChild), 1);
+        assert_eq!(count(IssueKind::UnreachableNode), 2, "不可达的子树和环各报告一次");
+        assert_eq!(count(IssueKind::DuplicateNoteId), 1, "只报告ID与位置不符的笔记");
+        assert_eq!(count(IssueKind::StrayTempFile), 1);
+        assert!(report.issues.iter().all(|issue| !issue.repaired));
+        // 只检查时不修改任何文件
+        assert!(dir.join("正常.json.pmn-tmp").exists());
+        assert!(dir.join("乱码.json").exists());
+
+        let report = workspace.check_workspace(true).expect("修复失败");
+        assert!(report.issues.iter().all(|issue| issue.repaired), "这些问题都可以安全修复");
+        assert!(!dir.join("正常.json.pmn-tmp").exists());
+        assert!(!dir.join("乱码.json").exists());
+        assert_eq!(fs::read_to_string(dir.join("乱码.json.corrupt")).unwrap(), "{ 不是JSON");
+
+        let repaired: serde_json::Value = serde_json::from_str(&fs::read_to_string(dir.join("损坏.json")).unwrap()).unwrap();
+        let children = repaired["data"]["root"]["children"].as_array().unwrap();
+        assert!(children.contains(&json!("lost")));
+        assert!(!children.contains(&json!("missing")));
+        assert_eq!(repaired["id"], json!("检查##损坏"));
+        let repaired: serde_json::Value = serde_json::from_str(&fs::read_to_string(dir.join("嵌套.json")).unwrap()).unwrap();
+        let children = repaired["data"]["children"].as_array().unwrap();
+        assert_eq!(children.len(), 3, "不是对象的子项被去掉");
+        assert_eq!(children[0]["children"][0]["data"]["text"], json!("重复"));
+        assert_ne!(children[0]["children"][0]["data"]["uid"], json!("a"), "重复的节点ID被替换");
+        assert!(children[1]["data"]["uid"].as_str().is_some_and(|uid| !uid.is_empty()));
+        assert_eq!(children[1]["children"], json!([]));
+        assert_eq!(children[2]["children"][0]["data"]["text"], json!("孤儿"), "缺少节点数据时保留子树");
+        // 修复后再检查没有问题
+        let report = workspace.check_workspace(false).unwrap();
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+        assert_eq!(report.notes_checked, 4);
+    }
+
+    // 测试草稿日志：崩溃后可以恢复比已保存内容更新的编辑
+    #[test]
+    fn test_draft_journal() {
+        use app_lib::drafts::DRAFTS_DIR;
+        use app_lib::ordering::SortMode;
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        use app_lib::workspace::Workspace;
+        use std::io::Write;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category = workspace.create_category("草稿").unwrap();
+        let note = workspace.create_note(&category, None, "日记", r#"{"title":"日记","text":"已保存"}"#).unwrap();
+        assert!(workspace.list_recoverable_drafts().unwrap().is_empty());
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        workspace.save_draft(&note.path, r#"{"title":"日记","text":"第一次编辑"}"#).unwrap();
+        workspace.save_draft(&note.path, r#"{"title":"日记","text":"第二次编辑"}"#).unwrap();
+        // 写了一半的最后一行被忽略
+        let journal = fs::read_dir(temp_dir.path().join(DRAFTS_DIR)).unwrap().next().unwrap().unwrap().path();
+        fs::OpenOptions::new().append(true).open(&journal).unwrap().write_all(b"eyJwYXRo").unwrap();
+
+        // 草稿目录不会被当作分类
+        let categories = workspace.get_all_categories(SortMode::Created).unwrap();
+        assert_eq!(categories.len(), 1);
+
+        let drafts = workspace.list_recoverable_drafts().unwrap();
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].path, note.path);
+        assert_eq!(drafts[0].title.as_deref(), Some("日记"));
+
+        let recovered = workspace.recover_draft(&note.path).expect("恢复草稿失败");
+        assert!(recovered.contains("第二次编辑"));
+        assert!(workspace.read_note(&note.path).unwrap().contains("第二次编辑"));
+        assert!(workspace.list_recoverable_drafts().unwrap().is_empty());
+        assert!(workspace.recover_draft(&note.path).is_err());
+
+        // 正常保存后草稿被清除
+        workspace.save_draft(&note.path, r#"{"title":"日记","text":"未保存"}"#).unwrap();
+        workspace.save_note(&note.path, r#"{"title":"日记","text":"已保存"}"#).unwrap();
+        assert!(workspace.list_recoverable_drafts().unwrap().is_empty());
+
+        // 笔记比草稿更新时不提示恢复
+        workspace.save_draft(&note.path, r#"{"title":"日记","text":"旧草稿"}"#).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let note_path = temp_dir.path().join(&note.path);
+        fs::write(&note_path, r#"{"title":"日记","text":"外部修改"}"#).unwrap();
+        assert!(workspace.list_recoverable_drafts().unwrap().is_empty());
+        workspace.discard_draft(&note.path).unwrap();
+
+        // 加锁时清除已有的草稿，之后也不再保存草稿
+        workspace.save_draft(&note.path, r#"{"title":"日记","text":"明文草稿"}"#).unwrap();
+        workspace.lock_note(&note.path, "密码").unwrap();
+        assert_eq!(fs::read_dir(temp_dir.path().join(DRAFTS_DIR)).unwrap().count(), 0);
+        assert!(workspace.save_draft(&note.path, "{}").is_err());
+
+        // 删除子分类时清除其中笔记的草稿
+        let sub = workspace.create_subcategory(&category, "周记").unwrap();
+        let weekly = workspace.create_note(&category, Some(&sub), "第一周", r#"{"title":"第一周"}"#).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        workspace.save_draft(&weekly.path, r#"{"title":"第一周","text":"未保存"}"#).unwrap();
+        assert_eq!(workspace.list_recoverable_drafts().unwrap().len(), 1);
+        workspace.delete_subcategory(&category, &sub).unwrap();
+        assert!(workspace.list_recoverable_drafts().unwrap().is_empty());
+        assert_eq!(fs::read_dir(temp_dir.path().join(DRAFTS_DIR)).unwrap().count(), 0);
+
+        // 分类在外部被删除或改名后，草稿不再列出，恢复时也不会重新创建分类
+        let other = workspace.create_category("临时").unwrap();
+        let orphan = workspace.create_note(&other, None, "便签", r#"{"title":"便签"}"#).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        workspace.save_draft(&orphan.path, r#"{"title":"便签","text":"未保存"}"#).unwrap();
+        fs::rename(temp_dir.path().join(&other), temp_dir.path().join("改名")).unwrap();
+        assert!(workspace.list_recoverable_drafts().unwrap().is_empty());
+        assert!(workspace.recover_draft(&orphan.path).is_err());
+        assert!(!temp_dir.path().join(&other).exists());
+
+        // 启用加密和更换口令时，草稿日志随笔记换用新密钥
+        let plan = workspace.create_note(&category, None, "计划", r#"{"title":"计划"}"#).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        workspace.save_draft(&plan.path, r#"{"title":"计划","text":"明文时的草稿"}"#).unwrap();
+        workspace.enable_encryption("旧口令", None).unwrap();
+        let journal = fs::read_dir(temp_dir.path().join(DRAFTS_DIR)).unwrap().next().unwrap().unwrap().path();
+        let line = fs::read_to_string(&journal).unwrap();
+        assert!(app_lib::crypto::is_encrypted(&STANDARD.decode(line.trim()).unwrap()));
+        workspace.save_draft(&plan.path, r#"{"title":"计划","text":"更换口令前的草稿"}"#).unwrap();
+        workspace.change_passphrase("旧口令", "新口令").unwrap();
+        workspace.vault().lock();
+        workspace.unlock("新口令").unwrap();
+        let recovered = workspace.recover_draft(&plan.path).expect("更换口令后无法恢复草稿");
+        assert!(recovered.contains("更换口令前的草稿"));
+    }
+
+    // 测试基于 JSON Patch 的增量更新
+    #[test]
+    fn test_apply_note_patch() {
+        use app_lib::patch::{apply_json_patch, REVISION_CONFLICT};
+        use app_lib::workspace::Workspace;
+
+        // RFC 6902 的各种操作
+        let mut document = json!({"a": {"b": [1, 2]}, "c~d": 1, "e/f": 2});
+        let patch = json!([
+            {"op": "add", "path": "/a/b/1", "value": 9},
+            {"op": "add", "path": "/a/b/-", "value": 3},
+            {"op": "replace", "path": "/c~0d", "value": 5},
+            {"op": "move", "from": "/e~1f", "path": "/g"},
+            {"op": "copy", "from": "/a/b", "path": "/h"},
+            {"op": "remove", "path": "/a/b/0"},
+            {"op": "test", "path": "/g", "value": 2}
+        ]);
+        apply_json_patch(&mut document, &patch).expect("应用补丁失败");
+        assert_eq!(document, json!({"a": {"b": [9, 2, 3]}, "c~d": 5, "g": 2, "h": [1, 9, 2, 3]}));
+        // 失败的补丁不会留下部分修改
+        let failing = json!([{"op": "remove", "path": "/g"}, {"op": "test", "path": "/a", "value": 1}]);
+        assert!(apply_json_patch(&mut document, &failing).is_err());
+        assert_eq!(document["g"], json!(2));
+        assert!(apply_json_patch(&mut document, &json!([{"op": "add", "path": "/a/b/7", "value": 1}])).is_err());
+        assert!(apply_json_patch(&mut document, &json!([{"op": "move", "from": "/a", "path": "/a/x"}])).is_err());
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category = workspace.create_category("补丁").unwrap();
+        let content = json!({
+            "title": "计划",
+            "data": {"data": {"text": "计划", "uid": "root"}, "children": []}
+        });
+        let note = workspace.create_note(&category, None, "计划", &content.to_string()).unwrap();
+        let base = workspace.read_note_with_revision(&note.path).unwrap();
+
+        // 添加一个子节点
+        let add_child = json!([
+            {"op": "add", "path": "/data/children/-", "value": {"data": {"text": "预算", "uid": "n1"}, "children": []}}
+        ]);
+        let revision = workspace.apply_note_patch(&note.path, &base.revision, &add_child).expect("应用补丁失败");
+        let current = workspace.read_note_with_revision(&note.path).unwrap();
+        assert_eq!(current.revision, revision);
+        let saved: serde_json::Value = serde_json::from_str(&current.content).unwrap();
+        assert_eq!(saved["data"]["children"][0]["data"]["text"], json!("预算"));
+
+        // 基准版本过期时拒绝
+        let rename = json!([{"op": "replace", "path": "/data/children/0/data/text", "value": "预算表"}]);
+        let error = workspace.apply_note_patch(&note.path, &base.revision, &rename).unwrap_err();
+        assert!(error.starts_with(REVISION_CONFLICT));
+        let revision = workspace.apply_note_patch(&note.path, &revision, &rename).unwrap();
+
+        // 破坏节点树的补丁不会被保存
+        let not_node = json!([{"op": "add", "path": "/data/children/-", "value": "missing"}]);
+        assert!(workspace.apply_note_patch(&note.path, &revision, &not_node).is_err());
+        let duplicate = json!([{"op": "add", "path": "/data/children/-", "value": {"data": {"text": "重复", "uid": "n1"}}}]);
+        assert!(workspace.apply_note_patch(&note.path, &revision, &duplicate).is_err());
+        let no_root = json!([{"op": "remove", "path": "/data/data"}]);
+        assert!(workspace.apply_note_patch(&note.path, &revision, &no_root).is_err());
+        assert_eq!(workspace.read_note_with_revision(&note.path).unwrap().revision, revision);
+    }
+
+    // 测试节点级别的增删改查与移动
+    #[test]
+    fn test_node_crud() {
+        use app_lib::note_format::NOTE_FORMAT_VERSION;
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category = workspace.create_category("节点").unwrap();
+        let content = json!({
+            "title": "旅行",
+            "data": {"data": {"text": "旅行", "uid": "root"}, "children": []}
+        });
+        let note = workspace.create_note(&category, None, "旅行", &content.to_string()).unwrap();
+        let data = |value: serde_json::Value| value.as_object().unwrap().clone();
+
+        // 添加节点，ID 由后端生成
+        let first = workspace.add_node(&note.path, "root", data(json!({"text": "交通"})), None).expect("添加节点失败");
+        let second = workspace.add_node(&note.path, "root", data(json!({"text": "住宿", "uid": "x"})), Some(0)).unwrap();
+        assert_ne!(second.id, "x");
+        assert_eq!(first.parent_id.as_deref(), Some("root"));
+        assert_eq!(workspace.get_node(&note.path, "root").unwrap().children, vec![second.id.clone(), first.id.clone()]);
+        let child = workspace.add_node(&note.path, &first.id, data(json!({"text": "机票"})), None).unwrap();
+        assert!(workspace.add_node(&note.path, "missing", data(json!({"text": "无"})), None).is_err());
+        assert!(workspace.add_node(&note.path, "root", data(json!({"tag": ["无文字"]})), None).is_err());
+
+        // 修改节点数据，null 删除字段，不能改 ID
+        let updated = workspace.update_node(&note.path, &child.id, data(json!({"text": "火车票", "tag": ["待办"]}))).unwrap();
+        assert_eq!(updated.data["text"], json!("火车票"));
+        assert_eq!(updated.data["uid"], json!(child.id));
+        let updated = workspace.update_node(&note.path, &child.id, data(json!({"tag": null}))).unwrap();
+        assert!(!updated.data.contains_key("tag"));
+        assert!(workspace.update_node(&note.path, &child.id, data(json!({"uid": "y"}))).is_err());
+
+        // 移动节点，不能移动根节点或移动到自己的后代下
+        let moved = workspace.move_node(&note.path, &child.id, &second.id, None).unwrap();
+        assert_eq!(moved.parent_id.as_deref(), Some(second.id.as_str()));
+        assert!(workspace.get_node(&note.path, &first.id).unwrap().children.is_empty());
+        assert!(workspace.move_node(&note.path, &second.id, &child.id, None).is_err());
+        assert!(workspace.move_node(&note.path, "root", &first.id, None).is_err());
+
+        // 删除节点会删除整个子树，根节点不能删除
+        let removed = workspace.delete_node(&note.path, &second.id).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(workspace.get_node(&note.path, &child.id).is_err());
+        assert!(workspace.delete_node(&note.path, "root").is_err());
+
+        let saved: serde_json::Value = serde_json::from_str(&workspace.read_note(&note.path).unwrap()).unwrap();
+        app_lib::patch::validate_note_tree(&saved).expect("节点树结构错误");
+        assert_eq!(saved["data"]["children"][0]["data"]["uid"], json!(first.id));
+        assert_eq!(saved["data"]["children"].as_array().map(Vec::len), Some(1));
+        assert_eq!(saved["formatVersion"], json!(NOTE_FORMAT_VERSION));
+
+        // 编辑器保存的笔记：节点按 data.uid 查找，修改后保持编辑器的嵌套结构和其他字段
+        let editor_note = include_str!("../tests/fixtures/notes/v2-editor.json");
+        let roadmap = workspace.create_note(&category, None, "路线图", editor_note).unwrap();
+        let root_id = "0f3b9d2c-7e41-4a58-b6c9-d0e1f2a3b4c5";
+        let milestone = "a61f0c8e-3b2d-4e97-8c5a-7d6e5f4a3b2c";
+        let launch = "e2d4c6b8-a0f1-4e3d-9c7b-5a3f1e2d4c6b";
+        let info = workspace.get_node(&roadmap.path, launch).expect("找不到编辑器保存的节点");
+        assert_eq!(info.parent_id.as_deref(), Some(milestone));
+        assert_eq!(info.data["tag"], json!(["todo"]));
+        assert_eq!(workspace.get_node(&roadmap.path, root_id).unwrap().children.len(), 2);
+        let added = workspace.add_node(&roadmap.path, milestone, data(json!({"text": "灰度"})), Some(0)).unwrap();
+        workspace.move_node(&roadmap.path, launch, root_id, Some(0)).unwrap();
+        workspace.update_node(&roadmap.path, "3c7a1e9b-5d2f-4b60-8e4a-9f1b2c3d4e5f", data(json!({"tag": ["已确认"]}))).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&workspace.read_note(&roadmap.path).unwrap()).unwrap();
+        assert_eq!(saved["theme"], json!("classic10"));
+        assert_eq!(saved["data"]["data"]["expand"], json!(true));
+        let children = saved["data"]["children"].as_array().unwrap();
+        assert_eq!(children[0]["data"]["uid"], json!(launch));
+        assert_eq!(children[1]["data"]["tag"], json!(["已确认"]));
+        assert_eq!(children[1]["data"]["richText"], json!(true));
+        assert_eq!(children[2]["children"][0]["data"]["uid"], json!(added.id));
+        assert_eq!(children[2]["children"].as_array().map(Vec::len), Some(1));
+        assert_eq!(workspace.delete_node(&roadmap.path, milestone).unwrap(), vec![milestone.to_string(), added.id]);
+    }
+
+    // 测试命令行接口的各个子命令
+    #[test]
+    fn test_cli_commands() {
+        use app_lib::{cli, workspace::Workspace};
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let root = temp_dir.path().join("notes");
+        let workspace = Workspace::open(&root).expect("无法打开工作区");
+        let category = workspace.create_category("项目").unwrap();
+        let content = json!({
+            "title": "发布计划",
+            "data": {"data": {"text": "发布计划", "uid": "root"}, "children": []}
+        });
+        let note = workspace.create_note(&category, None, "发布计划", &content.to_string()).unwrap();
+        drop(workspace);
+
+        let run = |args: &[&str]| {
+            let mut args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            args.splice(0..0, ["--workspace".to_string(), root.display().to_string()]);
+            let mut out = Vec::new();
+            let code = cli::run(&args, &mut out);
+            (code, String::from_utf8(out).unwrap())
+        };
+
+        let (code, out) = run(&["list"]);
+        assert_eq!(code, Ok(0));
+        assert_eq!(out, format!("{}\t发布计划\n", note.path));
+
+        // 添加节点后可以搜索到，标签也参与搜索
+        let (code, out) = run(&["add-node", &note.path, "编写 Changelog", "--tag", "待办"]);
+        assert_eq!(code, Ok(0));
+        let node_id = out.trim().to_string();
+        let (code, out) = run(&["--json", "search", "changelog", "待办"]);
+        assert_eq!(code, Ok(0));
+        let hits: serde_json::Value = serde_json::from_str(&out).expect("输出不是JSON");
+        assert_eq!(hits[0]["nodeId"], json!(node_id));
+        assert_eq!(hits[0]["path"], json!(note.path));
+        assert_eq!(run(&["search", "不存在"]).0, Ok(1));
+
+        let (_, out) = run(&["cat", &note.path]);
+        assert_eq!(out, "# 发布计划\n\n- 编写 Changelog #待办\n");
+        let (_, out) = run(&["--json", "cat", &note.path]);
+        let saved: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(saved["data"]["children"][0]["data"]["uid"], json!(node_id));
+
+        let exported = temp_dir.path().join("发布计划.md");
+        let (code, _) = run(&["export", &note.path, "--output", exported.to_str().unwrap()]);
+        assert_eq!(code, Ok(0));
+        assert!(fs::read_to_string(&exported).unwrap().contains("- 编写 Changelog"));
+
+        // 有未修复的问题时退出码为 1，修复后为 0
+        fs::write(root.join(&category).join("坏.json"), "{").unwrap();
+        assert_eq!(run(&["check"]).0, Ok(1));
+        assert_eq!(run(&["check", "--repair"]).0, Ok(0));
+
+        assert!(run(&["unknown"]).0.is_err());
+        assert!(run(&["list", "--bogus"]).0.is_err());
+        assert!(run(&["add-node", &note.path]).0.is_err());
+    }
+
+    // 测试本地 HTTP 接口的认证与请求处理
+    #[test]
+    fn test_local_api_requests() {
+        use app_lib::api::{handle_request, ApiRequest};
+        use app_lib::workspace::{Workspace, WorkspaceState};
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let state = WorkspaceState::default();
+        let request = |method: &str, url: &str, body: serde_json::Value| ApiRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            authorization: Some("Bearer secret".to_string()),
+            body: if body.is_null() { String::new() } else { body.to_string() },
+        };
+
+        // 没有令牌或令牌错误时拒绝，令牌未生成时一律拒绝
+        let unauthorized = ApiRequest { authorization: Some("Bearer wrong".to_string()), ..request("GET", "/api/notes", json!(null)) };
+        assert_eq!(handle_request(&state, "secret", &unauthorized).status, 401);
+        assert_eq!(handle_request(&state, "", &request("GET", "/api/notes", json!(null))).status, 401);
+        assert_eq!(handle_request(&state, "secret", &request("GET", "/api/notes", json!(null))).status, 503);
+
+        let workspace = state.set(Workspace::open(temp_dir.path()).unwrap()).unwrap();
+        let category = workspace.create_category("接口").unwrap();
+        let content = json!({
+            "title": "插件",
+            "data": {"data": {"text": "插件", "uid": "root"}, "children": []}
+        });
+        let note = workspace.create_note(&category, None, "插件", &content.to_string()).unwrap();
+        let encoded_path = note.path.bytes().map(|b| format!("%{:02X}", b)).collect::<String>();
+
+        let response = handle_request(&state, "secret", &request("GET", "/api/notes?sort=title", json!(null)));
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body[0]["path"], json!(note.path));
+
+        // 添加节点会报告被修改的笔记
+        let url = format!("/api/nodes?path={}", encoded_path);
+        let response = handle_request(&state, "secret", &request("POST", &url, json!({"parentId": "root", "data": {"text": "VS Code 扩展"}})));
+        assert_eq!(response.status, 200, "{}", response.body);
+        assert_eq!(response.changed.as_deref(), Some(note.path.as_str()));
+        let node_id = response.body["id"].as_str().unwrap().to_string();
+
+        let response = handle_request(&state, "secret", &request("GET", "/api/search?q=vs+code", json!(null)));
+        assert_eq!(response.body[0]["nodeId"], json!(node_id));
+        let url = format!("/api/nodes/{}?path={}", node_id, encoded_path);
+        let response = handle_request(&state, "secret", &request("PATCH", &url, json!({"tag": ["进行中"]})));
+        assert_eq!(response.body["data"]["tag"], json!(["进行中"]));
+        let response = handle_request(&state, "secret", &request("GET", &format!("/api/note/markdown?path={}", encoded_path), json!(null)));
+        assert_eq!(response.body["markdown"], json!("# 插件\n\n- VS Code 扩展 #进行中\n"));
+        let response = handle_request(&state, "secret", &request("DELETE", &url, json!(null)));
+        assert_eq!(response.body["removed"], json!([node_id]));
+
+        // 错误的请求
+        assert_eq!(handle_request(&state, "secret", &request("GET", "/api/unknown", json!(null))).status, 404);
+        assert_eq!(handle_request(&state, "secret", &request("GET", "/api/note", json!(null))).status, 400);
+        assert_eq!(handle_request(&state, "secret", &request("GET", "/api/note?path=../x.json", json!(null))).status, 400);
+        assert_eq!(handle_request(&state, "secret", &request("POST", &format!("/api/nodes?path={}", encoded_path), json!({}))).status, 400);
+        // 笔记或节点不存在时是 404，不能应用到笔记上的修改是 400
+        assert_eq!(handle_request(&state, "secret", &request("GET", "/api/note?path=%E6%8E%A5%E5%8F%A3/none.json", json!(null))).status, 404);
+        assert_eq!(handle_request(&state, "secret", &request("PUT", "/api/note?path=none/a.json", json!({}))).status, 404);
+        assert_eq!(handle_request(&state, "secret", &request("GET", &format!("/api/nodes/missing?path={}", encoded_path), json!(null))).status, 404);
+        let missing_parent = request("POST", &format!("/api/nodes?path={}", encoded_path), json!({"parentId": "missing", "data": {"text": "子节点"}}));
+        assert_eq!(handle_request(&state, "secret", &missing_parent).status, 404);
+        assert_eq!(handle_request(&state, "secret", &request("DELETE", &format!("/api/nodes/root?path={}", encoded_path), json!(null))).status, 400);
+        // 保存笔记的请求体必须是 JSON，非 JSON 内容不会覆盖笔记
+        let not_json = ApiRequest { body: "不是JSON".to_string(), ..request("PUT", &format!("/api/note?path={}", encoded_path), json!(null)) };
+        assert_eq!(handle_request(&state, "secret", &not_json).status, 400);
+        assert!(workspace.read_note(&note.path).unwrap().contains("插件"));
+        let response = handle_request(&state, "secret", &request("PUT", &format!("/api/note?path={}", encoded_path), content));
+        assert_eq!(response.status, 200, "{}", response.body);
+    }
+
+    // 测试快速记录：写入收集箱并可以移动到其他笔记
+    #[test]
+    fn test_quick_capture_inbox() {
+        use app_lib::inbox::{InboxMove, DEFAULT_INBOX_NAME};
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+
+        // 首次记录时在默认位置创建收集箱
+        let first = workspace.append_to_inbox("买牛奶", &[]).expect("记录失败");
+        let second = workspace.append_to_inbox("  读《设计模式》 ", &["阅读".to_string()]).unwrap();
+        let inbox = workspace.inbox_note().unwrap();
+        assert_eq!(inbox, format!("{0}/{0}.json", DEFAULT_INBOX_NAME));
+        assert!(first.data["capturedAt"].is_string());
+        assert_eq!(second.data["text"], json!("读《设计模式》"));
+        assert_eq!(second.data["tag"], json!(["阅读"]));
+        assert!(workspace.append_to_inbox(" ", &[]).is_err());
+        let items = workspace.list_inbox().unwrap();
+        assert_eq!(items.iter().map(|item| item.id.clone()).collect::<Vec<_>>(), vec![first.id.clone(), second.id.clone()]);
+
+        // 把记录移动到目标笔记，失败的项留在收集箱中
+        let category = workspace.create_category("阅读").unwrap();
+        let content = json!({
+            "title": "书单",
+            "data": {"data": {"text": "书单", "uid": "root"}, "children": []}
+        });
+        let books = workspace.create_note(&category, None, "书单", &content.to_string()).unwrap();
+        workspace.add_node(&inbox, &second.id, json!({"text": "第 3 章"}).as_object().unwrap().clone(), None).unwrap();
+        let results = workspace.process_inbox(&[
+            InboxMove { node_id: second.id.clone(), target_path: books.path.clone(), parent_id: None },
+            InboxMove { node_id: first.id.clone(), target_path: "阅读/不存在.json".to_string(), parent_id: None },
+        ]).unwrap();
+        assert!(results[0].error.is_none());
+        assert!(results[1].error.is_some());
+        let moved = results[0].node.clone().unwrap();
+        assert_eq!(moved.id, second.id);
+        assert_eq!(moved.parent_id.as_deref(), Some("root"));
+        assert_eq!(moved.children.len(), 1);
+        assert_eq!(workspace.list_inbox().unwrap().iter().map(|item| item.id.clone()).collect::<Vec<_>>(), vec![first.id.clone()]);
+        let saved: serde_json::Value = serde_json::from_str(&workspace.read_note(&books.path).unwrap()).unwrap();
+        app_lib::patch::validate_note_tree(&saved).expect("节点树结构错误");
+
+        // 指定其他笔记作为收集箱
+        let settings = workspace.set_inbox_note(Some(&books.path)).unwrap();
+        assert_eq!(settings.path.as_deref(), Some(books.path.as_str()));
+        let third = workspace.append_to_inbox("想法", &[]).unwrap();
+        assert!(workspace.get_node(&books.path, &third.id).is_ok());
+        assert!(workspace.set_inbox_note(Some("阅读/不存在.json")).is_err());
+        workspace.set_inbox_note(None).unwrap();
+        assert_eq!(workspace.inbox_note().unwrap(), inbox);
+    }
+
+    // 测试笔记模板：变量替换、重新生成节点ID与保存为模板
+    #[test]
+    fn test_note_templates() {
+        use app_lib::templates::TEMPLATES_DIR;
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        assert!(workspace.list_templates().unwrap().is_empty());
+
+        let templates = temp_dir.path().join(TEMPLATES_DIR);
+        fs::create_dir_all(&templates).unwrap();
+        let meeting = json!({
+            "title": "会议纪要", "rootId": "r",
+            "data": {
+                "r": {"data": {"id": "r", "text": "{{title}}"}, "children": ["a", "b"]},
+                "a": {"data": {"id": "a", "text": "日期: {{date}}", "tag": ["{{category}}"]}},
+                "b": {"data": {"id": "b", "text": "议题 {{未知}}"}}
+            }
+        });
+        fs::write(templates.join("meeting.json"), meeting.to_string()).unwrap();
+        fs::write(templates.join("项目.md"), "# {{category}}: {{title}}\n\n## 目标\n- 里程碑 #重要\n  - 第一阶段\n## 风险\n").unwrap();
+        fs::write(templates.join("说明.txt"), "不是模板").unwrap();
+
+        let listed = workspace.list_templates().unwrap();
+        let files: Vec<_> = listed.iter().map(|template| (template.file.as_str(), template.name.as_str(), template.format.as_str())).collect();
+        assert_eq!(files, vec![("meeting.json", "会议纪要", "mindmap"), ("项目.md", "项目", "markdown")]);
+
+        // 模板目录不会被当作分类
+        let category = workspace.create_category("工作").unwrap();
+        assert_eq!(workspace.get_all_categories(Default::default()).unwrap().len(), 1);
+
+        // 脑图模板：旧格式的模板转换为嵌套树，替换变量、重新生成节点ID
+        let note = workspace.create_note_from_template("meeting.json", &category, None, "周会").unwrap();
+        assert_eq!(note.title, "周会");
+        let saved: serde_json::Value = serde_json::from_str(&workspace.read_note(&note.path).unwrap()).unwrap();
+        app_lib::patch::validate_note_tree(&saved).expect("节点树结构错误");
+        let root_id = saved["data"]["data"]["uid"].as_str().unwrap();
+        assert_ne!(root_id, "r");
+        assert_eq!(saved["data"]["data"]["text"], json!("周会"));
+        let children: Vec<&serde_json::Value> = saved["data"]["children"].as_array().unwrap().iter().map(|child| &child["data"]).collect();
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(children[0]["text"], json!(format!("日期: {}", today)));
+        assert_eq!(children[0]["tag"], json!(["工作"]));
+        assert_ne!(children[0]["uid"], json!("a"));
+        assert_eq!(children[1]["text"], json!("议题 {{未知}}"));
+
+        // 同一模板可以多次使用，节点ID互不相同
+        let second = workspace.create_note_from_template("meeting.json", &category, None, "月会").unwrap();
+        let second: serde_json::Value = serde_json::from_str(&workspace.read_note(&second.path).unwrap()).unwrap();
+        assert_ne!(second["data"]["data"]["uid"], saved["data"]["data"]["uid"]);
+
+        // Markdown 模板按大纲生成节点
+        let project = workspace.create_note_from_template("项目.md", &category, None, "新官网").unwrap();
+        assert_eq!(
+            workspace.export_markdown(&project.path).unwrap(),
+            "# 工作: 新官网\n\n- 目标\n  - 里程碑 #重要\n    - 第一阶段\n- 风险\n"
+        );
+
+        // 无效的模板和分类
+        assert!(workspace.create_note_from_template("../工作/周会.json", &category, None, "x").is_err());
+        assert!(workspace.create_note_from_template("说明.txt", &category, None, "x").is_err());
+        assert!(workspace.create_note_from_template("不存在.json", &category, None, "x").is_err());
+        assert!(workspace.create_note_from_template("meeting.json", "没有这个分类", None, "x").is_err());
+
+        // 把笔记保存为模板
+        let template = workspace.save_note_as_template(&note.path, "周会模板").unwrap();
+        assert_eq!(template.format, "mindmap");
+        assert!(workspace.list_templates().unwrap().iter().any(|listed| listed.file == template.file && listed.name == "周会模板"));
+        assert!(workspace.save_note_as_template(&note.path, "周会模板").is_err());
+
+        // 富文本节点中代入的值会被转义，普通节点保持原样
+        let rich = json!({
+            "formatVersion": 2, "title": "富文本",
+            "data": {
+                "data": {"text": "<p>{{title}}</p>", "richText": true, "uid": "r"},
+                "children": [{"data": {"text": "{{title}}", "uid": "a"}, "children": []}]
+            }
+        });
+        fs::write(templates.join("rich.json"), rich.to_string()).unwrap();
+        let title = "<img src=x onerror=alert(1)>";
+        let note = workspace.create_note_from_template("rich.json", &category, None, title).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&workspace.read_note(&note.path).unwrap()).unwrap();
+        assert_eq!(saved["data"]["data"]["text"], json!("<p>&lt;img src=x onerror=alert(1)&gt;</p>"));
+        assert_eq!(saved["data"]["children"][0]["data"]["text"], json!(title));
+    }
+
+    // 测试笔记链接、反向链接与关系图
+    #[test]
+    fn test_note_links_and_backlinks() {
+        use app_lib::links::{node_links, LinkTarget};
+        use app_lib::workspace::Workspace;
+
+        let data = json!({"text": "参考 [[设计文档|文档]] 和 [[ 会议 ]]，[[]] 不是链接", "link": "abc"});
+        assert_eq!(node_links(data.as_object().unwrap()), vec![
+            LinkTarget::Title("设计文档".to_string()),
+            LinkTarget::Title("会议".to_string()),
+            LinkTarget::Id("abc".to_string()),
+        ]);
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category = workspace.create_category("项目").unwrap();
+        // 与编辑器保存的笔记相同的嵌套树，children 是根节点下的 (uid, 节点数据)
+        let note = |title: &str, children: Vec<(&str, serde_json::Value)>| {
+            let children: Vec<serde_json::Value> = children.into_iter().map(|(uid, mut data)| {
+                data["uid"] = json!(uid);
+                json!({"data": data, "children": []})
+            }).collect();
+            let content = json!({"title": title, "data": {"data": {"text": title, "uid": "root"}, "children": children}});
+            workspace.create_note(&category, None, title, &content.to_string()).unwrap()
+        };
+        let design = note("设计文档", vec![("d1", json!({"text": "接口设计"}))]);
+        let plan = note("计划", vec![
+            ("p1", json!({"text": "先看 [[设计文档]] 再看 [[设计文档]]"})),
+            ("p2", json!({"text": "接口", "link": "d1"})),
+            ("p3", json!({"text": "<p>富文本 [[计划]]</p>", "richText": true})),
+        ]);
+        let review = note("评审", vec![
+            ("r1", json!({"text": "见设计", "link": design.id.clone()})),
+            ("r2", json!({"text": "[[不存在的笔记]]"})),
+            // 每篇笔记都有 root 节点，无法确定指向哪篇笔记
+            ("r3", json!({"text": "根节点", "link": "root"})),
+        ]);
+
+        // 标题、节点ID和笔记ID都能解析，同一节点只返回一次
+        let backlinks = workspace.get_backlinks(&design.id).unwrap();
+        let found: Vec<_> = backlinks.iter()
+            .map(|link| (link.note_id.as_str(), link.node_id.as_str(), link.target_node_id.as_deref()))
+            .collect();
+        assert_eq!(found.len(), 3);
+        assert!(found.contains(&(plan.id.as_str(), "p1", None)));
+        assert!(found.contains(&(plan.id.as_str(), "p2", Some("d1"))));
+        assert!(found.contains(&(review.id.as_str(), "r1", None)));
+        assert_eq!(backlinks.iter().find(|link| link.node_id == "p1").unwrap().text, "先看 [[设计文档]] 再看 [[设计文档]]");
+        assert!(workspace.get_backlinks(&review.id).unwrap().is_empty());
+        assert_eq!(workspace.get_backlinks(&plan.id).unwrap().len(), 1);
+        assert!(workspace.get_backlinks("项目##不存在").is_err());
+
+        // 关系图包含所有笔记，不含指向自身的链接
+        let graph = workspace.get_link_graph().unwrap();
+        assert_eq!(graph.nodes.len(), 3);
+        let mut edges: Vec<_> = graph.edges.iter()
+            .map(|edge| (edge.source.as_str(), edge.target.as_str(), edge.count))
+            .collect();
+        edges.sort();
+        let mut expected = vec![(plan.id.as_str(), design.id.as_str(), 3), (review.id.as_str(), design.id.as_str(), 1)];
+        expected.sort();
+        assert_eq!(edges, expected);
+
+        // 节点移动到其他笔记后，按节点ID的链接仍然有效
+        let moved = workspace.move_node_to_note(&design.path, "d1", &review.path, None, None).unwrap();
+        assert_eq!(moved.id, "d1");
+        let backlinks = workspace.get_backlinks(&review.id).unwrap();
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].target_node_id.as_deref(), Some("d1"));
+    }
+
+    // 测试标题或文件名中含有 '#' 的笔记
+    #[test]
+    fn test_note_ids_with_hash() {
+        use app_lib::naming::slugify;
+        use app_lib::ordering::SortMode;
+        use app_lib::workspace::Workspace;
+
+        assert_eq!(slugify("C#入门").unwrap(), "C_入门");
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category = workspace.create_category("编程").unwrap();
+        let note = workspace.create_note(&category, None, "C#入门", "{}").expect("创建笔记失败");
+        assert_eq!(note.id, format!("{}##C_入门", category));
+        assert_eq!(note.title, "C#入门");
+
+        // 旧版本创建的文件名中可能含有 '#'，只按前两个 '#' 解析ID
+        fs::write(temp_dir.path().join(&category).join("C.json"), "{}").unwrap();
+        fs::write(temp_dir.path().join(&category).join("旧#笔记.json"), "{}").unwrap();
+        let legacy = format!("{}##旧#笔记", category);
+        workspace.reorder_notes(&category, None, &[legacy.clone(), note.id.clone()]).expect("保存笔记顺序失败");
+        let ordered: Vec<String> = workspace.get_all_notes(SortMode::Custom).unwrap().into_iter().map(|n| n.id).collect();
+        assert_eq!(ordered[..2], [legacy.clone(), note.id.clone()]);
+
+        workspace.delete_note(&legacy).expect("删除笔记失败");
+        assert!(!temp_dir.path().join(&category).join("旧#笔记.json").exists());
+        assert!(temp_dir.path().join(&category).join("C.json").exists(), "不应删除 '#' 之前同名的笔记");
+        assert!(workspace.delete_note("只有分类").is_err());
+    }
+
+    // 测试更换口令期间并发的节点修改不会用旧密钥写入笔记
+    #[test]
+    fn test_change_passphrase_during_edits() {
+        use app_lib::workspace::Workspace;
+
+        let temp_dir = tempdir().expect("无法创建临时目录");
+        let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+        let category = workspace.create_category("加密").unwrap();
+        let content = json!({
+            "title": "并发",
+            "data": {"data": {"text": "并发", "uid": "root"}, "children": []}
+        });
+        let note = workspace.create_note(&category, None, "并发", &content.to_string()).unwrap();
+        workspace.enable_encryption("旧口令", Some(0)).expect("启用加密失败");
+
+        std::thread::scope(|scope| {
+            let editor = scope.spawn(|| {
+                for index in 0..40 {
+                    let data = json!({"text": format!("节点{}", index)}).as_object().unwrap().clone();
+                    workspace.add_node(&note.path, "root", data, None).expect("添加节点失败");
+                }
+            });
+            workspace.change_passphrase("旧口令", "新口令").expect("更换口令失败");
+            editor.join().unwrap();
+        });
+
+        // 重新打开后只用新口令解锁，所有修改都能读出
+        workspace.close();
+        let reopened = Workspace::open(temp_dir.path()).expect("无法重新打开工作区");
+        reopened.unlock("新口令").expect("解锁失败");
+        let saved: serde_json::Value = serde_json::from_str(&reopened.read_note(&note.path).unwrap()).unwrap();
+        assert_eq!(saved["data"]["children"].as_array().map(Vec::len), Some(40));
+    }
 }
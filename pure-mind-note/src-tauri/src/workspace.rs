@@ -0,0 +1,260 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, OnceLock, RwLock},
+};
+use notify::RecommendedWatcher;
+use tauri::{AppHandle, Manager};
+
+use crate::crypto::Vault;
+use crate::history::History;
+use crate::note_index::{ChangeNotifier, NoteIndex};
+use crate::registry::{run_blocking, WorkspaceRegistry};
+
+// 写入文件时使用的临时文件后缀
+pub const TEMP_SUFFIX: &str = ".pmn-tmp";
+
+// 先写入临时文件再重命名，避免写入中途崩溃留下半个文件
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(TEMP_SUFFIX);
+    let temp_path = path.with_file_name(temp_name);
+
+    fs::write(&temp_path, bytes).map_err(|e| format!("无法写入文件: {}", e))?;
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        format!("无法替换文件: {}", e)
+    })
+}
+
+// 检查路径（包括其中的符号链接）最终仍位于 root 内，root 须是规范化的路径
+pub fn ensure_within(root: &Path, path: &Path) -> Result<(), String> {
+    // 找到最近的已存在祖先，规范化后检查是否逃出根目录
+    let mut existing = path;
+    while !existing.exists() && fs::symlink_metadata(existing).is_err() {
+        match existing.parent() {
+            Some(parent) => existing = parent,
+            None => break,
+        }
+    }
+
+    let canonical = fs::canonicalize(existing)
+        .map_err(|e| format!("无法解析路径 {}: {}", existing.display(), e))?;
+    if canonical.starts_with(root) {
+        Ok(())
+    } else {
+        Err(format!("非法路径，超出工作区范围: {}", path.display()))
+    }
+}
+
+// 当前打开的工作区，所有文件操作都以其根目录为边界
+#[derive(Debug)]
+pub struct Workspace {
+    root: PathBuf,
+    vault: Vault,
+    history: History,
+    // 读取-修改-写入笔记的操作（补丁、节点编辑）依次执行
+    edits: Mutex<()>,
+    // 笔记内容索引和文件监视，随工作区打开和关闭
+    index: Arc<NoteIndex>,
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl Workspace {
+    // 打开工作区：目录不存在时创建，并将根目录规范化为绝对路径
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        if path.as_os_str().is_empty() {
+            return Err("工作区路径不能为空".to_string());
+        }
+
+        fs::create_dir_all(path).map_err(|e| format!("无法创建工作区目录: {}", e))?;
+        let root = fs::canonicalize(path).map_err(|e| format!("无法解析工作区路径: {}", e))?;
+        if !root.is_dir() {
+            return Err(format!("工作区路径不是目录: {}", root.display()));
+        }
+
+        let vault = Vault::load(&root)?;
+        let history = History::load(&root);
+        Ok(Self {
+            root,
+            vault,
+            history,
+            edits: Mutex::new(()),
+            index: Arc::default(),
+            watcher: Mutex::new(None),
+        })
+    }
+
+    // 工作区根目录（已规范化）
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // 工作区的加密状态
+    pub fn vault(&self) -> &Vault {
+        &self.vault
+    }
+
+    // 工作区的 git 历史
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    // 工作区的笔记内容索引
+    pub fn index(&self) -> &Arc<NoteIndex> {
+        &self.index
+    }
+
+    pub(crate) fn lock_watcher(&self) -> MutexGuard<'_, Option<RecommendedWatcher>> {
+        self.watcher.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // 在读取和写回笔记之间持有，避免并发的修改互相覆盖
+    pub fn lock_edits(&self) -> MutexGuard<'_, ()> {
+        self.edits.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // 将前端传入的相对路径（如 "分类/子分类/笔记.json"）解析为工作区内的绝对路径
+    pub fn resolve(&self, relative: &str) -> Result<PathBuf, String> {
+        let mut parts = Vec::new();
+        for component in Path::new(relative).components() {
+            match component {
+                Component::Normal(part) => parts.push(part),
+                Component::CurDir => {}
+                _ => return Err(format!("非法路径，超出工作区范围: {}", relative)),
+            }
+        }
+
+        let mut path = self.root.clone();
+        path.extend(parts);
+        self.ensure_inside(&path)?;
+        Ok(path)
+    }
+
+    // 按目录层级解析路径，每一级都必须是单个普通的文件名
+    pub fn resolve_parts(&self, parts: &[&str]) -> Result<PathBuf, String> {
+        let mut path = self.root.clone();
+        for part in parts {
+            let mut components = Path::new(part).components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(name)), None) if name.to_str() == Some(*part) => path.push(name),
+                _ => return Err(format!("非法的名称: '{}'", part)),
+            }
+        }
+
+        self.ensure_inside(&path)?;
+        Ok(path)
+    }
+
+    // 检查路径（包括其中的符号链接）最终仍位于工作区内
+    pub fn ensure_inside(&self, path: &Path) -> Result<(), String> {
+        ensure_within(&self.root, path)
+    }
+
+    // 离开工作区前提交等待中的修改，停止文件监视，并清除内存中的密钥和索引
+    pub fn close(&self) {
+        self.unwatch();
+        if let Err(e) = self.flush_history() {
+            backend_log!("[后端] 提交历史失败: {}", e);
+        }
+        self.vault.lock();
+        self.index.clear();
+        backend_log!("[后端] 已关闭工作区: {}", self.root.display());
+    }
+
+    // 将工作区内的绝对路径转换为以 "/" 分隔的相对路径，供前端回传
+    pub fn relative_path(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+// 作为 Tauri 托管状态保存当前工作区
+#[derive(Default)]
+pub struct WorkspaceState {
+    current: RwLock<Option<Arc<Workspace>>>,
+    // 设置后，每个打开的工作区都会监视笔记在应用之外的修改
+    notifier: OnceLock<ChangeNotifier>,
+}
+
+impl WorkspaceState {
+    // 获取当前工作区，未打开时返回错误
+    pub fn current(&self) -> Result<Arc<Workspace>, String> {
+        self.current
+            .read()
+            .map_err(|_| "工作区状态已损坏".to_string())?
+            .clone()
+            .ok_or_else(|| "尚未打开工作区".to_string())
+    }
+
+    // 在后台阻塞线程池中执行工作区操作，避免文件 I/O 阻塞命令线程
+    pub async fn run<T, F>(&self, task: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Workspace) -> Result<T, String> + Send + 'static,
+    {
+        let workspace = self.current()?;
+        tauri::async_runtime::spawn_blocking(move || task(&workspace))
+            .await
+            .map_err(|e| format!("后台任务执行失败: {}", e))?
+    }
+
+    // 替换当前工作区，并关闭旧工作区；后台的自动锁定、提交和备份任务随之作用于新工作区，
+    // 文件监视从旧工作区移到新工作区
+    pub fn set(&self, workspace: Workspace) -> Result<Arc<Workspace>, String> {
+        let workspace = Arc::new(workspace);
+        let previous = self.current
+            .write()
+            .map_err(|_| "工作区状态已损坏".to_string())?
+            .replace(workspace.clone());
+        if let Some(previous) = previous {
+            if previous.root() != workspace.root() {
+                previous.close();
+            } else {
+                previous.unwatch();
+                if let Err(e) = previous.flush_history() {
+                    backend_log!("[后端] 提交历史失败: {}", e);
+                }
+            }
+        }
+        self.start_watching(&workspace);
+        Ok(workspace)
+    }
+
+    // 设置笔记在应用之外被修改时的通知，并开始监视当前工作区
+    pub fn watch_changes(&self, notifier: ChangeNotifier) {
+        if self.notifier.set(notifier).is_ok() {
+            if let Ok(workspace) = self.current() {
+                self.start_watching(&workspace);
+            }
+        }
+    }
+
+    // 监视失败（如超出系统的监视数量限制）不影响使用工作区，只是不再通知外部修改
+    fn start_watching(&self, workspace: &Workspace) {
+        if let Some(notifier) = self.notifier.get() {
+            if let Err(e) = workspace.watch(notifier.clone()) {
+                backend_log!("[后端] {}", e);
+            }
+        }
+    }
+}
+
+// 打开工作区，返回规范化后的根目录；未登记的目录会自动加入工作区列表
+#[tauri::command]
+pub async fn open_workspace(app: AppHandle, path: String) -> Result<String, String> {
+    backend_log!("[后端] 打开工作区: {}", path);
+    run_blocking(app, move |app| {
+        let workspace = app.state::<WorkspaceState>().set(Workspace::open(&path)?)?;
+        let root = workspace.root().to_string_lossy().to_string();
+        let registry = app.state::<WorkspaceRegistry>();
+        let entry = registry.find_or_add(&root)?;
+        registry.mark_opened(&entry.id)?;
+        Ok(root)
+    }).await
+}
@@ -0,0 +1,151 @@
+// 通过管道驱动 pmn --stdio-server，按 JSON-RPC 2.0 收发消息
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+use serde_json::{json, Value};
+use tempfile::tempdir;
+
+use app_lib::workspace::Workspace;
+
+struct Server {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    lines: Receiver<Value>,
+    // 等待响应时收到的通知
+    notifications: Vec<Value>,
+}
+
+impl Server {
+    fn start(workspace: &std::path::Path) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_pmn"))
+            .arg("--workspace")
+            .arg(workspace)
+            .arg("--stdio-server")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("无法启动 pmn");
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let line = line.expect("无法读取输出");
+                let message = serde_json::from_str(&line).unwrap_or_else(|e| panic!("输出不是JSON: {} ({})", line, e));
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { child, stdin, lines, notifications: Vec::new() }
+    }
+
+    fn send(&mut self, message: &str) {
+        let stdin = self.stdin.as_mut().unwrap();
+        writeln!(stdin, "{}", message).unwrap();
+        stdin.flush().unwrap();
+    }
+
+    fn next_message(&mut self) -> Value {
+        self.lines.recv_timeout(Duration::from_secs(10)).expect("等待输出超时")
+    }
+
+    // 读取下一条响应，期间的通知留待之后检查
+    fn response(&mut self) -> Value {
+        loop {
+            let message = self.next_message();
+            if message.get("method").is_some() {
+                self.notifications.push(message);
+            } else {
+                return message;
+            }
+        }
+    }
+
+    fn call(&mut self, id: u64, method: &str, params: Value) -> Value {
+        self.send(&json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}).to_string());
+        let response = self.response();
+        assert_eq!(response["jsonrpc"], json!("2.0"));
+        assert_eq!(response["id"], json!(id));
+        response
+    }
+
+    // 等待包含 path 的 notesChanged 通知
+    fn wait_for_change(&mut self, path: &str) {
+        let mentions = |message: &Value| {
+            message["method"] == json!("notesChanged")
+                && message["params"]["paths"].as_array().is_some_and(|paths| paths.contains(&json!(path)))
+        };
+        if self.notifications.iter().any(mentions) {
+            return;
+        }
+        loop {
+            let message = self.next_message();
+            if mentions(&message) {
+                return;
+            }
+        }
+    }
+}
+
+// 测试失败时也不留下服务进程
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+#[test]
+fn stdio_server_speaks_json_rpc() {
+    let temp_dir = tempdir().expect("无法创建临时目录");
+    let workspace = Workspace::open(temp_dir.path()).expect("无法打开工作区");
+    let category = workspace.create_category("集成").unwrap();
+    let content = json!({
+        "title": "工具",
+        "data": {"data": {"uid": "root", "text": "工具"}, "children": []}
+    });
+    let note = workspace.create_note(&category, None, "工具", &content.to_string()).unwrap();
+
+    let mut server = Server::start(temp_dir.path());
+
+    let listed = server.call(1, "list", json!({}));
+    assert_eq!(listed["result"][0]["path"], json!(note.path));
+
+    let added = server.call(2, "addNode", json!({"path": note.path, "text": "编辑器插件", "tags": ["待办"]}));
+    let node_id = added["result"]["id"].as_str().expect("缺少节点ID").to_string();
+    assert_eq!(added["result"]["parentId"], json!("root"));
+
+    let found = server.call(3, "search", json!({"query": "插件"}));
+    assert_eq!(found["result"][0]["nodeId"], json!(node_id));
+
+    let exported = server.call(4, "export", json!({"path": note.path}));
+    assert_eq!(exported["result"]["content"], json!("# 工具\n\n- 编辑器插件 #待办\n"));
+
+    // 批量请求中的通知没有响应
+    server.send(&json!([
+        {"jsonrpc": "2.0", "id": 5, "method": "read", "params": {"path": note.path}},
+        {"jsonrpc": "2.0", "method": "list"}
+    ]).to_string());
+    let batch = server.response();
+    assert_eq!(batch.as_array().map(Vec::len), Some(1));
+    assert_eq!(batch[0]["result"]["data"]["children"][0]["data"]["uid"], json!(node_id));
+
+    // 错误码
+    assert_eq!(server.call(6, "unknown", json!({}))["error"]["code"], json!(-32601));
+    assert_eq!(server.call(7, "read", json!({}))["error"]["code"], json!(-32602));
+    assert_eq!(server.call(8, "read", json!({"path": "../外部.json"}))["error"]["code"], json!(-32000));
+    server.send("{not json");
+    assert_eq!(server.response()["error"]["code"], json!(-32700));
+
+    // 其他进程修改笔记时发出通知
+    let other = workspace.create_note(&category, None, "外部", &content.to_string()).unwrap();
+    server.wait_for_change(&other.path);
+
+    // 输入结束后服务正常退出
+    drop(server.stdin.take());
+    let status = server.child.wait().unwrap();
+    assert!(status.success());
+}
@@ -23,14 +23,10 @@ const AppContent: React.FC = () => {
     setCurrentView(view);
   };
 
-  // 处理 TODO 状态变更
-  const handleTodoStatusChange = (nodeId: string, completed: boolean) => {
+  // 处理 TODO 状态变更：TodoView 已经在节点树中更新了节点的标签，这里只需刷新笔记数据
+  const handleTodoStatusChange = (_nodeId: string, _completed: boolean) => {
     if (activeNoteData) {
-      const updatedData = { ...activeNoteData };
-      if (updatedData.data[nodeId]) {
-        updatedData.data[nodeId].data.completed = completed;
-        setActiveNoteData(updatedData);
-      }
+      setActiveNoteData({ ...activeNoteData });
     }
   };
 
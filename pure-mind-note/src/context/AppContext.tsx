@@ -215,7 +215,6 @@ export const AppProvider: React.FC<{ children: ReactNode }> = ({ children }) =>
         console.log('[前端] 成功读取笔记数据:', {
           id: noteData.id,
           title: noteData.title,
-          rootId: noteData.rootId,
           dataKeys: Object.keys(noteData.data || {}),
           lastUpdated: noteData.lastUpdated
         });
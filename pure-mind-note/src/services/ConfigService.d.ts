@@ -1,4 +1,6 @@
 import { UserConfig } from '../types';
-export declare function saveConfig(config: UserConfig): Promise<void>;
+import { UnlistenFn } from '@tauri-apps/api/event';
+export declare function saveConfig(config: Partial<UserConfig>): Promise<UserConfig>;
 export declare function loadConfig(): Promise<UserConfig>;
+export declare function onConfigChanged(callback: (config: UserConfig) => void): Promise<UnlistenFn>;
 export declare function testConfigStorage(): Promise<boolean>;
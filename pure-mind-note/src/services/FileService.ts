@@ -1,7 +1,8 @@
-import { NoteMetadata, MindMapData, Category, SubCategory, NotebookType } from '../types';
+import { NoteMetadata, MindMapData, Category, SubCategory, NotebookType, CategoryMetadataUpdate, SortMode } from '../types';
 import { invoke } from '@tauri-apps/api/core';
-import { BaseDirectory, exists, mkdir } from '@tauri-apps/plugin-fs';
 import { appDataDir, normalize } from '@tauri-apps/api/path';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
+
 // 获取应用数据目录
 let cachedDataDir: string | null = null;
 let customWorkspacePath: string | null = null;
@@ -90,14 +91,11 @@ export async function initializeWorkspace(customPath?: string): Promise<void> {
     const dataDir = await getDataDir();
     console.log('工作区实际使用路径:', dataDir);
     
-    // 检查目录是否存在，不存在则创建
-    const dirExists = await exists(dataDir);
-    if (!dirExists) {
-      await mkdir(dataDir, { recursive: true });
-    }
+    // 由后端打开工作区（不存在时创建），之后所有路径都相对于该目录
+    const rootDir = await invoke('open_workspace', { path: dataDir }) as string;
     
     // 记录工作区初始化成功
-    console.log('工作区初始化成功:', dataDir);
+    console.log('工作区初始化成功:', rootDir);
   } catch (error) {
     console.error('工作区初始化失败:', error);
     throw new Error(`无法初始化工作区: ${error}`);
@@ -107,7 +105,6 @@ export async function initializeWorkspace(customPath?: string): Promise<void> {
 // 扫描工作区目录结构
 export async function scanWorkspaceDirectory(): Promise<{categories: Category[], notes: NoteMetadata[]}> {
   try {
-    const dataDir = await getDataDir();
     let categories: Category[] = [];
     let notes: NoteMetadata[] = [];
     
@@ -146,24 +143,30 @@ async function getDefaultCategories(): Promise<Category[]> {
 }
 
 // 获取所有分类
-export async function getAllCategories(): Promise<Category[]> {
+export async function getAllCategories(sort?: SortMode): Promise<Category[]> {
   try {
-    const dataDir = await getDataDir();
-    
     try {
       // 调用后端获取目录结构
-      const rawCategories = await invoke('get_all_categories', { dataDir }) as any[];
+      const rawCategories = await invoke('get_all_categories', { sort: sort ?? null }) as any[];
       
       // 将后端返回的数据转换为前端类型
       const categories: Category[] = rawCategories.map(cat => ({
         id: cat.id,
         name: cat.name,
         createdTime: cat.created_time,
+        description: cat.description ?? undefined,
+        color: cat.color ?? undefined,
+        icon: cat.icon ?? undefined,
+        position: cat.position ?? undefined,
         subCategories: (cat.sub_categories || []).map((sub: any) => ({
           id: sub.id,
           name: sub.name,
           parentId: cat.id,
-          createdTime: sub.created_time
+          createdTime: sub.created_time,
+          description: sub.description ?? undefined,
+          color: sub.color ?? undefined,
+          icon: sub.icon ?? undefined,
+          position: sub.position ?? undefined
         }))
       }));
       
@@ -222,16 +225,17 @@ interface BackendNote {
   category_id: string;
   sub_category_id?: string;
   last_updated: string;
+  position?: number | null;
+  locked?: boolean;
 }
 
 // 获取所有笔记
-export async function getAllNotes(): Promise<NoteMetadata[]> {
+export async function getAllNotes(sort?: SortMode): Promise<NoteMetadata[]> {
   try {
-    const dataDir = await getDataDir();
-    console.log('[FileService] 开始获取笔记列表，数据目录:', dataDir);
+    console.log('[FileService] 开始获取笔记列表');
     
     try {
-      const notes = await invoke('get_all_notes', { dataDir }) as BackendNote[];
+      const notes = await invoke('get_all_notes', { sort: sort ?? null }) as BackendNote[];
       console.log('[FileService] 后端返回笔记数据:', notes.length, '个');
       
       // 将后端返回的数据转换为前端类型
@@ -242,7 +246,9 @@ export async function getAllNotes(): Promise<NoteMetadata[]> {
         categoryId: note.category_id,
         subCategoryId: note.sub_category_id,
         lastUpdated: note.last_updated,
-        type: NotebookType.MINDMAP  // 默认设置为思维导图类型
+        type: NotebookType.MINDMAP,  // 默认设置为思维导图类型
+        position: note.position ?? undefined,
+        locked: note.locked ?? false
       }));
       
       console.log('[FileService] 笔记列表转换完成:', result.length, '个');
@@ -270,57 +276,35 @@ export async function createNote(
       subCategoryId
     });
     
-    const dataDir = await getDataDir();
-    
-    // 生成与后端一致的唯一ID格式
-    const id = subCategoryId 
-      ? `${categoryId}#${subCategoryId}#${title}`
-      : `${categoryId}##${title}`;
-    
-    console.log('[FileService] 生成笔记ID:', id);
-    
-    // 构建文件路径
-    let notePath: string;
-    if (subCategoryId) {
-      notePath = `${dataDir}/${categoryId}/${subCategoryId}/${title}.json`;
-    } else {
-      notePath = `${dataDir}/${categoryId}/${title}.json`;
-    }
-    
-    console.log('[FileService] 笔记文件路径:', notePath);
-    
-    // 创建初始思维导图数据
+    // 创建初始思维导图数据，id 和文件名由后端根据标题生成
     const initialData: MindMapData = {
-      id,
+      id: '',
       title,
-      rootId: 'root',
       lastUpdated: new Date().toISOString(),
       data: {
-        root: {
-          data: {
-            id: 'root',
-            text: title
-          }
-        }
+        data: {
+          uid: 'root',
+          text: title
+        },
+        children: []
       }
     };
     
-    console.log('[FileService] 创建初始数据:', {
-      id: initialData.id,
-      title: initialData.title,
-      rootId: initialData.rootId
-    });
-    
-    // 保存新笔记
-    await saveNote(notePath, initialData);
+    // 由后端校验标题、检查重名并保存新笔记
+    const note = await invoke('create_note', {
+      categoryId,
+      subCategoryId: subCategoryId ?? null,
+      title,
+      content: JSON.stringify(initialData, null, 2)
+    }) as BackendNote;
     
     // 返回笔记元数据
     const metadata = {
-      id,
-      title,
-      path: notePath,
-      categoryId,
-      subCategoryId,
+      id: note.id,
+      title: note.title,
+      path: note.path,
+      categoryId: note.category_id,
+      subCategoryId: note.sub_category_id ?? undefined,
       lastUpdated: initialData.lastUpdated,
       type: NotebookType.MINDMAP  // 设置默认类型为思维导图
     };
@@ -336,8 +320,7 @@ export async function createNote(
 // 创建分类
 export async function createCategory(name: string): Promise<string> {
   try {
-    const dataDir = await getDataDir();
-    return await invoke('create_category', { dataDir, name }) as string;
+    return await invoke('create_category', { name }) as string;
   } catch (error) {
     console.error('创建分类失败:', error);
     throw new Error(`无法创建分类: ${error}`);
@@ -347,19 +330,69 @@ export async function createCategory(name: string): Promise<string> {
 // 创建子分类
 export async function createSubcategory(categoryId: string, name: string): Promise<string> {
   try {
-    const dataDir = await getDataDir();
-    return await invoke('create_subcategory', { dataDir, categoryId, name }) as string;
+    return await invoke('create_subcategory', { categoryId, name }) as string;
   } catch (error) {
     console.error('创建子分类失败:', error);
     throw new Error(`无法创建子分类: ${error}`);
   }
 }
 
+// 更新分类或子分类的元数据
+export async function updateCategoryMetadata(
+  categoryId: string,
+  update: CategoryMetadataUpdate,
+  subCategoryId?: string
+): Promise<void> {
+  try {
+    await invoke('update_category_metadata', {
+      categoryId,
+      subCategoryId: subCategoryId ?? null,
+      update
+    });
+  } catch (error) {
+    console.error('更新分类信息失败:', error);
+    throw new Error(`无法更新分类信息: ${error}`);
+  }
+}
+
+// 保存拖拽后的分类顺序
+export async function reorderCategories(orderedIds: string[]): Promise<void> {
+  try {
+    await invoke('reorder_categories', { orderedIds });
+  } catch (error) {
+    console.error('保存分类顺序失败:', error);
+    throw new Error(`无法保存分类顺序: ${error}`);
+  }
+}
+
+// 保存拖拽后的子分类顺序
+export async function reorderSubcategories(categoryId: string, orderedIds: string[]): Promise<void> {
+  try {
+    await invoke('reorder_subcategories', { categoryId, orderedIds });
+  } catch (error) {
+    console.error('保存子分类顺序失败:', error);
+    throw new Error(`无法保存子分类顺序: ${error}`);
+  }
+}
+
+// 保存拖拽后的笔记顺序
+export async function reorderNotes(
+  categoryId: string,
+  orderedNoteIds: string[],
+  subCategoryId?: string
+): Promise<void> {
+  try {
+    await invoke('reorder_notes', { categoryId, subCategoryId: subCategoryId ?? null, orderedNoteIds });
+  } catch (error) {
+    console.error('保存笔记顺序失败:', error);
+    throw new Error(`无法保存笔记顺序: ${error}`);
+  }
+}
+
 // 删除分类
 export async function deleteCategory(categoryId: string): Promise<void> {
   try {
-    const dataDir = await getDataDir();
-    await invoke('delete_category', { dataDir, categoryId });
+    await invoke('delete_category', { categoryId });
   } catch (error) {
     console.error('删除分类失败:', error);
     throw new Error(`无法删除分类: ${error}`);
@@ -369,8 +402,7 @@ export async function deleteCategory(categoryId: string): Promise<void> {
 // 删除子分类
 export async function deleteSubcategory(categoryId: string, subCategoryId: string): Promise<void> {
   try {
-    const dataDir = await getDataDir();
-    await invoke('delete_subcategory', { dataDir, categoryId, subCategoryId });
+    await invoke('delete_subcategory', { categoryId, subCategoryId });
   } catch (error) {
     console.error('删除子分类失败:', error);
     throw new Error(`无法删除子分类: ${error}`);
@@ -380,10 +412,829 @@ export async function deleteSubcategory(categoryId: string, subCategoryId: strin
 // 删除笔记
 export async function deleteNote(noteId: string): Promise<void> {
   try {
-    const dataDir = await getDataDir();
-    await invoke('delete_note', { dataDir, noteId });
+    await invoke('delete_note', { noteId });
   } catch (error) {
     console.error('删除笔记失败:', error);
     throw new Error(`无法删除笔记: ${error}`);
   }
-} 
\ No newline at end of file
+} 
+// 工作区加密状态
+export interface EncryptionStatus {
+  enabled: boolean;
+  unlocked: boolean;
+  autoLockMinutes?: number | null;
+}
+
+// 获取工作区加密状态
+export async function getEncryptionStatus(): Promise<EncryptionStatus> {
+  return await invoke('get_encryption_status') as EncryptionStatus;
+}
+
+// 启用工作区加密，autoLockMinutes 为 0 表示不自动锁定；
+// 启用前的明文版本会留在历史记录中，purgeHistory 为 true 时一并清除
+export async function enableEncryption(passphrase: string, autoLockMinutes?: number, purgeHistory?: boolean): Promise<void> {
+  try {
+    await invoke('enable_encryption', { passphrase, autoLockMinutes: autoLockMinutes ?? null, purgeHistory: purgeHistory ?? null });
+  } catch (error) {
+    console.error('启用加密失败:', error);
+    throw new Error(`无法启用加密: ${error}`);
+  }
+}
+
+// 用口令解锁工作区
+export async function unlockWorkspace(passphrase: string): Promise<void> {
+  try {
+    await invoke('unlock_workspace', { passphrase });
+  } catch (error) {
+    console.error('解锁工作区失败:', error);
+    throw new Error(`无法解锁工作区: ${error}`);
+  }
+}
+
+// 立即锁定工作区
+export async function lockWorkspace(): Promise<void> {
+  await invoke('lock_workspace');
+}
+
+// 更换口令并重新加密所有笔记
+export async function changePassphrase(oldPassphrase: string, newPassphrase: string): Promise<void> {
+  try {
+    await invoke('change_passphrase', { oldPassphrase, newPassphrase });
+  } catch (error) {
+    console.error('更换口令失败:', error);
+    throw new Error(`无法更换口令: ${error}`);
+  }
+}
+
+// 用密码加锁笔记；加锁前的明文版本会留在历史记录中，purgeHistory 为 true 时一并清除
+export async function lockNote(path: string, password: string, purgeHistory?: boolean): Promise<void> {
+  try {
+    await invoke('lock_note', { path, password, purgeHistory: purgeHistory ?? null });
+  } catch (error) {
+    console.error('加锁笔记失败:', error);
+    throw new Error(`无法加锁笔记: ${error}`);
+  }
+}
+
+// 解除笔记的密码锁，返回笔记内容
+export async function unlockNote(path: string, password: string): Promise<string> {
+  try {
+    return await invoke('unlock_note', { path, password }) as string;
+  } catch (error) {
+    console.error('解锁笔记失败:', error);
+    throw new Error(`无法解锁笔记: ${error}`);
+  }
+}
+
+// 用密码读取加锁笔记，笔记保持加锁
+export async function readLockedNote(path: string, password: string): Promise<string> {
+  try {
+    return await invoke('read_locked_note', { path, password }) as string;
+  } catch (error) {
+    console.error('读取加锁笔记失败:', error);
+    throw new Error(`无法读取加锁笔记: ${error}`);
+  }
+}
+
+// 保存加锁笔记，保存后仍保持加锁
+export async function saveLockedNote(path: string, password: string, content: string): Promise<void> {
+  try {
+    await invoke('save_locked_note', { path, password, content });
+  } catch (error) {
+    console.error('保存加锁笔记失败:', error);
+    throw new Error(`无法保存加锁笔记: ${error}`);
+  }
+}
+
+// git 历史中的一次提交
+export interface HistoryEntry {
+  id: string;
+  shortId: string;
+  summary: string;
+  message: string;
+  author: string;
+  time: string;
+}
+
+// git 历史的开启状态
+export interface HistoryStatus {
+  enabled: boolean;
+  pendingChanges: number;
+}
+
+// 在工作区中启用 git 历史
+export async function enableHistory(): Promise<void> {
+  try {
+    await invoke('enable_history');
+  } catch (error) {
+    console.error('启用历史记录失败:', error);
+    throw new Error(`无法启用历史记录: ${error}`);
+  }
+}
+
+// 获取历史记录状态
+export async function getHistoryStatus(): Promise<HistoryStatus> {
+  return await invoke('get_history_status') as HistoryStatus;
+}
+
+// 获取提交历史，传入 path 时只返回修改过该笔记的提交
+export async function getHistoryLog(path?: string, limit?: number): Promise<HistoryEntry[]> {
+  try {
+    return await invoke('get_history_log', { path: path ?? null, limit: limit ?? null }) as HistoryEntry[];
+  } catch (error) {
+    console.error('获取历史记录失败:', error);
+    throw new Error(`无法获取历史记录: ${error}`);
+  }
+}
+
+// 查看笔记的历史版本，笔记已加锁时需要密码
+export async function readNoteVersion(path: string, commitId: string, password?: string): Promise<string> {
+  try {
+    return await invoke('read_note_version', { path, commitId, password: password ?? null }) as string;
+  } catch (error) {
+    console.error('读取历史版本失败:', error);
+    throw new Error(`无法读取历史版本: ${error}`);
+  }
+}
+
+// 将笔记恢复到历史版本，笔记已加锁时需要密码，恢复后仍保持加锁
+export async function checkoutNoteVersion(path: string, commitId: string, password?: string): Promise<void> {
+  try {
+    await invoke('checkout_note_version', { path, commitId, password: password ?? null });
+  } catch (error) {
+    console.error('恢复历史版本失败:', error);
+    throw new Error(`无法恢复历史版本: ${error}`);
+  }
+}
+
+// 清除历史记录，只保留当前版本（用于移除加密或加锁前的明文版本）
+export async function purgeHistory(): Promise<void> {
+  try {
+    await invoke('purge_history');
+  } catch (error) {
+    console.error('清除历史记录失败:', error);
+    throw new Error(`无法清除历史记录: ${error}`);
+  }
+}
+
+// 同步时两边都修改过的条目
+export interface SyncConflict {
+  path: string;
+  conflictCopy?: string | null;
+}
+
+// 一次同步的结果汇总
+export interface SyncSummary {
+  target: string;
+  pulled: string[];
+  pushed: string[];
+  deletedInWorkspace: string[];
+  deletedInTarget: string[];
+  conflicts: SyncConflict[];
+}
+
+// 与另一个本地目录（如U盘、NAS挂载目录）双向同步，未指定时使用配置中的同步目录
+export async function syncFolder(target?: string): Promise<SyncSummary> {
+  try {
+    return await invoke('sync_folder', { target: target ?? null }) as SyncSummary;
+  } catch (error) {
+    console.error('同步失败:', error);
+    throw new Error(`无法同步: ${error}`);
+  }
+}
+
+// 合并冲突的类型
+export type MergeConflictKind = 'field' | 'deleteModify' | 'order' | 'move' | 'orphan';
+
+// 合并时某个节点上的冲突，nodeId 为空时是笔记本身的字段
+export interface NodeConflict {
+  nodeId?: string | null;
+  field: string;
+  kind: MergeConflictKind;
+  base?: unknown;
+  ours?: unknown;
+  theirs?: unknown;
+}
+
+// 合并结果
+export interface MergeOutcome {
+  merged: string;
+  conflicts: NodeConflict[];
+}
+
+// 按节点结构三方合并同一篇笔记的两个版本，没有共同祖先时 base 传 null
+export async function mergeNoteVersions(base: string | null, ours: string, theirs: string): Promise<MergeOutcome> {
+  try {
+    return await invoke('merge_note_versions', { base, ours, theirs }) as MergeOutcome;
+  } catch (error) {
+    console.error('合并笔记失败:', error);
+    throw new Error(`无法合并笔记: ${error}`);
+  }
+}
+
+// 自动备份设置
+export interface BackupSettings {
+  enabled: boolean;
+  folder?: string | null;
+  intervalHours: number;
+  keepDaily: number;
+  keepWeekly: number;
+  keepMonthly: number;
+  lastBackup?: string | null;
+}
+
+// 备份目录中的一个备份
+export interface BackupInfo {
+  fileName: string;
+  path: string;
+  created: string;
+  size: number;
+}
+
+// 获取备份设置
+export async function getBackupSettings(): Promise<BackupSettings> {
+  return await invoke('get_backup_settings') as BackupSettings;
+}
+
+// 更新备份设置
+export async function updateBackupSettings(settings: BackupSettings): Promise<BackupSettings> {
+  try {
+    return await invoke('update_backup_settings', { settings }) as BackupSettings;
+  } catch (error) {
+    console.error('保存备份设置失败:', error);
+    throw new Error(`无法保存备份设置: ${error}`);
+  }
+}
+
+// 立即备份
+export async function createBackup(): Promise<BackupInfo> {
+  try {
+    return await invoke('create_backup') as BackupInfo;
+  } catch (error) {
+    console.error('备份失败:', error);
+    throw new Error(`无法备份: ${error}`);
+  }
+}
+
+// 列出备份，最新的在前
+export async function listBackups(): Promise<BackupInfo[]> {
+  try {
+    return await invoke('list_backups') as BackupInfo[];
+  } catch (error) {
+    console.error('获取备份列表失败:', error);
+    throw new Error(`无法获取备份列表: ${error}`);
+  }
+}
+
+// 从备份恢复：传入 target 时恢复到新目录，否则覆盖当前工作区；返回恢复后的目录
+export async function restoreBackup(fileName: string, target?: string): Promise<string> {
+  try {
+    return await invoke('restore_backup', { fileName, target: target ?? null }) as string;
+  } catch (error) {
+    console.error('恢复备份失败:', error);
+    throw new Error(`无法恢复备份: ${error}`);
+  }
+}
+
+// 归档清单
+export interface ArchiveManifest {
+  format: string;
+  version: number;
+  appVersion: string;
+  created: string;
+  notes: { path: string; title: string }[];
+  files: { path: string; size: number }[];
+}
+
+// 导出范围，都为空时导出整个工作区
+export interface ArchiveSelection {
+  categories?: string[];
+  notes?: string[];
+}
+
+// 导入时与已有文件重名的处理方式
+export type CollisionStrategy = 'rename' | 'overwrite' | 'skip';
+
+// 导入结果汇总
+export interface ImportSummary {
+  imported: string[];
+  renamed: { from: string; to: string }[];
+  overwritten: string[];
+  skipped: string[];
+}
+
+// 导出为 .pmn 归档
+export async function exportArchive(destination: string, selection?: ArchiveSelection): Promise<ArchiveManifest> {
+  try {
+    return await invoke('export_archive', { destination, selection: selection ?? null }) as ArchiveManifest;
+  } catch (error) {
+    console.error('导出归档失败:', error);
+    throw new Error(`无法导出归档: ${error}`);
+  }
+}
+
+// 将 .pmn 归档合并到当前工作区
+export async function importArchive(source: string, strategy?: CollisionStrategy): Promise<ImportSummary> {
+  try {
+    return await invoke('import_archive', { source, strategy: strategy ?? null }) as ImportSummary;
+  } catch (error) {
+    console.error('导入归档失败:', error);
+    throw new Error(`无法导入归档: ${error}`);
+  }
+}
+
+// 已登记的工作区
+export interface WorkspaceEntry {
+  id: string;
+  name: string;
+  path: string;
+  lastOpened: string | null;
+  settings: Record<string, unknown>;
+}
+
+// 列出已登记的工作区，最近打开的在前
+export async function listWorkspaces(): Promise<WorkspaceEntry[]> {
+  return await invoke('list_workspaces') as WorkspaceEntry[];
+}
+
+// 获取当前工作区的登记信息
+export async function getActiveWorkspace(): Promise<WorkspaceEntry | null> {
+  return await invoke('get_active_workspace') as WorkspaceEntry | null;
+}
+
+// 登记新的工作区，未指定名称时使用目录名
+export async function addWorkspace(path: string, name?: string): Promise<WorkspaceEntry> {
+  try {
+    return await invoke('add_workspace', { path, name: name ?? null }) as WorkspaceEntry;
+  } catch (error) {
+    console.error('登记工作区失败:', error);
+    throw new Error(`无法登记工作区: ${error}`);
+  }
+}
+
+// 从列表中移除工作区（不删除文件）
+export async function removeWorkspace(id: string): Promise<void> {
+  try {
+    await invoke('remove_workspace', { id });
+  } catch (error) {
+    console.error('移除工作区失败:', error);
+    throw new Error(`无法移除工作区: ${error}`);
+  }
+}
+
+// 修改工作区名称
+export async function renameWorkspace(id: string, name: string): Promise<WorkspaceEntry> {
+  try {
+    return await invoke('rename_workspace', { id, name }) as WorkspaceEntry;
+  } catch (error) {
+    console.error('重命名工作区失败:', error);
+    throw new Error(`无法重命名工作区: ${error}`);
+  }
+}
+
+// 合并工作区设置，值为 null 的键会被删除
+export async function updateWorkspaceSettings(id: string, settings: Record<string, unknown>): Promise<WorkspaceEntry> {
+  try {
+    return await invoke('update_workspace_settings', { id, settings }) as WorkspaceEntry;
+  } catch (error) {
+    console.error('更新工作区设置失败:', error);
+    throw new Error(`无法更新工作区设置: ${error}`);
+  }
+}
+
+// 切换到另一个已登记的工作区
+export async function switchWorkspace(id: string): Promise<WorkspaceEntry> {
+  try {
+    const entry = await invoke('switch_workspace', { id }) as WorkspaceEntry;
+    setCustomWorkspacePath(entry.path);
+    return entry;
+  } catch (error) {
+    console.error('切换工作区失败:', error);
+    throw new Error(`无法切换工作区: ${error}`);
+  }
+}
+
+// 笔记格式批量迁移的结果
+export interface MigrationReport {
+  dryRun: boolean;
+  targetVersion: number;
+  migrated: { path: string; fromVersion: number }[];
+  upToDate: number;
+  skipped: { path: string; reason: string }[];
+}
+
+// 将工作区中的笔记迁移到当前格式版本，默认只预览
+export async function migrateWorkspace(dryRun = true): Promise<MigrationReport> {
+  try {
+    return await invoke('migrate_workspace', { dryRun }) as MigrationReport;
+  } catch (error) {
+    console.error('迁移笔记格式失败:', error);
+    throw new Error(`无法迁移笔记格式: ${error}`);
+  }
+}
+
+// 工作区检查发现的问题类型
+export type IssueKind =
+  | 'unreadableDirectory'
+  | 'unreadableNote'
+  | 'invalidJson'
+  | 'missingRoot'
+  | 'danglingChild'
+  | 'unreachableNode'
+  | 'invalidNode'
+  | 'missingNodeId'
+  | 'duplicateNodeId'
+  | 'duplicateNoteId'
+  | 'strayTempFile';
+
+// 工作区检查发现的一个问题
+export interface WorkspaceIssue {
+  kind: IssueKind;
+  path: string;
+  nodeId: string | null;
+  detail: string;
+  repaired: boolean;
+}
+
+// 工作区检查结果
+export interface CheckReport {
+  notesChecked: number;
+  lockedNotes: number;
+  issues: WorkspaceIssue[];
+}
+
+// 检查工作区完整性（只读）
+export async function checkWorkspace(): Promise<CheckReport> {
+  try {
+    return await invoke('check_workspace') as CheckReport;
+  } catch (error) {
+    console.error('检查工作区失败:', error);
+    throw new Error(`无法检查工作区: ${error}`);
+  }
+}
+
+// 检查并修复工作区中可以安全修复的问题
+export async function repairWorkspace(): Promise<CheckReport> {
+  try {
+    return await invoke('repair_workspace') as CheckReport;
+  } catch (error) {
+    console.error('修复工作区失败:', error);
+    throw new Error(`无法修复工作区: ${error}`);
+  }
+}
+
+// 可以恢复的草稿
+export interface RecoverableDraft {
+  path: string;
+  title: string | null;
+  savedAt: string;
+  noteModified: string | null;
+}
+
+// 记录尚未保存的编辑内容，崩溃后可以恢复
+export async function saveDraft(path: string, data: MindMapData): Promise<void> {
+  try {
+    await invoke('save_draft', { path, content: JSON.stringify(data) });
+  } catch (error) {
+    console.error('保存草稿失败:', error);
+    throw new Error(`无法保存草稿: ${error}`);
+  }
+}
+
+// 列出比已保存内容更新的草稿，启动时用于提示恢复
+export async function listRecoverableDrafts(): Promise<RecoverableDraft[]> {
+  try {
+    return await invoke('list_recoverable_drafts') as RecoverableDraft[];
+  } catch (error) {
+    console.error('获取草稿失败:', error);
+    throw new Error(`无法获取草稿: ${error}`);
+  }
+}
+
+// 用草稿内容恢复笔记
+export async function recoverDraft(path: string): Promise<MindMapData> {
+  try {
+    const content = await invoke('recover_draft', { path }) as string;
+    return JSON.parse(content) as MindMapData;
+  } catch (error) {
+    console.error('恢复草稿失败:', error);
+    throw new Error(`无法恢复草稿: ${error}`);
+  }
+}
+
+// 放弃草稿
+export async function discardDraft(path: string): Promise<void> {
+  try {
+    await invoke('discard_draft', { path });
+  } catch (error) {
+    console.error('放弃草稿失败:', error);
+    throw new Error(`无法放弃草稿: ${error}`);
+  }
+}
+
+// RFC 6902 JSON Patch 操作
+export type JsonPatchOperation =
+  | { op: 'add' | 'replace' | 'test'; path: string; value: unknown }
+  | { op: 'remove'; path: string }
+  | { op: 'move' | 'copy'; from: string; path: string };
+
+// 读取笔记及其版本号，版本号用于后续的增量更新
+export async function readNoteWithRevision(path: string): Promise<{ data: MindMapData; revision: string }> {
+  try {
+    const result = await invoke('read_note_with_revision', { path }) as { content: string; revision: string };
+    return { data: JSON.parse(result.content) as MindMapData, revision: result.revision };
+  } catch (error) {
+    console.error('读取笔记失败:', error);
+    throw new Error(`无法读取笔记: ${error}`);
+  }
+}
+
+// 在 baseRevision 的基础上增量更新笔记，返回新的版本号；版本过期时错误信息以"版本冲突"开头
+export async function applyNotePatch(path: string, patch: JsonPatchOperation[], baseRevision: string): Promise<string> {
+  try {
+    return await invoke('apply_note_patch', { path, patch, baseRevision }) as string;
+  } catch (error) {
+    console.error('增量更新笔记失败:', error);
+    throw new Error(`无法更新笔记: ${error}`);
+  }
+}
+
+// 笔记中的一个节点
+export interface MindMapNodeInfo {
+  id: string;
+  parentId: string | null;
+  data: { id: string; text: string; tag?: string[]; [key: string]: unknown };
+  children: string[];
+}
+
+// 读取一个节点
+export async function getNode(path: string, nodeId: string): Promise<MindMapNodeInfo> {
+  try {
+    return await invoke('get_node', { path, nodeId }) as MindMapNodeInfo;
+  } catch (error) {
+    console.error('读取节点失败:', error);
+    throw new Error(`无法读取节点: ${error}`);
+  }
+}
+
+// 在 parentId 下添加节点，index 省略时放在最后；节点ID由后端生成
+export async function addNode(
+  path: string,
+  parentId: string,
+  data: { text: string; [key: string]: unknown },
+  index?: number
+): Promise<MindMapNodeInfo> {
+  try {
+    return await invoke('add_node', { path, parentId, data, index: index ?? null }) as MindMapNodeInfo;
+  } catch (error) {
+    console.error('添加节点失败:', error);
+    throw new Error(`无法添加节点: ${error}`);
+  }
+}
+
+// 修改节点数据，值为 null 的字段会被删除
+export async function updateNode(path: string, nodeId: string, changes: Record<string, unknown>): Promise<MindMapNodeInfo> {
+  try {
+    return await invoke('update_node', { path, nodeId, changes }) as MindMapNodeInfo;
+  } catch (error) {
+    console.error('修改节点失败:', error);
+    throw new Error(`无法修改节点: ${error}`);
+  }
+}
+
+// 删除节点及其后代，返回被删除的节点ID
+export async function deleteNode(path: string, nodeId: string): Promise<string[]> {
+  try {
+    return await invoke('delete_node', { path, nodeId }) as string[];
+  } catch (error) {
+    console.error('删除节点失败:', error);
+    throw new Error(`无法删除节点: ${error}`);
+  }
+}
+
+// 把节点移动到 parentId 下的 index 位置
+export async function moveNode(path: string, nodeId: string, parentId: string, index?: number): Promise<MindMapNodeInfo> {
+  try {
+    return await invoke('move_node', { path, nodeId, parentId, index: index ?? null }) as MindMapNodeInfo;
+  } catch (error) {
+    console.error('移动节点失败:', error);
+    throw new Error(`无法移动节点: ${error}`);
+  }
+}
+
+// 搜索命中的节点
+export interface SearchHit {
+  noteId: string;
+  noteTitle: string;
+  path: string;
+  nodeId: string;
+  text: string;
+  tags: string[];
+}
+
+// 在节点文字和标签中搜索，多个关键词用空格分隔，加锁的笔记不参与搜索
+export async function searchNotes(query: string): Promise<SearchHit[]> {
+  try {
+    return await invoke('search_notes', { query }) as SearchHit[];
+  } catch (error) {
+    console.error('搜索失败:', error);
+    throw new Error(`无法搜索: ${error}`);
+  }
+}
+
+// 导出笔记为 Markdown 大纲
+export async function exportMarkdown(path: string): Promise<string> {
+  try {
+    return await invoke('export_markdown', { path }) as string;
+  } catch (error) {
+    console.error('导出 Markdown 失败:', error);
+    throw new Error(`无法导出 Markdown: ${error}`);
+  }
+}
+
+// 通过本地接口修改笔记、或笔记在应用之外被修改（同步工具、其他编辑器）后后端发出的事件，载荷为笔记路径
+const NOTES_CHANGED_EVENT = 'notes-changed';
+
+// 监听外部（本地接口、文件监视）对笔记的修改，返回取消监听的函数
+export async function onNotesChanged(callback: (path: string) => void): Promise<UnlistenFn> {
+  return await listen<string>(NOTES_CHANGED_EVENT, event => callback(event.payload));
+}
+
+// 把节点及其后代移动到另一篇笔记，parentId 省略时放在目标笔记的根节点下
+export async function moveNodeToNote(
+  path: string,
+  nodeId: string,
+  targetPath: string,
+  parentId?: string,
+  index?: number
+): Promise<MindMapNodeInfo> {
+  try {
+    return await invoke('move_node_to_note', {
+      path,
+      nodeId,
+      targetPath,
+      parentId: parentId ?? null,
+      index: index ?? null
+    }) as MindMapNodeInfo;
+  } catch (error) {
+    console.error('移动节点失败:', error);
+    throw new Error(`无法移动节点: ${error}`);
+  }
+}
+
+// 快速记录到收集箱，收集箱笔记不存在时自动创建
+export async function appendToInbox(text: string, tags?: string[]): Promise<MindMapNodeInfo> {
+  try {
+    return await invoke('append_to_inbox', { text, tags: tags ?? null }) as MindMapNodeInfo;
+  } catch (error) {
+    console.error('记录到收集箱失败:', error);
+    throw new Error(`无法记录到收集箱: ${error}`);
+  }
+}
+
+// 收集箱中待整理的记录
+export async function listInbox(): Promise<MindMapNodeInfo[]> {
+  try {
+    return await invoke('list_inbox') as MindMapNodeInfo[];
+  } catch (error) {
+    console.error('读取收集箱失败:', error);
+    throw new Error(`无法读取收集箱: ${error}`);
+  }
+}
+
+// 收集箱中一条记录的去向
+export interface InboxMove {
+  nodeId: string;
+  targetPath: string;
+  parentId?: string | null;
+}
+
+export interface InboxMoveResult {
+  nodeId: string;
+  targetPath: string;
+  node: MindMapNodeInfo | null;
+  error: string | null;
+}
+
+// 把收集箱中的记录移动到目标笔记，失败的记录留在收集箱中
+export async function processInbox(moves: InboxMove[]): Promise<InboxMoveResult[]> {
+  try {
+    return await invoke('process_inbox', { moves }) as InboxMoveResult[];
+  } catch (error) {
+    console.error('整理收集箱失败:', error);
+    throw new Error(`无法整理收集箱: ${error}`);
+  }
+}
+
+// 获取收集箱笔记的设置，path 为空表示使用默认位置
+export async function getInboxSettings(): Promise<{ path: string | null }> {
+  try {
+    return await invoke('get_inbox_settings') as { path: string | null };
+  } catch (error) {
+    console.error('获取收集箱设置失败:', error);
+    throw new Error(`无法获取收集箱设置: ${error}`);
+  }
+}
+
+// 指定收集箱笔记，传入 null 恢复默认位置
+export async function setInboxNote(path: string | null): Promise<{ path: string | null }> {
+  try {
+    return await invoke('set_inbox_note', { path }) as { path: string | null };
+  } catch (error) {
+    console.error('设置收集箱失败:', error);
+    throw new Error(`无法设置收集箱: ${error}`);
+  }
+}
+
+// 工作区模板目录中的模板
+export interface TemplateInfo {
+  file: string;
+  name: string;
+  format: 'mindmap' | 'markdown';
+}
+
+// 列出可用的模板
+export async function listTemplates(): Promise<TemplateInfo[]> {
+  try {
+    return await invoke('list_templates') as TemplateInfo[];
+  } catch (error) {
+    console.error('获取模板列表失败:', error);
+    throw new Error(`无法获取模板列表: ${error}`);
+  }
+}
+
+// 用模板创建笔记，模板中的 {{date}}、{{title}}、{{category}} 会被替换
+export async function createNoteFromTemplate(
+  template: string,
+  categoryId: string,
+  title: string,
+  subCategoryId?: string
+): Promise<NoteMetadata> {
+  try {
+    const note = await invoke('create_note_from_template', {
+      template,
+      categoryId,
+      subCategoryId: subCategoryId ?? null,
+      title
+    }) as BackendNote;
+    return {
+      id: note.id,
+      title: note.title,
+      path: note.path,
+      categoryId: note.category_id,
+      subCategoryId: note.sub_category_id ?? undefined,
+      lastUpdated: new Date().toISOString(),
+      type: NotebookType.MINDMAP
+    };
+  } catch (error) {
+    console.error('用模板创建笔记失败:', error);
+    throw new Error(`无法用模板创建笔记: ${error}`);
+  }
+}
+
+// 把笔记保存为模板
+export async function saveNoteAsTemplate(path: string, name: string): Promise<TemplateInfo> {
+  try {
+    return await invoke('save_note_as_template', { path, name }) as TemplateInfo;
+  } catch (error) {
+    console.error('保存模板失败:', error);
+    throw new Error(`无法保存模板: ${error}`);
+  }
+}
+
+// 指向某篇笔记的节点
+export interface Backlink {
+  noteId: string;
+  noteTitle: string;
+  path: string;
+  nodeId: string;
+  text: string;
+  // 链接指向的节点，指向整篇笔记时为 null
+  targetNodeId: string | null;
+}
+
+// 整个工作区的笔记关系图
+export interface LinkGraph {
+  nodes: { id: string; title: string; path: string; categoryId: string }[];
+  edges: { source: string; target: string; count: number }[];
+}
+
+// 获取通过 [[笔记标题]] 或 link 字段指向笔记的节点
+export async function getBacklinks(noteId: string): Promise<Backlink[]> {
+  try {
+    return await invoke('get_backlinks', { noteId }) as Backlink[];
+  } catch (error) {
+    console.error('获取反向链接失败:', error);
+    throw new Error(`无法获取反向链接: ${error}`);
+  }
+}
+
+// 获取笔记关系图
+export async function getLinkGraph(): Promise<LinkGraph> {
+  try {
+    return await invoke('get_link_graph') as LinkGraph;
+  } catch (error) {
+    console.error('获取笔记关系图失败:', error);
+    throw new Error(`无法获取笔记关系图: ${error}`);
+  }
+}
@@ -22,15 +22,13 @@ export interface SubCategory {
 export interface MindMapData {
     id: string;
     title: string;
-    rootId: string;
     lastUpdated: string;
     theme?: string;
-    data: {
-        [key: string]: {
-            data: MindMapNodeData;
-            children?: string[];
-        };
-    };
+    data: MindMapNode;
+}
+export interface MindMapNode {
+    data: MindMapNodeData;
+    children?: MindMapNode[];
 }
 export interface ThemeConfig {
     mode: 'light' | 'dark' | 'system';
@@ -57,7 +55,8 @@ export declare enum NodeTag {
 export declare const NODE_TAG_LABELS: Record<NodeTag, string>;
 export declare const NODE_TAG_COLORS: Record<NodeTag, string>;
 export interface MindMapNodeData {
-    id: string;
+    uid?: string;
+    id?: string;
     text: string;
     tags?: NodeTag[];
     [key: string]: any;
@@ -74,14 +73,8 @@ export interface BaseNotebook {
 }
 export interface MindMapNotebook extends BaseNotebook {
     type: NotebookType.MINDMAP;
-    rootId: string;
     theme?: string;
-    data: {
-        [key: string]: {
-            data: MindMapNodeData;
-            children?: string[];
-        };
-    };
+    data: MindMapNode;
 }
 export interface MarkdownNotebook extends BaseNotebook {
     type: NotebookType.MARKDOWN;
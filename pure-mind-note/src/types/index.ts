@@ -7,14 +7,24 @@ export interface NoteMetadata {
   subCategoryId?: string;
   lastUpdated: string;
   type: NotebookType;
+  position?: number;
+  // 是否单独加了密码锁
+  locked?: boolean;
 }
 
+// 列表排序方式
+export type SortMode = 'title' | 'modified' | 'created' | 'custom';
+
 // 分类类型
 export interface Category {
   id: string;
   name: string;
   subCategories: SubCategory[];
   createdTime?: string;
+  description?: string;
+  color?: string;
+  icon?: string;
+  position?: number;
 }
 
 // 子分类类型
@@ -23,21 +33,37 @@ export interface SubCategory {
   name: string;
   parentId: string;
   createdTime?: string;
+  description?: string;
+  color?: string;
+  icon?: string;
+  position?: number;
+}
+
+// 分类元数据的部分更新，空字符串表示清除该字段
+export interface CategoryMetadataUpdate {
+  name?: string;
+  description?: string;
+  color?: string;
+  icon?: string;
+  position?: number;
 }
 
 // 脑图数据类型
 export interface MindMapData {
+  // 笔记格式版本，由后端在读取和保存时维护
+  formatVersion?: number;
   id: string;
   title: string;
-  rootId: string;
   lastUpdated: string;
   theme?: string;
-  data: {
-    [key: string]: {
-      data: MindMapNodeData;
-      children?: string[];
-    };
-  };
+  // 脑图组件 getData() 得到的嵌套节点树
+  data: MindMapNode;
+}
+
+// 脑图中的一个节点及其子节点
+export interface MindMapNode {
+  data: MindMapNodeData;
+  children?: MindMapNode[];
 }
 
 // 主题配置类型
@@ -48,8 +74,14 @@ export interface ThemeConfig {
 
 // 用户配置类型
 export interface UserConfig {
+  // 配置格式版本，由后端维护
+  version?: number;
   workspacePath: string;
+  locale?: string;
   theme?: ThemeConfig;
+  sync?: { folder: string | null };
+  // 本地 HTTP 接口，只监听 127.0.0.1，请求需携带 Bearer 令牌
+  api?: { enabled: boolean; port: number; token: string };
   [key: string]: any; // 允许其他字段存在，用于测试和未来扩展
 }
 
@@ -90,7 +122,9 @@ export const NODE_TAG_COLORS: Record<NodeTag, string> = {
 
 // 扩展脑图数据类型中的节点数据，添加标签字段
 export interface MindMapNodeData {
-  id: string;
+  // 节点ID，由脑图组件生成；旧格式的笔记使用 id
+  uid?: string;
+  id?: string;
   text: string;
   tags?: NodeTag[];
   [key: string]: any;
@@ -113,14 +147,8 @@ export interface BaseNotebook {
 // 思维导图记事本
 export interface MindMapNotebook extends BaseNotebook {
   type: NotebookType.MINDMAP;
-  rootId: string;
   theme?: string;
-  data: {
-    [key: string]: {
-      data: MindMapNodeData;
-      children?: string[];
-    };
-  };
+  data: MindMapNode;
 }
 
 // Markdown记事本